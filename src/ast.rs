@@ -1,90 +1,194 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line : usize,
+    pub column : usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub file : usize, // The loader numbers files in the order it reads them, starting at 0 for the main file
+    pub start : Position,
+    pub end : Position,
+}
 
-#[derive(Debug)]
+// The text of a '#' comment without the '#'.  Doc comments ('##') still start with their second '#'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text : String,
+    pub span : Span,
+}
+
+#[derive(Debug, Clone)]
 pub enum Ast {
-    FunDef { name : String, params : Vec<FunParam>, return_type : Type, expr : Expr },
-    DataDef { name : String, cons_defs : Vec<ConsDef> },
+    FunDef { name : String, params : Vec<FunParam>, return_type : Type, expr : Expr, doc : Option<String>, span : Span },
+    LetDef { name : String, t : Option<Type>, expr : Expr, doc : Option<String>, span : Span }, // Evaluated once before main
+    DataDef { name : String, type_params : Vec<String>, cons_defs : Vec<ConsDef>, doc : Option<String>, span : Span },
+    Import { path : String, alias : String, span : Span },
+    PatternDef { name : String, pattern : NamedPattern, span : Span },
+}
+
+#[derive(Debug, Clone)]
+pub enum NamedPattern {
+    Path(Vec<PathPattern>),
+    Array(Vec<ArrayPattern>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ConsDef {
     pub name : String,
     pub params : Vec<Type>,
+    pub fields : Option<Vec<String>>, // Record constructors name each of their params
+    pub doc : Option<String>,
+    pub span : Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FunParam {
+    pub pattern : StandardPattern, // Anything other than a variable is matched against the argument
+    pub t : Option<Type>,
+}
+
+// A 'let rec' or 'where' binding; the value is always a lambda.
+#[derive(Debug, Clone)]
+pub struct Binding {
     pub name : String,
     pub t : Option<Type>,
+    pub value : Expr,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Case {
     pub pattern : StandardPattern,
     pub expr : Expr,
 }
 
-#[derive(Debug)]
-pub enum Expr {
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind : ExprKind,
+    pub span : Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
     Number(i64),
+    Float(f64),
     Bool(bool),
+    String(String),
     Variable(String),
     Cons { name : String, params : Vec<Expr> },
-    Let { name : String, t : Option<Type>, value : Box<Expr>, expr : Box<Expr> },
+    Record { name : String, fields : Vec<(String, Expr)> },
+    Tuple(Vec<Expr>), // Always has at least two items
+    FieldAccess { expr : Box<Expr>, field : String },
+    Let { pattern : StandardPattern, t : Option<Type>, value : Box<Expr>, expr : Box<Expr> },
+    LetRec { bindings : Vec<Binding>, expr : Box<Expr> }, // Every binding can refer to all of the others
     Lambda { params : Vec<FunParam>, return_type : Option<Type>, expr : Box<Expr> },
     Match { expr : Box<Expr>, cases : Vec<Case> },
+    If { condition : Box<Expr>, then_expr : Box<Expr>, else_expr : Box<Expr> },
     FunCall { fun_expr : Box<Expr>, params : Vec<Expr> },
     Array(Vec<Expr>),
     PathPattern(Vec<PathPattern>),
     ArrayPattern(Vec<ArrayPattern>),
+    NamedPattern(String),
 }
 
-#[derive(Debug)]
-pub enum Type {
+#[derive(Debug, Clone)]
+pub struct Type {
+    pub kind : TypeKind,
+    pub span : Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeKind {
     Generic(String),
     Concrete(String),
     Array(Box<Type>),
     Fun { input : Vec<Type>, output : Box<Type> },
     Index { name : String, params : Vec<Type> },
+    Anon(Vec<(String, Type)>),
+    Tuple(Vec<Type>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum StandardArrayPattern<P> {
     Empty,
     Array { items : Vec<P>, rest : Option<Box<P>> },
 }
 
-#[derive(Debug)]
-pub enum StandardPattern {
+#[derive(Debug, Clone)]
+pub struct StandardPattern {
+    pub kind : StandardPatternKind,
+    pub span : Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StandardPatternKind {
     Number(i64),
+    Range { min : Option<i64>, max : Option<i64> }, // Both bounds are inclusive
     Bool(bool),
+    String(String),
     Variable(String),
     Cons { name : String, params : Vec<StandardPattern> },
+    Record { name : String, fields : Vec<(String, StandardPattern)> },
+    Tuple(Vec<StandardPattern>),
     At { name : String, pattern : Box<StandardPattern> },
     Wildcard,
     If { pattern : Box<StandardPattern>, predicate : Box<Expr> },
     StandardArray(StandardArrayPattern<StandardPattern>),
+    Or(Vec<StandardPattern>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayPattern {
+    pub kind : ArrayPatternKind,
+    pub span : Span,
 }
 
-#[derive(Debug)]
-pub enum ArrayPattern {
+#[derive(Debug, Clone)]
+pub enum ArrayPatternKind {
     Number(i64),
+    Range { min : Option<i64>, max : Option<i64> }, // Both bounds are inclusive
     Bool(bool),
+    String(String),
     Variable(String),
     Cons { name : String, params : Vec<ArrayPattern> },
+    Record { name : String, fields : Vec<(String, ArrayPattern)> },
+    Tuple(Vec<ArrayPattern>),
     At { name : String, pattern : Box<ArrayPattern> },
     Wildcard,
     WildcardZeroOrMore,
     WildcardN(Box<Expr>),
+    Quantified { pattern : Box<ArrayPattern>, quantifier : Quantifier }, // Variables bound under a quantifier collect into arrays
     If { pattern : Box<ArrayPattern>, predicate : Box<Expr> },
     StandardArray(StandardArrayPattern<ArrayPattern>),
+    Or(Vec<ArrayPattern>),
+    Named(String),
 }
 
-#[derive(Debug)]
-pub enum PathPattern {
+#[derive(Debug, Clone)]
+pub enum Quantifier {
+    ZeroOrMore,
+    OneOrMore,
+    ZeroOrOne,
+    Exactly(Box<Expr>),
+    Range { min : Option<Box<Expr>>, max : Option<Box<Expr>> },
+}
+
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    pub kind : PathPatternKind,
+    pub span : Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum PathPatternKind {
     Number(i64),
+    Range { min : Option<i64>, max : Option<i64> }, // Both bounds are inclusive
     Bool(bool),
+    String(String),
     Variable(String),
     Cons { name : String, params : Vec<PathPattern> },
+    Record { name : String, fields : Vec<(String, PathPattern)> },
+    Tuple(Vec<PathPattern>),
     At { name : String, pattern : Box<PathPattern> },
     Wildcard,
     Next(Option<i64>),
@@ -92,4 +196,6 @@ pub enum PathPattern {
     NextAnd { order : Option<i64>, name : String, output : String },
     If { pattern : Box<PathPattern>, predicate : Box<Expr> },
     StandardArray(StandardArrayPattern<PathPattern>),
-}
\ No newline at end of file
+    Or(Vec<PathPattern>),
+    Named(String),
+}
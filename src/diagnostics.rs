@@ -0,0 +1,185 @@
+use crate::ast::Span;
+use crate::parsing::ParseError;
+use crate::parsing::loader::{LoadError, SourceFile};
+use crate::generation::data::{StaticError, StaticWarning};
+
+pub fn render_parse_error(file_name : &str, source : &str, error : &ParseError) -> String {
+    match error {
+        ParseError::Error => format!("error: unable to parse\n --> {file_name}\n"),
+        ParseError::Fatal(fs) if fs.is_empty() => format!("error: unable to parse\n --> {file_name}\n"),
+        ParseError::Fatal(fs) => {
+            let (message, span) = &fs[0];
+            let context = fs[1..].iter().map(|(m, s)| (m.as_str(), *s)).collect::<Vec<_>>();
+            render("error", file_name, source, message, *span, &context)
+        },
+    }
+}
+
+// Static errors are rendered against the loaded file their span points into.  Errors
+// about the program as a whole are reported against the main file.
+pub fn render_static_error(files : &[SourceFile], error : &StaticError) -> String {
+    match error {
+        StaticError::Fatal(message, span) => render_in("error", files, message, *span),
+        StaticError::Program(message) => format!("error: {message}\n --> {}\n", files[0].name),
+    }
+}
+
+pub fn render_static_warning(files : &[SourceFile], warning : &StaticWarning) -> String {
+    let StaticWarning(message, span) = warning;
+    render_in("warning", files, message, *span)
+}
+
+pub fn render_load_error(error : &LoadError) -> String {
+    match error {
+        LoadError::Io { file, message } => format!("error: unable to read {file}: {message}\n"),
+        LoadError::Parse { file, source, errors } => 
+            errors.iter().map(|e| render_parse_error(file, source, e)).collect::<Vec<_>>().join("\n"),
+        LoadError::Fatal { file, source, message, span } => render("error", file, source, message, *span, &[]),
+    }
+}
+
+// A program that stops because of an error is pointed at the code that it was running.
+pub fn render_runtime_error(files : &[SourceFile], message : &str, span : Span) -> String {
+    render_in("error", files, message, span)
+}
+
+fn render_in(level : &str, files : &[SourceFile], message : &str, span : Span) -> String {
+    let file = &files[span.file];
+    render(level, &file.name, &file.source, message, span, &[])
+}
+
+fn render(level : &str, file_name : &str, source : &str, message : &str, span : Span, context : &[(&str, Span)]) -> String {
+    let line_number = span.start.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+
+    let line = source.lines().nth(span.start.line - 1).unwrap_or("");
+    let line_length = line.chars().count();
+
+    let start = span.start.column - 1;
+    let end = if span.end.line == span.start.line {
+        span.end.column - 1
+    }
+    else {
+        line_length
+    };
+    let underline_length = if end > start { end - start } else { 1 };
+
+    let mut output = vec![];
+    output.push(format!("{level}: {message}"));
+    output.push(format!("{gutter}--> {file_name}:{}:{}", span.start.line, span.start.column));
+    output.push(format!("{gutter} |"));
+    output.push(format!("{line_number} | {line}"));
+    output.push(format!("{gutter} | {}{}", caret_padding(line, start), "^".repeat(underline_length)));
+
+    for (message, span) in context {
+        output.push(format!("{gutter} = note: {message} ({}:{})", span.start.line, span.start.column));
+    }
+
+    output.push(String::new());
+    output.join("\n")
+}
+
+// Tabs in the source line are kept so that the caret lines up with the source no
+// matter how wide the terminal renders them.
+fn caret_padding(line : &str, column : usize) -> String {
+    line.chars()
+        .chain(std::iter::repeat(' '))
+        .take(column)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ast::Position;
+    use crate::parsing::parser::parse;
+
+    #[test]
+    fn should_render_fatal_parse_error() {
+        let source = "fun name() -> Number =
+    blah.;
+";
+        let errors = match parse(source) {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected parse error"),
+        };
+
+        let output = render_parse_error("test.pat", source, &errors[0]);
+
+        assert_eq!( output, "error: there must exist a symbol after .
+ --> test.pat:2:10
+  |
+2 |     blah.;
+  |          ^
+  = note: fun must have an expr (2:10)
+" );
+    }
+
+    #[test]
+    fn should_underline_entire_span() {
+        let source = "data X = Nil;\ndata X = Other;\n";
+        let span = Span { file: 0, start: Position { line: 2, column: 1 }, end: Position { line: 2, column: 16 } };
+        let error = StaticError::Fatal("Encountered duplicate type name X".to_string(), span);
+
+        let files = [SourceFile { name: "test.pat".to_string(), source: source.to_string() }];
+
+        let output = render_static_error(&files, &error);
+
+        assert_eq!( output, "error: Encountered duplicate type name X
+ --> test.pat:2:1
+  |
+2 | data X = Other;
+  | ^^^^^^^^^^^^^^^
+" );
+    }
+
+    #[test]
+    fn should_render_static_error_against_file_of_span() {
+        let files = [ SourceFile { name: "main.pat".to_string(), source: "import \"a.pat\" as a;\n".to_string() }
+                    , SourceFile { name: "a.pat".to_string(), source: "data X = Nil;\ndata X = Other;\n".to_string() }
+                    ];
+        let span = Span { file: 1, start: Position { line: 2, column: 1 }, end: Position { line: 2, column: 16 } };
+        let error = StaticError::Fatal("Encountered duplicate type name X".to_string(), span);
+
+        let output = render_static_error(&files, &error);
+
+        assert_eq!( output, "error: Encountered duplicate type name X
+ --> a.pat:2:1
+  |
+2 | data X = Other;
+  | ^^^^^^^^^^^^^^^
+" );
+    }
+
+    #[test]
+    fn should_render_program_error_against_main_file() {
+        let files = [ SourceFile { name: "main.pat".to_string(), source: String::new() }
+                    , SourceFile { name: "a.pat".to_string(), source: String::new() }
+                    ];
+        let error = StaticError::Program("program has no main function".to_string());
+
+        assert_eq!( render_static_error(&files, &error), "error: program has no main function\n --> main.pat\n" );
+    }
+
+    #[test]
+    fn should_keep_tabs_when_padding_caret() {
+        assert_eq!( caret_padding("\tab", 2), "\t " );
+    }
+
+    #[test]
+    fn should_render_load_error_against_imported_file() {
+        let source = "import \"a.pat\" as a;\nimport \"b.pat\" as a;\n";
+        let span = Span { file: 0, start: Position { line: 2, column: 1 }, end: Position { line: 2, column: 21 } };
+        let error = LoadError::Fatal { file: "lib.pat".to_string(), source: source.to_string(), message: "Encountered duplicate import alias a".to_string(), span };
+
+        let output = render_load_error(&error);
+
+        assert_eq!( output, "error: Encountered duplicate import alias a
+ --> lib.pat:2:1
+  |
+2 | import \"b.pat\" as a;
+  | ^^^^^^^^^^^^^^^^^^^^
+" );
+    }
+}
@@ -9,9 +9,16 @@ pub struct HeapAddress(pub usize);
 pub enum Data {
     Bool(bool),
     Number(i64),
+    Float(f64),
     String(String),
     Fun(InstructionAddress),
     Ref(HeapAddress),
+    // Record constructors keep the names of their fields so that field access can find the slot.
+    // Tuples have a tag that starts with '#' and anon records have an empty tag.
+    Cons { tag : String, fields : Vec<String>, slots : Vec<HeapAddress> },
+    Tag(String),
+    Array(Vec<HeapAddress>),
+    Closure { fun : InstructionAddress, env : HeapAddress }, // The environment is passed as the first parameter
 }
 
 #[derive(Debug)]
@@ -5,6 +5,10 @@ pub struct StackOffset(pub usize);
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct InstructionAddress(pub usize);
 
+// Top level lets are stored in globals in the order that they are evaluated
+#[derive(Debug, Clone, Copy)]
+pub struct GlobalIndex(pub usize);
+
 impl InstructionAddress {
     pub fn next(&self) -> InstructionAddress {
         InstructionAddress(self.0 + 1)
@@ -20,11 +24,14 @@ pub enum Instruction {
    Call(InstructionAddress),
    CallFromHeap(StackOffset),
    PushReturnPointerToStack,
+   PushReturnPointerToGlobals,
+   LoadGlobal(GlobalIndex),
    PushStackToParam(StackOffset),
    BranchFalse(StackOffset, InstructionAddress),
    Move { src : StackOffset, dest: StackOffset },
    MoveReturnPointerToStack(StackOffset),
    Exit,
+   Fail(String), // Stops the program with the error message
    // Needs to put a HeapAddress on the return_pointer
    PopStack,
    Multiply(StackOffset, StackOffset),
@@ -41,9 +48,21 @@ pub enum Instruction {
    Equal(StackOffset, StackOffset),
    ConsBool(bool),
    ConsNumber(i64),
+   ConsFloat(f64),
    ConsString(String),
    ConsFunAddress(InstructionAddress),
    ConsRef(StackOffset),
+   ConsData { tag : String, fields : Vec<String>, slots : Vec<StackOffset> },
+   ConsTag(String),
+   ConsArray(Vec<StackOffset>),
+   ConsClosure { fun : InstructionAddress, env : StackOffset },
+   GetTag(StackOffset),
+   GetSlot(StackOffset, usize), // Works on constructed data and on arrays
+   GetField(StackOffset, String),
+   Length(StackOffset),
+   Index(StackOffset, StackOffset),
+   Drop(StackOffset, StackOffset),
+   Concat(StackOffset, StackOffset),
    Deref(StackOffset),
    Return(StackOffset),
 }
\ No newline at end of file
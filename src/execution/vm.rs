@@ -5,6 +5,7 @@ use std::mem;
 use super::instr::{ Instruction
                   , InstructionAddress
                   , StackOffset
+                  , GlobalIndex
                   };
 
 use super::data::{ Frame
@@ -20,10 +21,12 @@ pub struct VM {
     frames : Vec<Frame>,
     current_frame : Frame,
     return_pointer : HeapAddress,
+    globals : Vec<HeapAddress>,
 }
 
 pub trait SystemCalls {
     fn print(&mut self, s : String);
+    fn fail(&mut self, message : String);
 }
 
 pub struct DefaultSystemCalls { }
@@ -32,6 +35,17 @@ impl SystemCalls for DefaultSystemCalls {
     fn print(&mut self, s : String) {
         println!("{}", s);
     }
+
+    fn fail(&mut self, message : String) {
+        eprint!("{}", message);
+    }
+}
+
+// How the program stopped, which the process exits with
+#[derive(Debug, PartialEq)]
+pub enum Exit {
+    Success,
+    Failure,
 }
 
 impl VM {
@@ -43,17 +57,17 @@ impl VM {
            , frames: vec![]
            , current_frame: Frame { stack: vec![], return_address: InstructionAddress(0) } 
            , return_pointer: HeapAddress(0)
+           , globals: vec![]
            }
     }
 
-    pub fn run( &mut self, sys_calls : &mut impl SystemCalls ) {
+    pub fn run( &mut self, sys_calls : &mut impl SystemCalls ) -> Exit {
 
         loop {
             match get_instruction(&self.instructions, self.instruction_pointer) {
                 Instruction::Print(stack_offset) => { 
                     let r = get_stack(&self.current_frame.stack, *stack_offset);
-                    let h = get_heap(&self.heap, r);
-                    sys_calls.print( display(h) );
+                    sys_calls.print( display(&self.heap, r) );
                 },
                 Instruction::Call(address) => {
                     let incoming_params = mem::take(&mut self.outgoing_params);
@@ -71,12 +85,16 @@ impl VM {
                 Instruction::CallFromHeap(stack_offset) => {
                     let s = get_stack(&self.current_frame.stack, *stack_offset);
                     let h = get_heap(&self.heap, s);
-                    let address = match h {
-                        Data::Fun(x) => x,
+                    let (address, env) = match h {
+                        Data::Fun(x) => (x, None),
+                        Data::Closure { fun, env } => (fun, Some(*env)),
                         _ => panic!("CallFromHeap must call a function instruction"),
                     };
 
-                    let incoming_params = mem::take(&mut self.outgoing_params);
+                    let mut incoming_params = mem::take(&mut self.outgoing_params);
+                    if let Some(env) = env {
+                        incoming_params.insert(0, env);
+                    }
 
                     let mut frame = Frame { stack: incoming_params 
                                           , return_address: self.instruction_pointer.next()
@@ -91,6 +109,12 @@ impl VM {
                 Instruction::PushReturnPointerToStack => {
                     self.current_frame.stack.push(self.return_pointer);
                 },
+                Instruction::PushReturnPointerToGlobals => {
+                    self.globals.push(self.return_pointer);
+                },
+                Instruction::LoadGlobal(GlobalIndex(index)) => {
+                    self.return_pointer = self.globals[*index];
+                },
                 Instruction::PushStackToParam(stack_offset) => {
                     let v = get_stack(&self.current_frame.stack, *stack_offset);
                     self.outgoing_params.push(v);
@@ -109,7 +133,11 @@ impl VM {
                 Instruction::MoveReturnPointerToStack(stack_offset) => {
                     set_stack(&mut self.current_frame.stack, *stack_offset, self.return_pointer);
                 },
-                Instruction::Exit => { break; },
+                Instruction::Exit => { return Exit::Success; },
+                Instruction::Fail(message) => {
+                    sys_calls.fail(message.to_string());
+                    return Exit::Failure;
+                },
 
                 // Needs to put a HeapAddress on the return_pointer
                 Instruction::PopStack => {
@@ -117,43 +145,53 @@ impl VM {
                     self.return_pointer = v;
                 },
                 Instruction::Multiply(offset_a, offset_b) => {
-                    let a = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_a);
-                    let b = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_b);
+                    let result = match get_heap_numbers_from_stack(&self.current_frame.stack, &self.heap, *offset_a, *offset_b) {
+                        Numbers::Number(a, b) => Data::Number(a * b),
+                        Numbers::Float(a, b) => Data::Float(a * b),
+                    };
 
                     let address = HeapAddress(self.heap.len());
-                    self.heap.push(Data::Number(a * b));
+                    self.heap.push(result);
                     self.return_pointer = address;
                 },
                 Instruction::Division(offset_a, offset_b) => {
-                    let a = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_a);
-                    let b = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_b);
+                    let result = match get_heap_numbers_from_stack(&self.current_frame.stack, &self.heap, *offset_a, *offset_b) {
+                        Numbers::Number(a, b) => Data::Number(a / b),
+                        Numbers::Float(a, b) => Data::Float(a / b),
+                    };
 
                     let address = HeapAddress(self.heap.len());
-                    self.heap.push(Data::Number(a / b));
+                    self.heap.push(result);
                     self.return_pointer = address;
                 },
                 Instruction::Remainder(offset_a, offset_b) => {
-                    let a = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_a);
-                    let b = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_b);
+                    let result = match get_heap_numbers_from_stack(&self.current_frame.stack, &self.heap, *offset_a, *offset_b) {
+                        Numbers::Number(a, b) => Data::Number(a % b),
+                        Numbers::Float(a, b) => Data::Float(a % b),
+                    };
 
                     let address = HeapAddress(self.heap.len());
-                    self.heap.push(Data::Number(a % b));
+                    self.heap.push(result);
                     self.return_pointer = address;
                 },
                 Instruction::Addition(offset_a, offset_b) => {
-                    let a = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_a);
-                    let b = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_b);
+                    let result = match get_heap_numbers_from_stack(&self.current_frame.stack, &self.heap, *offset_a, *offset_b) {
+                        Numbers::Number(a, b) => Data::Number(a + b),
+                        Numbers::Float(a, b) => Data::Float(a + b),
+                    };
 
                     let address = HeapAddress(self.heap.len());
-                    self.heap.push(Data::Number(a + b));
+                    self.heap.push(result);
                     self.return_pointer = address;
                 },
                 Instruction::Substract(offset_a, offset_b) => {
-                    let a = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_a);
-                    let b = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_b);
+                    let result = match get_heap_numbers_from_stack(&self.current_frame.stack, &self.heap, *offset_a, *offset_b) {
+                        Numbers::Number(a, b) => Data::Number(a - b),
+                        Numbers::Float(a, b) => Data::Float(a - b),
+                    };
 
                     let address = HeapAddress(self.heap.len());
-                    self.heap.push(Data::Number(a - b));
+                    self.heap.push(result);
                     self.return_pointer = address;
                 },
                 Instruction::LogicalXor(offset_a, offset_b) => {
@@ -188,29 +226,30 @@ impl VM {
                     self.return_pointer = address;
                 },
                 Instruction::GreaterThan(offset_a, offset_b) => {
-                    let a = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_a);
-                    let b = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_b);
+                    let result = match get_heap_numbers_from_stack(&self.current_frame.stack, &self.heap, *offset_a, *offset_b) {
+                        Numbers::Number(a, b) => a > b,
+                        Numbers::Float(a, b) => a > b,
+                    };
 
                     let address = HeapAddress(self.heap.len());
-                    self.heap.push(Data::Bool(a > b));
+                    self.heap.push(Data::Bool(result));
                     self.return_pointer = address;
                 },
                 Instruction::LessThan(offset_a, offset_b) => {
-                    let a = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_a);
-                    let b = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_b);
+                    let result = match get_heap_numbers_from_stack(&self.current_frame.stack, &self.heap, *offset_a, *offset_b) {
+                        Numbers::Number(a, b) => a < b,
+                        Numbers::Float(a, b) => a < b,
+                    };
 
                     let address = HeapAddress(self.heap.len());
-                    self.heap.push(Data::Bool(a < b));
+                    self.heap.push(Data::Bool(result));
                     self.return_pointer = address;
                 },
                 Instruction::Equal(offset_a, offset_b) => {
-                    let a_r = get_stack(&self.current_frame.stack, *offset_a);
-                    let a = get_heap(&self.heap, a_r);
-
-                    let b_r = get_stack(&self.current_frame.stack, *offset_b);
-                    let b = get_heap(&self.heap, b_r);
+                    let a = get_stack(&self.current_frame.stack, *offset_a);
+                    let b = get_stack(&self.current_frame.stack, *offset_b);
 
-                    let result = b == a;
+                    let result = equal(&self.heap, a, b);
 
                     let address = HeapAddress(self.heap.len());
                     self.heap.push(Data::Bool(result));
@@ -226,6 +265,11 @@ impl VM {
                     self.heap.push(Data::Number(*n));
                     self.return_pointer = address;
                 },
+                Instruction::ConsFloat(f) => {
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Float(*f));
+                    self.return_pointer = address;
+                },
                 Instruction::ConsString(s) => {
                     let address = HeapAddress(self.heap.len());
                     self.heap.push(Data::String(s.clone()));
@@ -242,6 +286,81 @@ impl VM {
                     self.heap.push(Data::Ref(r));
                     self.return_pointer = address;
                 },
+                Instruction::ConsData { tag, fields, slots } => {
+                    let slots = slots.iter().map(|s| get_stack(&self.current_frame.stack, *s)).collect();
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Cons { tag: tag.clone(), fields: fields.clone(), slots });
+                    self.return_pointer = address;
+                },
+                Instruction::ConsTag(tag) => {
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Tag(tag.clone()));
+                    self.return_pointer = address;
+                },
+                Instruction::ConsArray(items) => {
+                    let items = items.iter().map(|s| get_stack(&self.current_frame.stack, *s)).collect();
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Array(items));
+                    self.return_pointer = address;
+                },
+                Instruction::ConsClosure { fun, env } => {
+                    let env = get_stack(&self.current_frame.stack, *env);
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Closure { fun: *fun, env });
+                    self.return_pointer = address;
+                },
+                Instruction::GetTag(stack_offset) => {
+                    let tag = match get_heap(&self.heap, get_stack(&self.current_frame.stack, *stack_offset)) {
+                        Data::Cons { tag, .. } => tag.clone(),
+                        _ => panic!("GetTag must get the tag of constructed data"),
+                    };
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Tag(tag));
+                    self.return_pointer = address;
+                },
+                Instruction::GetSlot(stack_offset, index) => {
+                    let items = match get_heap(&self.heap, get_stack(&self.current_frame.stack, *stack_offset)) {
+                        Data::Cons { slots, .. } => slots,
+                        Data::Array(items) => items,
+                        _ => panic!("GetSlot must get the slot of constructed data or an array"),
+                    };
+                    self.return_pointer = items[*index];
+                },
+                Instruction::GetField(stack_offset, field) => {
+                    let (fields, slots) = match get_heap(&self.heap, get_stack(&self.current_frame.stack, *stack_offset)) {
+                        Data::Cons { fields, slots, .. } => (fields, slots),
+                        _ => panic!("GetField must get the field of constructed data"),
+                    };
+                    let index = fields.iter().position(|f| f == field).expect("GetField must get a field that the data has");
+                    self.return_pointer = slots[index];
+                },
+                Instruction::Length(stack_offset) => {
+                    let length = get_heap_array_from_stack(&self.current_frame.stack, &self.heap, *stack_offset).len();
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Number(length as i64));
+                    self.return_pointer = address;
+                },
+                Instruction::Index(offset_array, offset_index) => {
+                    let items = get_heap_array_from_stack(&self.current_frame.stack, &self.heap, *offset_array);
+                    let index = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_index);
+                    self.return_pointer = *usize::try_from(index).ok().and_then(|i| items.get(i)).expect("Index must be inside of the array");
+                },
+                Instruction::Drop(offset_array, offset_count) => {
+                    let items = get_heap_array_from_stack(&self.current_frame.stack, &self.heap, *offset_array);
+                    let count = get_heap_number_from_stack(&self.current_frame.stack, &self.heap, *offset_count);
+                    let rest = items.iter().skip(count.max(0) as usize).copied().collect();
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Array(rest));
+                    self.return_pointer = address;
+                },
+                Instruction::Concat(offset_a, offset_b) => {
+                    let a = get_heap_array_from_stack(&self.current_frame.stack, &self.heap, *offset_a);
+                    let b = get_heap_array_from_stack(&self.current_frame.stack, &self.heap, *offset_b);
+                    let items = a.iter().chain(b).copied().collect();
+                    let address = HeapAddress(self.heap.len());
+                    self.heap.push(Data::Array(items));
+                    self.return_pointer = address;
+                },
                 Instruction::Deref(stack_offset) => {
                     let s = get_stack(&self.current_frame.stack, *stack_offset);
                     let h1 = get_heap(&self.heap, s);
@@ -291,6 +410,25 @@ fn get_heap_number_from_stack(stack : &Vec<HeapAddress>, heap : &Vec<Data>, offs
     }
 }
 
+// Arithmetic finds out whether it has Numbers or Floats from the data itself, because an operand isn't
+// always known to be one or the other before the program runs.  A Number next to a Float is used as a Float.
+enum Numbers {
+    Number(i64, i64),
+    Float(f64, f64),
+}
+
+fn get_heap_numbers_from_stack(stack : &Vec<HeapAddress>, heap : &Vec<Data>, offset_a : StackOffset, offset_b : StackOffset) -> Numbers {
+    let a = get_heap(heap, get_stack(stack, offset_a));
+    let b = get_heap(heap, get_stack(stack, offset_b));
+    match (a, b) {
+        (Data::Number(a), Data::Number(b)) => Numbers::Number(*a, *b),
+        (Data::Number(a), Data::Float(b)) => Numbers::Float(*a as f64, *b),
+        (Data::Float(a), Data::Number(b)) => Numbers::Float(*a, *b as f64),
+        (Data::Float(a), Data::Float(b)) => Numbers::Float(*a, *b),
+        _ => panic!("Getting Numbers from heap must be numbers or floats"),
+    }
+}
+
 fn get_heap_bool_from_stack(stack : &Vec<HeapAddress>, heap : &Vec<Data>, offset : StackOffset) -> bool {
     let r = get_stack(stack, offset);
     let v = get_heap(heap, r);
@@ -300,14 +438,50 @@ fn get_heap_bool_from_stack(stack : &Vec<HeapAddress>, heap : &Vec<Data>, offset
     }
 }
 
-fn display(d : &Data) -> String {
-    match d {
+fn get_heap_array_from_stack<'a>(stack : &Vec<HeapAddress>, heap : &'a Vec<Data>, offset : StackOffset) -> &'a Vec<HeapAddress> {
+    let r = get_stack(stack, offset);
+    let v = get_heap(heap, r);
+    match v {
+        Data::Array(items) => items,
+        _ => panic!("Getting Array from heap must be array"),
+    }
+}
+
+// Constructed data and arrays are equal when everything inside of them is equal.
+fn equal(heap : &Vec<Data>, a : HeapAddress, b : HeapAddress) -> bool {
+    fn all(heap : &Vec<Data>, a : &[HeapAddress], b : &[HeapAddress]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(heap, *a, *b))
+    }
+
+    match (get_heap(heap, a), get_heap(heap, b)) {
+        (Data::Cons { tag: tag_a, slots: slots_a, .. }, Data::Cons { tag: tag_b, slots: slots_b, .. }) => tag_a == tag_b && all(heap, slots_a, slots_b),
+        (Data::Array(items_a), Data::Array(items_b)) => all(heap, items_a, items_b),
+        (Data::Number(a), Data::Float(b)) | (Data::Float(b), Data::Number(a)) => *a as f64 == *b,
+        (a, b) => a == b,
+    }
+}
+
+fn display(heap : &Vec<Data>, address : HeapAddress) -> String {
+    let all = |items : &[HeapAddress]| items.iter().map(|i| display(heap, *i)).collect::<Vec<_>>().join(", ");
+
+    match get_heap(heap, address) {
         Data::Bool(true) => "true".to_string(),
         Data::Bool(false) => "false".to_string(),
         Data::Number(i) => i.to_string(),
+        Data::Float(f) => format!("{:?}", f),
         Data::String(s) => s.to_string(),
         Data::Fun(address) => format!("function at:  {:X}", address.0),
         Data::Ref(address) => format!("data at:  {:X}", address.0),
+        Data::Cons { tag, slots, .. } if tag.starts_with('#') => format!("({})", all(slots)),
+        Data::Cons { tag, fields, slots } if fields.is_empty() && slots.is_empty() => tag.to_string(),
+        Data::Cons { tag, fields, slots } if fields.is_empty() => format!("{tag}({})", all(slots)),
+        Data::Cons { tag, fields, slots } => {
+            let fields = fields.iter().zip(slots).map(|(f, s)| format!("{f}: {}", display(heap, *s))).collect::<Vec<_>>().join(", ");
+            format!("{tag} {{ {fields} }}").trim_start().to_string()
+        },
+        Data::Tag(tag) => tag.to_string(),
+        Data::Array(items) => format!("[{}]", all(items)),
+        Data::Closure { fun, .. } => format!("closure at:  {:X}", fun.0),
     }
 }
 
@@ -323,6 +497,10 @@ mod test {
         fn print(&mut self, s : String) {
             self.prints.push(s);
         }
+
+        fn fail(&mut self, message : String) {
+            panic!("program should not fail: {message}");
+        }
     }
 
     #[test]
@@ -330,7 +508,34 @@ mod test {
         let mut sys = TestSysCall { prints: vec![] };
         let mut vm = VM::new(vec![ Instruction::Exit ], InstructionAddress(0));
 
-        vm.run(&mut sys);
+        assert_eq!( vm.run(&mut sys), Exit::Success );
+    }
+
+    #[test]
+    fn fail_should_stop_with_failure() {
+        struct Failures(Vec<String>);
+
+        impl SystemCalls for Failures {
+            fn print(&mut self, s : String) {
+                panic!("program should not print: {s}");
+            }
+
+            fn fail(&mut self, message : String) {
+                self.0.push(message);
+            }
+        }
+
+        let mut sys = Failures(vec![]);
+        let mut vm = VM::new( vec![ Instruction::Fail("error: stopped".to_string())
+                                  , Instruction::ConsNumber(1)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Print(StackOffset(0))
+                                  , Instruction::Exit
+                                  ]
+                            , InstructionAddress(0));
+
+        assert_eq!( vm.run(&mut sys), Exit::Failure );
+        assert_eq!( sys.0, vec!["error: stopped"] );
     }
 
     #[test]
@@ -600,4 +805,106 @@ mod test {
         assert_eq!( sys.prints.len(), 11 );
         assert_eq!( sys.prints.iter().map(|x| x.to_string()).collect::<String>(), "9876543210-1" );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_do_float_arithmetic() {
+        let mut sys = TestSysCall { prints: vec![] };
+        let mut vm = VM::new( vec![ Instruction::ConsFloat(7.5)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::ConsFloat(2.0)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Division(StackOffset(0), StackOffset(1))
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Multiply(StackOffset(1), StackOffset(1))
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::LessThan(StackOffset(2), StackOffset(3))
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::ConsNumber(3)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Addition(StackOffset(2), StackOffset(5))
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Print(StackOffset(2))
+                                  , Instruction::Print(StackOffset(3))
+                                  , Instruction::Print(StackOffset(4))
+                                  , Instruction::Print(StackOffset(6))
+                                  , Instruction::Exit
+                                  ]
+                            , InstructionAddress(0));
+
+        vm.run(&mut sys);
+        assert_eq!( sys.prints, vec![ "3.75", "4.0", "true", "6.75" ] );
+    }
+
+    #[test]
+    fn should_pass_environment_to_closure() {
+        let mut sys = TestSysCall { prints: vec![] };
+        let mut vm = VM::new( vec![ Instruction::GetSlot(StackOffset(0), 0)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Addition(StackOffset(1), StackOffset(2))
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Return(StackOffset(3))
+
+                                  , Instruction::ConsNumber(10)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::ConsArray(vec![StackOffset(0)])
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::ConsClosure { fun: InstructionAddress(0), env: StackOffset(1) }
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::ConsNumber(5)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::PushStackToParam(StackOffset(3))
+                                  , Instruction::CallFromHeap(StackOffset(2))
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Print(StackOffset(4))
+                                  , Instruction::Exit
+                                  ]
+                            , InstructionAddress(5));
+
+        vm.run(&mut sys);
+        assert_eq!( sys.prints, vec![ "15" ] );
+    }
+
+    #[test]
+    fn should_compare_data_by_contents() {
+        let mut sys = TestSysCall { prints: vec![] };
+        let mut vm = VM::new( vec![ Instruction::ConsNumber(1)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::ConsNumber(1)
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::ConsData { tag: "P".to_string(), fields: vec!["a".to_string()], slots: vec![StackOffset(0)] }
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::ConsData { tag: "P".to_string(), fields: vec!["a".to_string()], slots: vec![StackOffset(1)] }
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Equal(StackOffset(2), StackOffset(3))
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::GetField(StackOffset(3), "a".to_string())
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Print(StackOffset(4))
+                                  , Instruction::Print(StackOffset(5))
+                                  , Instruction::Print(StackOffset(3))
+                                  , Instruction::Exit
+                                  ]
+                            , InstructionAddress(0));
+
+        vm.run(&mut sys);
+        assert_eq!( sys.prints, vec![ "true", "1", "P { a: 1 }" ] );
+    }
+
+    #[test]
+    fn should_store_and_load_globals() {
+        let mut sys = TestSysCall { prints: vec![] };
+        let mut vm = VM::new( vec![ Instruction::ConsNumber(1)
+                                  , Instruction::PushReturnPointerToGlobals
+                                  , Instruction::ConsNumber(2)
+                                  , Instruction::PushReturnPointerToGlobals
+                                  , Instruction::LoadGlobal(GlobalIndex(0))
+                                  , Instruction::PushReturnPointerToStack
+                                  , Instruction::Print(StackOffset(0))
+                                  , Instruction::Exit
+                                  ]
+                            , InstructionAddress(0));
+
+        vm.run(&mut sys);
+        assert_eq!( sys.prints, vec![ "1" ] );
+    }
+}
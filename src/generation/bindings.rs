@@ -0,0 +1,123 @@
+use crate::ast::{ Ast
+                , Span
+                , StandardPattern
+                , StandardPatternKind
+                , ArrayPattern
+                , ArrayPatternKind
+                , PathPattern
+                , PathPatternKind
+                };
+
+use crate::visit::{self, Visit};
+
+use super::data::StaticError;
+
+// Whichever alternative of an or pattern matches, the same variables need to be bound afterwards.
+// This runs after inlining since a named pattern can bind variables in one of the alternatives.
+pub fn check(asts : &[Ast]) -> Result<(), StaticError> {
+    let mut checker = Alternatives(None);
+    for ast in asts {
+        match ast {
+            Ast::FunDef { params, expr, .. } => {
+                params.iter().for_each(|p| checker.standard(&p.pattern));
+                checker.expr(expr);
+            },
+            Ast::LetDef { expr, .. } => checker.expr(expr),
+            _ => { },
+        }
+    }
+    match checker.0 {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+// Keeps the first error, which belongs to the innermost or pattern that is written first
+struct Alternatives(Option<StaticError>);
+
+impl Alternatives {
+    fn alternatives<P>(&mut self, alternatives : &[P], variables : fn(&P, &mut Vec<String>), span : fn(&P) -> Span) {
+        if self.0.is_none() {
+            self.0 = check_alternatives(alternatives, variables, span).err();
+        }
+    }
+}
+
+impl Visit for Alternatives {
+    fn standard(&mut self, pattern : &StandardPattern) {
+        visit::walk_standard(self, pattern);
+        if let StandardPatternKind::Or(alternatives) = &pattern.kind {
+            self.alternatives(alternatives, visit::standard_variables, |p| p.span);
+        }
+    }
+
+    fn array(&mut self, pattern : &ArrayPattern) {
+        visit::walk_array(self, pattern);
+        if let ArrayPatternKind::Or(alternatives) = &pattern.kind {
+            self.alternatives(alternatives, visit::array_variables, |p| p.span);
+        }
+    }
+
+    fn path(&mut self, pattern : &PathPattern) {
+        visit::walk_path(self, pattern);
+        if let PathPatternKind::Or(alternatives) = &pattern.kind {
+            self.alternatives(alternatives, visit::path_variables, |p| p.span);
+        }
+    }
+}
+
+fn check_alternatives<P>( alternatives : &[P]
+                        , variables : fn(&P, &mut Vec<String>)
+                        , span : fn(&P) -> Span
+                        ) -> Result<(), StaticError> {
+
+    let bound = alternatives.iter().map(|p| { let mut out = vec![]; variables(p, &mut out); out }).collect::<Vec<_>>();
+
+    for (alternative, vars) in alternatives.iter().zip(&bound) {
+        if let Some(missing) = bound.iter().flatten().find(|v| !vars.contains(v)) {
+            return Err(StaticError::Fatal(format!("Variable {missing} is not bound in every alternative of | pattern"), span(alternative)));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsing::parser::parse;
+    use crate::generation::named_patterns;
+
+    fn check_input(input : &str) -> Result<(), StaticError> {
+        let asts = named_patterns::inline(parse(input).expect("test input should parse"))?;
+        check(&asts)
+    }
+
+    #[test]
+    fn should_accept_alternatives_binding_same_variables() {
+        let result = check_input("
+            pattern pair = {| Pair(x, _) |};
+            fun f(a : Color) -> Number = match a { Red(x) | Blue(x, _) => x };
+            fun g() -> Number = {| $pair | Single(x) |};
+        ");
+
+        assert!( result.is_ok() );
+    }
+
+    #[test]
+    fn should_reject_alternatives_binding_different_variables() {
+        let result = check_input("fun f(a : Color) -> Number = match a { Red(x) | Blue(y) => x };");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Variable y is not bound in every alternative of | pattern" ) );
+    }
+
+    #[test]
+    fn should_check_alternatives_after_inlining() {
+        let result = check_input("
+            pattern pair = [| Pair(x, y) |];
+            fun f() -> Number = [| $pair | Single(x) |];
+        ");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, span)) if message == "Variable y is not bound in every alternative of | pattern" && span.start.line == 3 ) );
+    }
+}
@@ -1,15 +1,22 @@
 
 use crate::ir::ConsTag;
+use crate::ast::Span;
 
 #[derive(Debug)]
 pub enum StaticError {
-    Fatal(String),
+    Fatal(String, Span),
+    Program(String), // About the program as a whole instead of any one place in it
 }
 
+// Reported without stopping generation.
+#[derive(Debug)]
+pub struct StaticWarning(pub String, pub Span);
+
 #[derive(Debug, Clone)]
 pub struct ConsInfo { 
     pub tag : ConsTag,
     pub ts : Vec<Type>,
+    pub fields : Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone)] 
@@ -29,4 +36,5 @@ pub enum Type {
     Fun { input : Vec<Type>, output : Box<Type> },
     Index { name : ConcreteType, params : Vec<Type> },
     Anon(Vec<RowType>),
+    Tuple(Vec<Type>),
 }
\ No newline at end of file
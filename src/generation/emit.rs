@@ -0,0 +1,640 @@
+use std::collections::HashMap;
+
+use crate::ir::{ Program
+               , Ir
+               , Symbol
+               , Statement
+               , Expr
+               , ConsTag
+               , SlotAccessType
+               };
+
+use crate::execution::instr::{ Instruction
+                             , InstructionAddress
+                             , StackOffset
+                             , GlobalIndex
+                             };
+
+use crate::parsing::loader::SourceFile;
+use crate::diagnostics;
+
+use super::data::StaticError;
+
+const BUILTINS : [&str; 19] = [ "print", "eq", "lt", "gt", "lte", "gte", "add", "sub", "div", "rem", "mul", "not", "and", "or", "xor"
+                              , "len", "nth", "drop", "concat"
+                              ];
+
+// Every function is laid out first and then the entry point, which evaluates the top level lets in
+// order, calls main and exits.  The vm starts at the returned address of the entry point.  The files are
+// the ones that the program was loaded from, for the errors that it can stop with.
+pub fn emit(program : &Program, files : &[SourceFile]) -> Result<(Vec<Instruction>, InstructionAddress), StaticError> {
+    let main = match &program.entry {
+        Some(main) => main,
+        None => return Err(StaticError::Program("program has no main function".to_string())),
+    };
+
+    let mut emitter = Emitter { instructions: vec![]
+                              , funs: program.irs.iter().map(|ir| ir.name.clone()).collect()
+                              , globals: program.constants.iter().enumerate().map(|(i, c)| (c.clone(), GlobalIndex(i))).collect()
+                              , addresses: HashMap::new()
+                              , fixups: vec![]
+                              , slots: HashMap::new()
+                              , locals: vec![]
+                              , fields: &program.fields
+                              , files
+                              };
+
+    for ir in &program.irs {
+        emitter.addresses.insert(ir.name.clone(), InstructionAddress(emitter.instructions.len()));
+        emitter.ir(ir)?;
+    }
+
+    let entry = InstructionAddress(emitter.instructions.len());
+    for constant in &program.constants {
+        emitter.call(constant);
+        emitter.instructions.push(Instruction::PushReturnPointerToGlobals);
+    }
+    emitter.call(main);
+    emitter.instructions.push(Instruction::Exit);
+
+    let Emitter { mut instructions, addresses, fixups, .. } = emitter;
+    for (index, fun) in fixups {
+        let address = addresses[&fun];
+        match &mut instructions[index] {
+            Instruction::Call(a) | Instruction::ConsFunAddress(a) | Instruction::ConsClosure { fun: a, .. } => *a = address,
+            i => panic!("emit should only fix up function addresses, found {:?}", i),
+        }
+    }
+
+    Ok((instructions, entry))
+}
+
+struct Emitter<'a> {
+    instructions : Vec<Instruction>,
+    funs : Vec<Symbol>,
+    globals : HashMap<Symbol, GlobalIndex>,
+    addresses : HashMap<Symbol, InstructionAddress>,
+    fixups : Vec<(usize, Symbol)>, // Instructions that need the address of a function which might not be laid out yet
+    // Every symbol in the current function has a stack slot.  Top level functions and lets
+    // that are used as values are loaded into their slot right before they are used.
+    slots : HashMap<Symbol, StackOffset>,
+    locals : Vec<Symbol>,
+    fields : &'a HashMap<ConsTag, Vec<String>>,
+    files : &'a [SourceFile],
+}
+
+impl Emitter<'_> {
+    fn ir(&mut self, ir : &Ir) -> Result<(), StaticError> {
+        fn assigned(statements : &[Statement], out : &mut Vec<Symbol>) {
+            for statement in statements {
+                match statement {
+                    Statement::Assign { name, .. } if !out.contains(name) => out.push(name.clone()),
+                    Statement::If { statements, .. } => assigned(statements, out),
+                    _ => { },
+                }
+            }
+        }
+
+        self.locals = ir.params.clone();
+        assigned(&ir.statements, &mut self.locals);
+
+        self.slots = self.locals.iter()
+                                .chain(&ir.free_symbols())
+                                .enumerate()
+                                .map(|(i, sym)| (sym.clone(), StackOffset(i)))
+                                .collect();
+
+        // The parameters are already on the stack and whatever is pushed for the
+        // rest is overwritten before it is read
+        for _ in ir.params.len()..self.slots.len() {
+            self.instructions.push(Instruction::PushReturnPointerToStack);
+        }
+
+        self.statements(&ir.statements)
+    }
+
+    fn statements(&mut self, statements : &[Statement]) -> Result<(), StaticError> {
+        for statement in statements {
+            match statement {
+                Statement::Assign { name, expr } => {
+                    let dest = self.slots[name];
+                    self.expr(expr, dest)?;
+                },
+                Statement::If { target, statements } => {
+                    let target = self.value(target)?;
+                    let branch = self.instructions.len();
+                    self.instructions.push(Instruction::BranchFalse(target, InstructionAddress(0)));
+                    self.statements(statements)?;
+                    self.instructions[branch] = Instruction::BranchFalse(target, InstructionAddress(self.instructions.len()));
+                },
+                Statement::Return(sym) => {
+                    let slot = self.value(sym)?;
+                    self.instructions.push(Instruction::Return(slot));
+                },
+                Statement::Fail(message, span) => {
+                    let message = diagnostics::render_runtime_error(self.files, message, *span);
+                    self.instructions.push(Instruction::Fail(message));
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr : &Expr, dest : StackOffset) -> Result<(), StaticError> {
+        match expr {
+            Expr::Number(n) => self.cons(Instruction::ConsNumber(*n), dest),
+            Expr::Float(f) => self.cons(Instruction::ConsFloat(*f), dest),
+            Expr::Bool(b) => self.cons(Instruction::ConsBool(*b), dest),
+            Expr::String(s) => self.cons(Instruction::ConsString(s.to_string()), dest),
+            Expr::Variable(sym) => {
+                let src = self.value(sym)?;
+                self.instructions.push(Instruction::Move { src, dest });
+            },
+            Expr::FunCall { name, params } => self.fun_call(name, params, dest)?,
+            Expr::Array(items) => {
+                let items = self.slots_of(items)?;
+                self.cons(Instruction::ConsArray(items), dest);
+            },
+            Expr::Environment(syms) => {
+                let mut items = vec![];
+                for sym in syms {
+                    items.push(self.value(sym)?);
+                }
+                self.cons(Instruction::ConsArray(items), dest);
+            },
+            Expr::Constructor { cons_tag, slots_assigns } => {
+                let slots = self.slots_of(slots_assigns)?;
+                let fields = self.fields.get(cons_tag).cloned().unwrap_or_default();
+                self.cons(Instruction::ConsData { tag: tag_name(cons_tag), fields, slots }, dest);
+            },
+            Expr::Anon(fields) => {
+                let slots = self.slots_of(fields.iter().map(|(_, e)| e))?;
+                let fields = fields.iter().map(|(f, _)| f.to_string()).collect();
+                self.cons(Instruction::ConsData { tag: String::new(), fields, slots }, dest);
+            },
+            Expr::Tag(cons_tag) => self.cons(Instruction::ConsTag(tag_name(cons_tag)), dest),
+            Expr::Closure { fun, env } => {
+                let env = self.value(env)?;
+                self.fixups.push((self.instructions.len(), fun.clone()));
+                self.cons(Instruction::ConsClosure { fun: InstructionAddress(0), env }, dest);
+            },
+            Expr::SlotAccess { data, slot } => {
+                let data = self.value(data)?;
+                let instruction = match slot {
+                    SlotAccessType::Tag => Instruction::GetTag(data),
+                    SlotAccessType::Index(index) => Instruction::GetSlot(data, *index),
+                    SlotAccessType::Field(field) => Instruction::GetField(data, field.to_string()),
+                };
+                self.cons(instruction, dest);
+            },
+        }
+        Ok(())
+    }
+
+    // The generator only puts variables inside of arrays and constructors
+    fn slots_of<'e>(&mut self, exprs : impl IntoIterator<Item = &'e Expr>) -> Result<Vec<StackOffset>, StaticError> {
+        let mut ret = vec![];
+        for expr in exprs {
+            match expr {
+                Expr::Variable(sym) => ret.push(self.value(sym)?),
+                e => panic!("emit should only have variables inside of arrays and constructors, found {:?}", e),
+            }
+        }
+        Ok(ret)
+    }
+
+    fn fun_call(&mut self, name : &Symbol, params : &[Symbol], dest : StackOffset) -> Result<(), StaticError> {
+        let mut args = vec![];
+        for param in params {
+            args.push(self.value(param)?);
+        }
+
+        let local = self.locals.contains(name);
+        if !local && !self.funs.contains(name) && !self.globals.contains_key(name) {
+            return self.builtin(name, &args, dest);
+        }
+
+        let fun = if local || self.globals.contains_key(name) { Some(self.value(name)?) } else { None };
+
+        for arg in args {
+            self.instructions.push(Instruction::PushStackToParam(arg));
+        }
+        match fun {
+            Some(fun) => self.instructions.push(Instruction::CallFromHeap(fun)),
+            None => self.call(name),
+        }
+        self.instructions.push(Instruction::MoveReturnPointerToStack(dest));
+        Ok(())
+    }
+
+    fn builtin(&mut self, name : &Symbol, args : &[StackOffset], dest : StackOffset) -> Result<(), StaticError> {
+        let name = match name {
+            Symbol::User(name) if BUILTINS.contains(&name.as_str()) => name.as_str(),
+            sym => return Err(unknown(sym)),
+        };
+
+        let instruction = match (name, args) {
+            ("add", [a, b]) => Instruction::Addition(*a, *b),
+            ("sub", [a, b]) => Instruction::Substract(*a, *b),
+            ("mul", [a, b]) => Instruction::Multiply(*a, *b),
+            ("div", [a, b]) => Instruction::Division(*a, *b),
+            ("rem", [a, b]) => Instruction::Remainder(*a, *b),
+            ("lt", [a, b]) => Instruction::LessThan(*a, *b),
+            ("gt", [a, b]) => Instruction::GreaterThan(*a, *b),
+            ("eq", [a, b]) => Instruction::Equal(*a, *b),
+            ("and", [a, b]) => Instruction::LogicalAnd(*a, *b),
+            ("or", [a, b]) => Instruction::LogicalOr(*a, *b),
+            ("xor", [a, b]) => Instruction::LogicalXor(*a, *b),
+            ("not", [a]) => Instruction::LogicalNot(*a),
+            ("len", [a]) => Instruction::Length(*a),
+            ("nth", [a, b]) => Instruction::Index(*a, *b),
+            ("drop", [a, b]) => Instruction::Drop(*a, *b),
+            ("concat", [a, b]) => Instruction::Concat(*a, *b),
+            ("lte", [a, b]) => {
+                self.cons(Instruction::GreaterThan(*a, *b), dest);
+                Instruction::LogicalNot(dest)
+            },
+            ("gte", [a, b]) => {
+                self.cons(Instruction::LessThan(*a, *b), dest);
+                Instruction::LogicalNot(dest)
+            },
+            ("print", [a]) => {
+                self.instructions.push(Instruction::Print(*a));
+                self.instructions.push(Instruction::Move { src: *a, dest });
+                return Ok(());
+            },
+            _ => return Err(StaticError::Program(format!("builtin {name} cannot be called with {} parameters", args.len()))),
+        };
+        self.cons(instruction, dest);
+        Ok(())
+    }
+
+    // The slot that holds the value of the symbol
+    fn value(&mut self, sym : &Symbol) -> Result<StackOffset, StaticError> {
+        let slot = self.slots[sym];
+        if self.locals.contains(sym) {
+            return Ok(slot);
+        }
+
+        if let Some(index) = self.globals.get(sym) {
+            self.instructions.push(Instruction::LoadGlobal(*index));
+        }
+        else if self.funs.contains(sym) {
+            self.fixups.push((self.instructions.len(), sym.clone()));
+            self.instructions.push(Instruction::ConsFunAddress(InstructionAddress(0)));
+        }
+        else if matches!( sym, Symbol::User(name) if BUILTINS.contains(&name.as_str()) ) {
+            return Err(unsupported("builtins used as values"));
+        }
+        else {
+            return Err(unknown(sym));
+        }
+
+        self.instructions.push(Instruction::MoveReturnPointerToStack(slot));
+        Ok(slot)
+    }
+
+    fn call(&mut self, fun : &Symbol) {
+        self.fixups.push((self.instructions.len(), fun.clone()));
+        self.instructions.push(Instruction::Call(InstructionAddress(0)));
+    }
+
+    fn cons(&mut self, instruction : Instruction, dest : StackOffset) {
+        self.instructions.push(instruction);
+        self.instructions.push(Instruction::MoveReturnPointerToStack(dest));
+    }
+}
+
+fn tag_name(cons_tag : &ConsTag) -> String {
+    match cons_tag {
+        ConsTag::User(name) | ConsTag::Anon(name) => name.to_string(),
+    }
+}
+
+fn unsupported(what : &str) -> StaticError {
+    StaticError::Program(format!("{what} cannot be run yet"))
+}
+
+fn unknown(sym : &Symbol) -> StaticError {
+    match sym {
+        Symbol::User(name) | Symbol::Anon(name) => StaticError::Program(format!("Encountered unknown name {name}")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsing::parser::parse;
+    use crate::execution::vm::{VM, SystemCalls, Exit};
+    use super::super::generator::generate;
+
+    // What the program printed and the errors that it stopped with
+    struct Prints(Vec<String>, Vec<String>);
+
+    impl SystemCalls for Prints {
+        fn print(&mut self, s : String) {
+            self.0.push(s);
+        }
+
+        fn fail(&mut self, message : String) {
+            self.1.push(message);
+        }
+    }
+
+    fn emit_program(input : &str) -> Result<(Vec<Instruction>, InstructionAddress), StaticError> {
+        let asts = parse(input).expect("test input should parse");
+        let (program, _) = generate(asts).expect("test input should generate");
+        emit(&program, &[SourceFile { name: "test.pat".to_string(), source: input.to_string() }])
+    }
+
+    fn run_to_exit(input : &str) -> (Exit, Prints) {
+        let (instructions, entry) = emit_program(input).expect("test input should emit");
+        let mut prints = Prints(vec![], vec![]);
+        let exit = VM::new(instructions, entry).run(&mut prints);
+        (exit, prints)
+    }
+
+    fn run(input : &str) -> Vec<String> {
+        let (exit, prints) = run_to_exit(input);
+        assert_eq!( exit, Exit::Success, "test program failed: {:?}", prints.1 );
+        prints.0
+    }
+
+    #[test]
+    fn should_run_main() {
+        let output = run("
+            fun fact(n : Number) -> Number = if n == 0 then 1 else n * fact(n - 1);
+            fun main() -> Number = print(fact(5));
+        ");
+
+        assert_eq!( output, vec!["120"] );
+    }
+
+    #[test]
+    fn should_evaluate_constants_once_in_dependency_order() {
+        let output = run("
+            let b = print(a + 1);
+            let a = print(1);
+            fun twice() -> Number = b + b;
+            fun main() -> Number = print(twice() + a);
+        ");
+
+        assert_eq!( output, vec!["1", "2", "5"] );
+    }
+
+    #[test]
+    fn should_call_function_values() {
+        let output = run("
+            fun apply(f : fun(Number) -> Number, x : Number) -> Number = f(x);
+            fun inc(x : Number) -> Number = x + 1;
+            let g = inc;
+            fun main() -> Bool = print(apply(inc, 1) <= g(2) && !(3 >= 4));
+        ");
+
+        assert_eq!( output, vec!["true"] );
+    }
+
+    #[test]
+    fn should_run_float_arithmetic() {
+        let output = run("
+            fun half(x : Float) -> Float = x / 2.0;
+            fun abs(x : Float) -> Float = if x < 0 then -x else x;
+            let scale : Float = 3.0;
+            fun main() -> Bool = let _ = print(1.5 + 2.0) in
+                                 let _ = print(half(7.0) * scale) in
+                                 let _ = print(abs(-2.5) % 2) in
+                                 print(half(1.0) >= 0.5 && 2 < 3);
+        ");
+
+        assert_eq!( output, vec!["3.5", "10.5", "0.5", "true"] );
+    }
+
+    #[test]
+    fn should_run_float_arithmetic_in_untyped_lambda() {
+        let output = run("
+            fun twice(f : fun(Float) -> Float, x : Float) -> Float = f(f(x));
+            fun main() -> Float = let _ = print(twice(|y| y + y, 1.5)) in
+                                  let _ = print(twice(|y| y * 2 - 1, 3)) in
+                                  print(twice(|y| if y < 1 then y / 2 else y, 0.5));
+        ");
+
+        assert_eq!( output, vec!["6.0", "9", "0.125"] );
+    }
+
+    #[test]
+    fn should_run_matches() {
+        let output = run(r#"
+            fun size(n : Number) -> String = match n {
+                0 => "zero",
+                1 | 2 => "few",
+                x if x < 0 => "negative",
+                3..=9 => "some",
+                _ => "many"
+            };
+            fun greet(s : String, loud : Bool) -> String = match (loud) { true => match s { "hi" => "HI", x => x }, false => s };
+            fun show(s : String) -> Number = let _ = print(s) in 0;
+            fun main() -> Number = show(size(0)) + show(size(2)) + show(size(-4)) + show(size(5)) + show(size(10)) + show(greet("hi", true)) + show(greet("yo", true));
+        "#);
+
+        assert_eq!( output, vec!["zero", "few", "negative", "some", "many", "HI", "yo"] );
+    }
+
+    #[test]
+    fn should_bind_variables_of_matching_alternative() {
+        let output = run("
+            fun f(n : Number) -> Number = match n { (x @ (1 | 2)) | (x @ 5) if x == n => x * 10, y => y };
+            fun g((x @ 7) | x : Number) -> Number = x;
+            data Pair = Pair(Number, Number);
+            fun h(p : Pair) -> Number = match p { Pair(x, 0) | Pair(0, x) => print(x), _ => 1 };
+            fun main() -> Number = let _ = h(Pair(0, 0)) in print(f(2) + f(5) + f(4) + g(1));
+        ");
+
+        // Only the first alternative that matches runs the case
+        assert_eq!( output, vec!["0", "75"] );
+    }
+
+    #[test]
+    fn should_match_constructed_data() {
+        let output = run("
+            data List = Cons(Number, List) | Nil;
+            fun sum(l : List) -> Number = match l { Cons(h, t) => h + sum(t), Nil => 0 };
+            fun main() -> Number = let l = Cons(1, Cons(2, Cons(3, Nil))) in let _ = print(l) in print(sum(l));
+        ");
+
+        assert_eq!( output, vec!["Cons(1, Cons(2, Cons(3, Nil)))", "6"] );
+    }
+
+    #[test]
+    fn should_access_record_fields_and_tuples() {
+        let output = run(r#"
+            data Person = Person { name : String, age : Number };
+            fun older(p : Person) -> Person = Person { age: p.age + 1, name: p.name };
+            fun swap((a, b) : (Number, String)) -> (String, Number) = (b, a);
+            fun main() -> Bool = let p = older(Person { name: "x", age: 5 }) in
+                                 let _ = print(p) in
+                                 let _ = print(swap((1, "y"))) in
+                                 print(p == Person { name: "x", age: 6 });
+        "#);
+
+        assert_eq!( output, vec!["Person { name: x, age: 6 }", "(y, 1)", "true"] );
+    }
+
+    #[test]
+    fn should_not_match_tuple_of_other_arity() {
+        let output = run("
+            fun main() -> Number = let first = |t| match t { (a, _) => a, _ => 0 } in
+                                   let _ = print((5, 6, 7)) in
+                                   let _ = print(first((5, 6, 7))) in
+                                   print(first((5, 6)));
+        ");
+
+        assert_eq!( output, vec!["(5, 6, 7)", "0", "5"] );
+    }
+
+    #[test]
+    fn should_run_array_builtins_and_patterns() {
+        let output = run("
+            fun sum(xs : [Number]) -> Number = match xs { [] => 0, [x | rest] => x + sum(rest) };
+            fun main() -> Number = let xs = [1, 2, 3].concat([4]) in
+                                   let _ = print(xs) in
+                                   let _ = print(xs.drop(2)) in
+                                   let _ = print(xs.nth(1) + xs.len()) in
+                                   print(sum(xs));
+        ");
+
+        assert_eq!( output, vec!["[1, 2, 3, 4]", "[3, 4]", "6", "10"] );
+    }
+
+    #[test]
+    fn should_call_closures() {
+        let output = run("
+            fun adder(n : Number) -> fun(Number) -> Number = |x| x + n;
+            fun twice(f : fun(Number) -> Number, x : Number) -> Number = f(f(x));
+            fun main() -> Number = let add2 = adder(2) in print(twice(add2, 1) + is_even(10)) 
+                where is_even = |i| if i == 0 then 1 else is_odd(i - 1), is_odd = |i| if i == 0 then 0 else is_even(i - 1);
+        ");
+
+        assert_eq!( output, vec!["6"] );
+    }
+
+    #[test]
+    fn should_run_path_pattern() {
+        let output = run("
+            data Tree = Node(Tree, Tree) | Leaf(Number);
+            fun main() -> Number = let t = Node(Leaf(1), Node(Leaf(2), Leaf(3))) in
+                                   let _ = print({| Node(!, !), Leaf(x) |}.path(t)) in
+                                   let _ = print({| Node(!2, !1), Node(a, !), Leaf(x) |}.path(Node(Node(Leaf(1), Leaf(2)), Node(Leaf(3), Leaf(4))))) in
+                                   print({| Node(l, _) if l == Leaf(1) |}.path(t).len());
+        ");
+
+        assert_eq!( output, vec!["[{ x: 1 }]", "[{ a: Leaf(3), x: 4 }, { a: Leaf(1), x: 2 }]", "1"] );
+    }
+
+    #[test]
+    fn should_run_array_pattern() {
+        let output = run("
+            data Pair = Pair(Number, Number);
+            fun main() -> Number = let _ = print([| x, Pair(a, 5), [y | _] |].match_all([1, Pair(2, 5), [3, 4]])) in
+                                   print([| x, Pair(a, 5) |].match_all([1, Pair(2, 6)]).len());
+        ");
+
+        assert_eq!( output, vec!["[{ x: 1, a: 2, y: 3 }]", "0"] );
+    }
+
+    #[test]
+    fn should_collect_variables_under_quantifiers() {
+        let output = run("
+            data Item = Pair(Number, Number) | End;
+            fun main() -> Number = let _ = print([| _*, x, _* |].match_all([1, 2, 3])) in
+                                   let _ = print([| Pair(a, _)*, End |].match_all([Pair(1, 0), Pair(2, 0), End])) in
+                                   let _ = print([| x+ |].match_all([]).len()) in
+                                   let _ = print([| x?, y* |].match_all([1])) in
+                                   let _ = print([| x{1,2}, _* |].match_all([1, 2, 3])) in
+                                   let _ = print([| n, _{n}, y |].match_all([2, 7, 8, 9])) in
+                                   print([| (x{2})* |].match_all([1, 2, 3, 4]));
+        ");
+
+        assert_eq!( output, vec![ "[{ x: 1 }, { x: 2 }, { x: 3 }]"
+                                , "[{ a: [1, 2] }]"
+                                , "0"
+                                , "[{ x: [], y: [1] }, { x: [1], y: [] }]"
+                                , "[{ x: [1] }, { x: [1, 2] }]"
+                                , "[{ n: 2, y: 9 }]"
+                                , "[{ x: [[1, 2], [3, 4]] }]"
+                                ] );
+    }
+
+    #[test]
+    fn should_match_ranges_in_path_and_array_patterns() {
+        let output = run("
+            data Tree = Node(Tree, Tree) | Leaf(Number);
+            fun main() -> Number = let t = Node(Leaf(1), Node(Leaf(5), Leaf(10))) in
+                                   let _ = print({| Node(!, !), Leaf(x @ 2..=10) |}.path(Node(Leaf(1), Leaf(10)))) in
+                                   let _ = print({| Node(!, !), Node(Leaf(x @ <=5), Leaf(>5)) |}.path(t)) in
+                                   let _ = print([| _*, x @ 2..10, _* |].match_all([1, 2, 9, 10])) in
+                                   print([| (x @ >0)+, ..=0, _* |].match_all([3, 4, 0, 5]));
+        ");
+
+        assert_eq!( output, vec![ "[{ x: 10 }]"
+                                , "[{ x: 5 }]"
+                                , "[{ x: 2 }, { x: 9 }]"
+                                , "[{ x: [3, 4] }]"
+                                ] );
+    }
+
+    #[test]
+    fn should_try_alternatives_in_order() {
+        let output = run("
+            data Tree = Node(Tree, Tree) | Leaf(Number);
+            data Pair = Pair(Number, Number);
+            fun main() -> Number = let _ = print({| Node(Leaf(x), _) | Node(_, Leaf(x)) |}.path(Node(Leaf(1), Leaf(2)))) in
+                                   let _ = print({| Node(!, _) | Node(_, !), Leaf(x) |}.path(Node(Leaf(3), Node(Leaf(4), Leaf(5))))) in
+                                   let _ = print([| _*, Pair(x, 0) | Pair(0, x), _* |].match_all([Pair(1, 0), Pair(5, 5), Pair(0, 0)])) in
+                                   print([| (Pair(x, 0) | Pair(0, x))* |].match_all([Pair(1, 0), Pair(0, 2)]));
+        ");
+
+        assert_eq!( output, vec![ "[{ x: 1 }, { x: 2 }]"
+                                , "[{ x: 3 }]"
+                                , "[{ x: 1 }, { x: 0 }, { x: 0 }]"
+                                , "[{ x: [1, 2] }]"
+                                ] );
+    }
+
+    #[test]
+    fn should_bind_results_of_and_pattern() {
+        let output = run("
+            data Tree = Node(Tree, Tree) | Leaf(Number);
+            fun main() -> Number = let leaf = {| Leaf(x) |} in
+                                   print({| Node(!, &leaf:left) |}.path(Node(Leaf(1), Leaf(2))));
+        ");
+
+        assert_eq!( output, vec!["[{ left: [{ x: 2 }] }]"] );
+    }
+
+    #[test]
+    fn should_stop_when_nothing_matches() {
+        let (exit, prints) = run_to_exit("
+            fun f(n : Number) -> Number = match n { 1 => 1 };
+            fun main() -> Number = let _ = print(1) in print(f(2));
+        ");
+
+        assert_eq!( exit, Exit::Failure );
+        assert_eq!( prints.0, vec!["1"] );
+        assert_eq!( prints.1.len(), 1 );
+        assert!( prints.1[0].starts_with("error: match expression did not match\n --> test.pat:2:43\n") );
+    }
+
+    #[test]
+    fn should_reject_program_without_main() {
+        let result = emit_program("fun f() -> Number = 1;");
+
+        assert!( matches!( result, Err(StaticError::Program(message)) if message == "program has no main function" ) );
+    }
+
+    #[test]
+    fn should_reject_unknown_function() {
+        let result = emit_program("fun main() -> Number = missing(1);");
+
+        assert!( matches!( result, Err(StaticError::Program(message)) if message == "Encountered unknown name missing" ) );
+    }
+}
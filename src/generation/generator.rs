@@ -1,17 +1,22 @@
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::mem;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::ast::Ast;
+use crate::ast::{self, Ast, Span};
 
-use crate::ir::{ Ir
+use crate::ir::{ Program
+               , Ir
                , Symbol
                , ConsTag
                , Statement
                , Expr
+               , SlotAccessType
                };
 
 use super::data::{ StaticError
+                 , StaticWarning
                  , Type
                  , RowType
                  , ConcreteType
@@ -19,6 +24,11 @@ use super::data::{ StaticError
                  };
 
 use super::type_info;
+use super::named_patterns;
+use super::bindings;
+use super::refutability;
+
+use crate::visit;
 
 static SYM_GEN_COUNT : AtomicUsize = AtomicUsize::new(0);
 
@@ -28,15 +38,15 @@ fn anon_sym(base : &str) -> Symbol {
     Symbol::Anon(format!("sym_gen_{base}_{post_fix}"))
 }
 
-fn anon_tag(base : &str) -> ConsTag {
-    let post_fix = SYM_GEN_COUNT.fetch_add(1, Ordering::Relaxed);
-
-    ConsTag::Anon(format!("cons_gen_{base}_{post_fix}"))
+// Every tuple with the same number of items shares a constructor.  The '#' keeps it apart from the
+// user's constructors and from anon records.
+fn tuple_tag(arity : usize) -> ConsTag {
+    ConsTag::Anon(format!("#tuple_{arity}"))
 }
 
 
 
-pub fn generate( asts : Vec<Ast> ) -> Result<Vec<Ir>, StaticError> {
+pub fn generate( asts : Vec<Ast> ) -> Result<(Program, Vec<StaticWarning>), StaticError> {
 
     /* TODO : no cycles
               order resulting ir so that nothing references things that later show up
@@ -44,36 +54,1300 @@ pub fn generate( asts : Vec<Ast> ) -> Result<Vec<Ir>, StaticError> {
     
     */
 
+    let asts = named_patterns::inline(asts)?;
+    bindings::check(&asts)?;
+
     let (datas, funcs) : (Vec<Ast>, Vec<Ast>) = asts.into_iter().partition(|tl| match tl {
         Ast::DataDef { .. } => true,
-        Ast::FunDef { .. } => false,
+        Ast::FunDef { .. } | Ast::LetDef { .. } => false,
+        Ast::Import { .. } => panic!("generate should not have any imports; they are resolved by the loader"),
+        Ast::PatternDef { .. } => panic!("generate should not have any pattern defs; they are inlined"),
+    });
+
+    let (_, type_to_info) = type_info::determine_type_info(datas)?;
+
+    let warnings = refutability::check(&funcs, &type_to_info);
+
+    let fun_types = fun_types(&funcs)?;
+
+    let context = Context::new(&type_to_info, &funcs, fun_types);
+
+    let mut irs = vec![];
+    for f in &funcs {
+        irs.append(&mut lower_fun_def(f, &context)?);
+    }
+
+    let constants = order_constants(&funcs, &irs)?;
+    let entry = entry(&funcs)?;
+    let fields = context.cons.values().filter_map(|info| Some((info.tag.clone(), info.fields.clone()?))).collect();
+
+    Ok((Program { irs, constants, entry, fields }, warnings))
+}
+
+// The function followed by every lambda that was lifted out of it.  A top level let is lowered
+// into a function without any parameters that is called once before main.
+fn lower_fun_def(fun : &Ast, context : &Context) -> Result<Vec<Ir>, StaticError> {
+    let (name, params, expr) = match fun {
+        Ast::FunDef { name, params, expr, .. } => (name, &params[..], expr),
+        Ast::LetDef { name, expr, .. } => (name, &[][..], expr),
+        _ => panic!("lower_fun_def should not have any data defs"),
+    };
+
+    let mut scope = Scope::new();
+    let (params, expr) = bind_params(params, expr, &mut scope)?;
+
+    let mut statements = vec![];
+    let result = lower_expr(&expr, context, &mut scope, &mut statements)?;
+    statements.push(Statement::Return(result));
+
+    let mut irs = vec![Ir { name: Symbol::User(name.to_string()), params, statements }];
+    irs.append(&mut scope.lifted);
+    Ok(irs)
+}
+
+// A parameter that is only a variable is bound directly.  Any other pattern gets a fresh parameter
+// which the body matches against, so '|Cons(h, _)| h' is lowered like '|p| match p { Cons(h, _) => h }'.
+fn bind_params<'a>(params : &[ast::FunParam], expr : &'a ast::Expr, scope : &mut Scope) -> Result<(Vec<Symbol>, Cow<'a, ast::Expr>), StaticError> {
+    let mut syms = vec![];
+    let mut matched = vec![];
+    for param in params {
+        let (name, sym) = match &param.pattern.kind {
+            ast::StandardPatternKind::Variable(name) => (name.to_string(), Symbol::User(name.to_string())),
+            _ => {
+                if let Some(t) = &param.t {
+                    check_tuple_arity(&param.pattern, &type_info::ast_to_ir_type(t))?;
+                }
+                let sym = anon_sym("param");
+                // Variables can't start with '#', so nothing in the body can shadow it
+                let name = format!("#{}", symbol_name(&sym));
+                matched.push((name.clone(), &param.pattern));
+                (name, sym)
+            },
+        };
+        scope.push(&name, sym.clone());
+        syms.push(sym);
+    }
+
+    let expr = matched.into_iter().rev().fold(Cow::Borrowed(expr), |body, (name, pattern)| {
+        let span = body.span;
+        let scrutinee = ast::Expr { kind: ast::ExprKind::Variable(name), span: pattern.span };
+        let cases = vec![ast::Case { pattern: pattern.clone(), expr: body.into_owned() }];
+        Cow::Owned(ast::Expr { kind: ast::ExprKind::Match { expr: Box::new(scrutinee), cases }, span })
     });
 
-    let (tag_to_type, type_to_info) = type_info::determine_type_info(datas)?;
+    Ok((syms, expr))
+}
+
+fn entry(funcs : &[Ast]) -> Result<Option<Symbol>, StaticError> {
+    for fun in funcs {
+        match fun {
+            Ast::FunDef { name, params, span, .. } if name == "main" => {
+                if !params.is_empty() {
+                    return Err(StaticError::Fatal("main must not take any parameters".to_string(), *span));
+                }
+                return Ok(Some(Symbol::User(name.to_string())));
+            },
+            _ => { },
+        }
+    }
+    Ok(None)
+}
+
+// Each top level let is evaluated after every top level let that it uses, including the ones that
+// it only uses through the functions that it calls.
+fn order_constants(funcs : &[Ast], irs : &[Ir]) -> Result<Vec<Symbol>, StaticError> {
+    fn order( name : &Symbol
+            , uses : &HashMap<Symbol, Vec<Symbol>>
+            , spans : &HashMap<Symbol, Span>
+            , stack : &mut Vec<Symbol>
+            , ordered : &mut Vec<Symbol>
+            ) -> Result<(), StaticError> {
+
+        if ordered.contains(name) {
+            return Ok(());
+        }
+
+        if let Some(index) = stack.iter().position(|n| n == name) {
+            let cycle = stack[index..].iter()
+                                      .chain(std::iter::once(name))
+                                      .map(symbol_name)
+                                      .collect::<Vec<_>>()
+                                      .join(" -> ");
+            return Err(StaticError::Fatal(format!("Encountered top level let cycle {cycle}"), spans[&stack[index]]));
+        }
+
+        stack.push(name.clone());
+        for used in &uses[name] {
+            order(used, uses, spans, stack, ordered)?;
+        }
+        stack.pop();
+
+        ordered.push(name.clone());
+        Ok(())
+    }
+
+    let lets = funcs.iter().filter_map(|f| match f {
+        Ast::LetDef { name, span, .. } => Some((Symbol::User(name.to_string()), *span)),
+        _ => None,
+    }).collect::<Vec<_>>();
+
+    for (name, span) in &lets {
+        if irs.iter().filter(|ir| ir.name == *name).count() > 1 {
+            return Err(StaticError::Fatal(format!("Encountered already defined top level let {}", symbol_name(name)), *span));
+        }
+    }
+
+    let spans = lets.into_iter().collect::<HashMap<_, _>>();
+    let irs = irs.iter().map(|ir| (&ir.name, ir)).collect::<HashMap<_, _>>();
+
+    let mut uses = HashMap::new();
+    for name in spans.keys() {
+        let mut seen = vec![name.clone()];
+        let mut todo = vec![name.clone()];
+        let mut used = vec![];
+        while let Some(n) = todo.pop() {
+            for sym in irs[&n].free_symbols() {
+                if spans.contains_key(&sym) {
+                    if !used.contains(&sym) {
+                        used.push(sym);
+                    }
+                }
+                else if irs.contains_key(&sym) && !seen.contains(&sym) {
+                    seen.push(sym.clone());
+                    todo.push(sym);
+                }
+            }
+        }
+        uses.insert(name.clone(), used);
+    }
+
+    // Source order is kept for the lets that do not depend on each other
+    let mut names = spans.iter().map(|(name, span)| (name.clone(), *span)).collect::<Vec<_>>();
+    names.sort_by_key(|(_, span)| (span.start.line, span.start.column));
+
+    let mut ordered = vec![];
+    for (name, _) in names {
+        order(&name, &uses, &spans, &mut vec![], &mut ordered)?;
+    }
+    Ok(ordered)
+}
+
+fn symbol_name(sym : &Symbol) -> &str {
+    match sym {
+        Symbol::User(name) | Symbol::Anon(name) => name,
+    }
+}
+
+// Information about the user defined data types that is needed while lowering expressions.
+struct Context {
+    cons : HashMap<String, ConsInfo>,
+    anon_fields : Vec<String>, // Every field name that shows up in an anon type
+    fun_types : HashMap<Symbol, Type>,
+}
+
+impl Context {
+    fn new(type_to_info : &HashMap<ConcreteType, Vec<ConsInfo>>, funcs : &[Ast], fun_types : HashMap<Symbol, Type>) -> Self {
+        fn rows(t : &Type, out : &mut Vec<String>) {
+            match t {
+                Type::Infer | Type::Generic(_) | Type::Concrete(_) => { },
+                Type::Fun { input, output } => {
+                    input.iter().for_each(|t| rows(t, out));
+                    rows(output, out);
+                },
+                Type::Index { params, .. } | Type::Tuple(params) => params.iter().for_each(|t| rows(t, out)),
+                Type::Anon(row_types) => {
+                    for RowType { name, t } in row_types {
+                        out.push(name.to_string());
+                        rows(t, out);
+                    }
+                },
+            }
+        }
+
+        let cons = type_to_info.values()
+                               .flatten()
+                               .filter_map(|info| match &info.tag {
+                                   ConsTag::User(name) => Some((name.to_string(), info.clone())),
+                                   ConsTag::Anon(_) => None,
+                               })
+                               .collect::<HashMap<_, _>>();
+
+        let mut anon_fields = vec![];
+        for info in cons.values() {
+            info.ts.iter().for_each(|t| rows(t, &mut anon_fields));
+        }
+        for fun in funcs {
+            let expr = match fun {
+                Ast::FunDef { params, return_type, expr, .. } => {
+                    params.iter().filter_map(|p| p.t.as_ref()).for_each(|t| rows(&type_info::ast_to_ir_type(t), &mut anon_fields));
+                    rows(&type_info::ast_to_ir_type(return_type), &mut anon_fields);
+                    expr
+                },
+                Ast::LetDef { t, expr, .. } => {
+                    t.iter().for_each(|t| rows(&type_info::ast_to_ir_type(t), &mut anon_fields));
+                    expr
+                },
+                _ => continue,
+            };
+            // The results of path and array patterns have a field for each variable
+            visit::exprs(expr, &mut |e| match &e.kind {
+                ast::ExprKind::PathPattern(_) | ast::ExprKind::ArrayPattern(_) => rows(&result_type(e), &mut anon_fields),
+                _ => { },
+            });
+        }
+
+        Context { cons, anon_fields, fun_types }
+    }
+
+    fn has_field(&self, field : &str) -> bool {
+        self.anon_fields.iter().any(|f| f == field)
+        || self.cons.values().any(|info| matches!( &info.fields, Some(fields) if fields.iter().any(|f| f == field) ))
+    }
+}
+
+// Local variable name to the symbol that holds its value.  Names that are not
+// in scope refer to top level functions (or builtins).
+struct Scope {
+    locals : Vec<(String, Symbol)>,
+    lifted : Vec<Ir>, // Lambdas lowered into functions of their own
+    nexts : Vec<(Option<i64>, Symbol)>, // The values marked by the '!'s of the path pattern stage being matched
+}
+
+impl Scope {
+    fn new() -> Self {
+        Scope { locals: vec![], lifted: vec![], nexts: vec![] }
+    }
+
+    fn push(&mut self, name : &str, sym : Symbol) {
+        self.locals.push((name.to_string(), sym));
+    }
+
+    fn pop(&mut self) {
+        self.locals.pop();
+    }
+
+    fn is_local(&self, name : &str) -> bool {
+        self.locals.iter().any(|(n, _)| n == name)
+    }
+
+    fn lookup(&self, name : &str) -> Symbol {
+        match self.locals.iter().rev().find(|(n, _)| n == name) {
+            Some((_, sym)) => sym.clone(),
+            None => Symbol::User(name.to_string()),
+        }
+    }
+}
+
+fn assign(base : &str, expr : Expr, statements : &mut Vec<Statement>) -> Symbol {
+    let name = anon_sym(base);
+    statements.push(Statement::Assign { name: name.clone(), expr });
+    name
+}
+
+fn call(builtin : &str, params : Vec<Symbol>, statements : &mut Vec<Statement>) -> Symbol {
+    assign(builtin, Expr::FunCall { name: Symbol::User(builtin.to_string()), params }, statements)
+}
+
+// Lambdas are lifted into functions that take their environment as the first parameter.  All of the
+// lambdas in a group (a 'let rec' or just a single lambda) share one environment with every local that
+// any of them uses, so inside of the group a reference to another member of the group is a new closure
+// over that same environment instead of a cycle between environments.
+fn lower_closures( names : &[String]
+                 , lambdas : &[&ast::Expr]
+                 , context : &Context
+                 , scope : &mut Scope
+                 , statements : &mut Vec<Statement>
+                 ) -> Result<Vec<Symbol>, StaticError> {
+
+    let mut used = vec![];
+    for lambda in lambdas {
+        visit::exprs(lambda, &mut |e| if let ast::ExprKind::Variable(name) = &e.kind { used.push(name.to_string()) });
+    }
+
+    let mut captured : Vec<(String, Symbol)> = vec![];
+    for (name, _) in &scope.locals {
+        if used.contains(name) && !names.contains(name) && !captured.iter().any(|(c, _)| c == name) {
+            captured.push((name.to_string(), scope.lookup(name)));
+        }
+    }
+
+    let env = assign("env", Expr::Environment(captured.iter().map(|(_, sym)| sym.clone()).collect()), statements);
+    let funs = lambdas.iter().map(|_| anon_sym("lambda")).collect::<Vec<_>>();
+
+    for (lambda, fun) in lambdas.iter().zip(&funs) {
+        let (params, expr) = match &lambda.kind {
+            ast::ExprKind::Lambda { params, expr, .. } => (params, expr),
+            _ => panic!("lower_closures should only have lambdas"),
+        };
+
+        let env_param = anon_sym("env");
+        let mut inner = Scope { locals: vec![], lifted: mem::take(&mut scope.lifted), nexts: vec![] };
+        let mut body = vec![];
+
+        enter_environment(&captured, &env_param, &mut inner, &mut body);
+        for (name, f) in names.iter().zip(&funs) {
+            let sym = assign("closure", Expr::Closure { fun: f.clone(), env: env_param.clone() }, &mut body);
+            inner.push(name, sym);
+        }
+        let (params, expr) = bind_params(params, expr, &mut inner)?;
+
+        let result = lower_expr(&expr, context, &mut inner, &mut body);
+        scope.lifted = inner.lifted;
+        body.push(Statement::Return(result?));
+
+        let params = std::iter::once(env_param).chain(params).collect();
+        scope.lifted.push(Ir { name: fun.clone(), params, statements: body });
+    }
+
+    Ok(funs.into_iter().map(|fun| assign("closure", Expr::Closure { fun, env: env.clone() }, statements)).collect())
+}
+
+// Each captured local is read out of the environment parameter at the start of the lifted function.
+fn enter_environment( captured : &[(String, Symbol)]
+                    , env : &Symbol
+                    , inner : &mut Scope
+                    , statements : &mut Vec<Statement>
+                    ) {
+
+    for (i, (name, _)) in captured.iter().enumerate() {
+        let sym = assign("captured", Expr::SlotAccess { data: env.clone(), slot: SlotAccessType::Index(i) }, statements);
+        inner.push(name, sym);
+    }
+}
+
+// Produces the statements of a lifted function and returns the symbol that it returns.  It is given
+// a closure over the function itself, so that the function can call itself, and its parameters.
+type Body<'a> = dyn FnMut(&mut Scope, &Symbol, &[Symbol], &mut Vec<Statement>) -> Result<Symbol, StaticError> + 'a;
+
+// Like a lambda, except that the lifted function captures every local in scope because the code that
+// the generator puts inside of it can refer to any of them.
+fn lift_fun( param_count : usize
+           , scope : &mut Scope
+           , statements : &mut Vec<Statement>
+           , body : &mut Body<'_>
+           ) -> Result<Symbol, StaticError> {
+
+    let mut captured : Vec<(String, Symbol)> = vec![];
+    for (name, _) in &scope.locals {
+        if name != RESULTS && !captured.iter().any(|(c, _)| c == name) {
+            captured.push((name.to_string(), scope.lookup(name)));
+        }
+    }
+
+    let env = assign("env", Expr::Environment(captured.iter().map(|(_, sym)| sym.clone()).collect()), statements);
+    let fun = anon_sym("lambda");
+
+    let env_param = anon_sym("env");
+    let params = (0..param_count).map(|_| anon_sym("param")).collect::<Vec<_>>();
+    let mut inner = Scope { locals: vec![], lifted: mem::take(&mut scope.lifted), nexts: vec![] };
+    let mut lifted = vec![];
+
+    enter_environment(&captured, &env_param, &mut inner, &mut lifted);
+    let itself = assign("closure", Expr::Closure { fun: fun.clone(), env: env_param.clone() }, &mut lifted);
+
+    let result = body(&mut inner, &itself, &params, &mut lifted);
+    scope.lifted = inner.lifted;
+    lifted.push(Statement::Return(result?));
+
+    let params = std::iter::once(env_param).chain(params).collect();
+    scope.lifted.push(Ir { name: fun.clone(), params, statements: lifted });
+
+    Ok(assign("closure", Expr::Closure { fun, env }, statements))
+}
+
+fn lower_exprs(exprs : &[ast::Expr], context : &Context, scope : &mut Scope, statements : &mut Vec<Statement>) -> Result<Vec<Symbol>, StaticError> {
+    let mut ret = vec![];
+    for expr in exprs {
+        ret.push(lower_expr(expr, context, scope, statements)?);
+    }
+    Ok(ret)
+}
+
+fn lower_expr(expr : &ast::Expr, context : &Context, scope : &mut Scope, statements : &mut Vec<Statement>) -> Result<Symbol, StaticError> {
+    match &expr.kind {
+        ast::ExprKind::Number(n) => Ok(assign("number", Expr::Number(*n), statements)),
+        ast::ExprKind::Float(f) => Ok(assign("float", Expr::Float(*f), statements)),
+        ast::ExprKind::Bool(b) => Ok(assign("bool", Expr::Bool(*b), statements)),
+        ast::ExprKind::String(s) => Ok(assign("string", Expr::String(s.to_string()), statements)),
+        ast::ExprKind::Variable(name) => Ok(scope.lookup(name)),
+        ast::ExprKind::Cons { name, params } => {
+            let slots_assigns = lower_exprs(params, context, scope, statements)?.into_iter().map(Expr::Variable).collect();
+            Ok(assign("cons", Expr::Constructor { cons_tag: ConsTag::User(name.to_string()), slots_assigns }, statements))
+        },
+        ast::ExprKind::Tuple(items) => {
+            let slots_assigns = lower_exprs(items, context, scope, statements)?.into_iter().map(Expr::Variable).collect();
+            Ok(assign("tuple", Expr::Constructor { cons_tag: tuple_tag(items.len()), slots_assigns }, statements))
+        },
+        ast::ExprKind::Record { name, fields } => {
+            let declared = match context.cons.get(name) {
+                Some(ConsInfo { fields: Some(declared), .. }) => declared,
+                Some(_) => return Err(StaticError::Fatal(format!("Constructor {name} does not have named fields"), expr.span)),
+                None => return Err(StaticError::Fatal(format!("Encountered unknown constructor {name}"), expr.span)),
+            };
+
+            for (i, (field, value)) in fields.iter().enumerate() {
+                if !declared.contains(field) {
+                    return Err(StaticError::Fatal(format!("Constructor {name} does not have field {field}"), value.span));
+                }
+                if fields[..i].iter().any(|(f, _)| f == field) {
+                    return Err(StaticError::Fatal(format!("Field {field} is assigned more than once"), value.span));
+                }
+            }
+
+            if let Some(missing) = declared.iter().find(|d| !fields.iter().any(|(f, _)| f == *d)) {
+                return Err(StaticError::Fatal(format!("Constructor {name} is missing field {missing}"), expr.span));
+            }
+
+            // Fields are evaluated in the order they are written but stored in the order they are declared
+            let mut values = vec![];
+            for (field, value) in fields {
+                values.push((field, lower_expr(value, context, scope, statements)?));
+            }
+
+            let slots_assigns = declared.iter()
+                                        .map(|d| values.iter().find(|(f, _)| *f == d).expect("record field should have been checked").1.clone())
+                                        .map(Expr::Variable)
+                                        .collect();
+
+            Ok(assign("record", Expr::Constructor { cons_tag: ConsTag::User(name.to_string()), slots_assigns }, statements))
+        },
+        ast::ExprKind::FieldAccess { expr: target, field } => {
+            if !context.has_field(field) {
+                return Err(StaticError::Fatal(format!("No record or anon type has a field named {field}"), expr.span));
+            }
+            let data = lower_expr(target, context, scope, statements)?;
+            Ok(assign("field", Expr::SlotAccess { data, slot: SlotAccessType::Field(field.to_string()) }, statements))
+        },
+        ast::ExprKind::Let { pattern, value, expr: body, .. } => {
+            let value = lower_expr(value, context, scope, statements)?;
+            match &pattern.kind {
+                ast::StandardPatternKind::Variable(name) => {
+                    scope.push(name, value);
+                    let ret = lower_expr(body, context, scope, statements);
+                    scope.pop();
+                    ret
+                },
+                _ => lower_match(value, &[(pattern, body)], failure("let pattern", expr.span), context, scope, statements),
+            }
+        },
+        ast::ExprKind::LetRec { bindings, expr } => {
+            let names = bindings.iter().map(|b| b.name.to_string()).collect::<Vec<_>>();
+            let lambdas = bindings.iter().map(|b| &b.value).collect::<Vec<_>>();
+            let closures = lower_closures(&names, &lambdas, context, scope, statements)?;
+            for (name, closure) in names.iter().zip(closures) {
+                scope.push(name, closure);
+            }
+            let ret = lower_expr(expr, context, scope, statements);
+            names.iter().for_each(|_| scope.pop());
+            ret
+        },
+        ast::ExprKind::Lambda { .. } => {
+            let closures = lower_closures(&[], &[expr], context, scope, statements)?;
+            Ok(closures.into_iter().next().expect("lower_closures should return a closure for each lambda"))
+        },
+        ast::ExprKind::If { condition, then_expr, else_expr } => {
+            let condition = lower_expr(condition, context, scope, statements)?;
+            let result = anon_sym("if");
+
+            let mut then_statements = vec![];
+            let then_result = lower_expr(then_expr, context, scope, &mut then_statements)?;
+            then_statements.push(Statement::Assign { name: result.clone(), expr: Expr::Variable(then_result.clone()) });
+            statements.push(Statement::If { target: condition.clone(), statements: then_statements });
+
+            let not_condition = assign( "not"
+                                      , Expr::FunCall { name: Symbol::User("not".to_string()), params: vec![condition] }
+                                      , statements );
+
+            let mut else_statements = vec![];
+            let else_result = lower_expr(else_expr, context, scope, &mut else_statements)?;
+            else_statements.push(Statement::Assign { name: result.clone(), expr: Expr::Variable(else_result.clone()) });
+            statements.push(Statement::If { target: not_condition, statements: else_statements });
+
+            Ok(result)
+        },
+        ast::ExprKind::FunCall { fun_expr, params } => {
+            if let ast::ExprKind::Variable(name) = &fun_expr.kind {
+                let expected = match context.fun_types.get(&Symbol::User(name.to_string())) {
+                    Some(Type::Fun { input, .. }) if !scope.is_local(name) => input.len(),
+                    None if !scope.is_local(name) && PATTERN_BUILTINS.contains(&name.as_str()) => match &params[..] {
+                        [pattern, value] => {
+                            let pattern = lower_expr(pattern, context, scope, statements)?;
+                            let value = lower_expr(value, context, scope, statements)?;
+                            return Ok(assign("call", Expr::FunCall { name: pattern, params: vec![value] }, statements));
+                        },
+                        _ => 2,
+                    },
+                    _ => params.len(),
+                };
+                if expected != params.len() {
+                    return Err(StaticError::Fatal(format!("Function {name} takes {expected} parameters but was given {}", params.len()), expr.span));
+                }
+            }
+            let name = lower_expr(fun_expr, context, scope, statements)?;
+            let params = lower_exprs(params, context, scope, statements)?;
+            Ok(assign("call", Expr::FunCall { name, params }, statements))
+        },
+        ast::ExprKind::Array(items) => {
+            let items = lower_exprs(items, context, scope, statements)?.into_iter().map(Expr::Variable).collect();
+            Ok(assign("array", Expr::Array(items), statements))
+        },
+        ast::ExprKind::Match { expr: target, cases } => {
+            let value = lower_expr(target, context, scope, statements)?;
+            let cases = cases.iter().map(|case| (&case.pattern, &case.expr)).collect::<Vec<_>>();
+            lower_match(value, &cases, failure("match expression", expr.span), context, scope, statements)
+        },
+        ast::ExprKind::PathPattern(stages) => {
+            let names = result_fields(expr);
+            lower_pattern_fun(scope, statements, &mut |value, scope, statements| lower_path_stages(stages, value, &names, context, scope, statements))
+        },
+        ast::ExprKind::ArrayPattern(items) => {
+            let names = result_fields(expr);
+            lower_pattern_fun(scope, statements, &mut |value, scope, statements| {
+                scope.push(ARRAY, value);
+                let start = assign("number", Expr::Number(0), statements);
+                let ret = lower_seq(items, start, context, scope, statements, &mut |scope, statements| add_result(&names, scope, statements));
+                scope.pop();
+                ret
+            })
+        },
+        ast::ExprKind::NamedPattern(_) => panic!("lower_expr should not have any named patterns; they are inlined"),
+    }
+}
+
+// Both call the pattern (which is a function) with the value, and are only told apart for the reader
+const PATTERN_BUILTINS : [&str; 2] = [ "path", "match_all" ];
 
-    Ok(vec![])
+fn failure(what : &str, span : Span) -> (String, Span) {
+    (format!("{what} did not match"), span)
 }
 
-fn fun_types(funs : &Vec<Ast>) -> Result<HashMap<Symbol, Type>, StaticError> {
+// Each case only runs when no earlier case has matched.  The statements that evaluate its body are nested
+// inside of the tests of its pattern, and a value that no case matches stops the program.
+fn lower_match( value : Symbol
+              , cases : &[(&ast::StandardPattern, &ast::Expr)]
+              , (failure, span) : (String, Span)
+              , context : &Context
+              , scope : &mut Scope
+              , statements : &mut Vec<Statement> ) -> Result<Symbol, StaticError> {
+
+    let result = anon_sym("match");
+    let done = assign("done", Expr::Bool(false), statements);
+
+    for (pattern, body) in cases {
+        let not_done = call("not", vec![done.clone()], statements);
+        let mut case_statements = vec![];
+        lower_item(*pattern, value.clone(), context, scope, &mut case_statements, &mut |scope, statements| {
+            let body = lower_expr(body, context, scope, statements)?;
+            statements.push(Statement::Assign { name: result.clone(), expr: Expr::Variable(body) });
+            statements.push(Statement::Assign { name: done.clone(), expr: Expr::Bool(true) });
+            Ok(())
+        })?;
+        statements.push(Statement::If { target: not_done, statements: case_statements });
+    }
+
+    let not_done = call("not", vec![done], statements);
+    statements.push(Statement::If { target: not_done, statements: vec![Statement::Fail(failure, span)] });
+
+    Ok(result)
+}
+
+// Fills in the statements that run when the pattern matches, with the variables of the pattern in scope.
+type Then<'a> = dyn FnMut(&mut Scope, &mut Vec<Statement>) -> Result<(), StaticError> + 'a;
+
+fn slot_values<'p, P>( patterns : Vec<(&'p P, usize)>
+                     , value : &Symbol
+                     , statements : &mut Vec<Statement> ) -> Vec<(&'p P, Symbol)> {
+
+    let mut values = vec![];
+    for (p, i) in patterns {
+        let slot = assign("slot", Expr::SlotAccess { data: value.clone(), slot: SlotAccessType::Index(i) }, statements);
+        values.push((p, slot));
+    }
+    values
+}
+
+// Like Then, along with the pattern of each item and the value that it is matched against
+type ItemsThen<'a, 'p, P> = dyn FnMut(&mut Scope, &mut Vec<Statement>, Vec<(&'p P, Symbol)>) -> Result<(), StaticError> + 'a;
+
+// The items are only read once the array is known to be long enough for them.  The rest pattern gets
+// the array without the items.
+fn lower_standard_array<'p, P>( array : &'p ast::StandardArrayPattern<P>
+                              , value : &Symbol
+                              , scope : &mut Scope
+                              , statements : &mut Vec<Statement>
+                              , then : &mut ItemsThen<'_, 'p, P>
+                              ) -> Result<(), StaticError> {
+
+    let (items, rest) = match array {
+        ast::StandardArrayPattern::Empty => (&[][..], None),
+        ast::StandardArrayPattern::Array { items, rest } => (&items[..], rest.as_deref()),
+    };
+
+    let length = call("len", vec![value.clone()], statements);
+    let count = assign("number", Expr::Number(items.len() as i64), statements);
+    let cond = match rest {
+        Some(_) => call("gte", vec![length, count.clone()], statements),
+        None => call("eq", vec![length, count.clone()], statements),
+    };
+    check(cond, scope, statements, &mut |scope, statements| {
+        let mut values = vec![];
+        for (i, p) in items.iter().enumerate() {
+            values.push((p, assign("item", Expr::SlotAccess { data: value.clone(), slot: SlotAccessType::Index(i) }, statements)));
+        }
+        if let Some(rest) = rest {
+            values.push((rest, call("drop", vec![value.clone(), count.clone()], statements)));
+        }
+        then(scope, statements, values)
+    })
+}
+
+fn equals(value : Symbol, expected : Expr, statements : &mut Vec<Statement>) -> Symbol {
+    let expected = assign("literal", expected, statements);
+    call("eq", vec![value, expected], statements)
+}
+
+// Both bounds are inclusive and at least one of them is set
+fn in_range(value : Symbol, min : Option<i64>, max : Option<i64>, statements : &mut Vec<Statement>) -> Symbol {
+    let mut bounds = vec![];
+    if let Some(min) = min {
+        let min = assign("number", Expr::Number(min), statements);
+        bounds.push(call("gte", vec![value.clone(), min], statements));
+    }
+    if let Some(max) = max {
+        let max = assign("number", Expr::Number(max), statements);
+        bounds.push(call("lte", vec![value, max], statements));
+    }
+    match &bounds[..] {
+        [bound] => bound.clone(),
+        [min, max] => call("and", vec![min.clone(), max.clone()], statements),
+        _ => panic!("range pattern should have at least one bound"),
+    }
+}
+
+fn check(cond : Symbol, scope : &mut Scope, statements : &mut Vec<Statement>, then : &mut Then<'_>) -> Result<(), StaticError> {
+    let mut inner = vec![];
+    then(scope, &mut inner)?;
+    statements.push(Statement::If { target: cond, statements: inner });
+    Ok(())
+}
+
+fn cons_info<'a>(name : &str, context : &'a Context, span : Span) -> Result<&'a ConsInfo, StaticError> {
+    match context.cons.get(name) {
+        Some(info) => Ok(info),
+        None => Err(StaticError::Fatal(format!("Encountered unknown constructor {name}"), span)),
+    }
+}
+
+fn has_tag(value : &Symbol, cons_tag : &ConsTag, statements : &mut Vec<Statement>) -> Symbol {
+    let tag = assign("tag", Expr::SlotAccess { data: value.clone(), slot: SlotAccessType::Tag }, statements);
+    let expected = assign("tag", Expr::Tag(cons_tag.clone()), statements);
+    call("eq", vec![tag, expected], statements)
+}
+
+// Names that variables can't have, for the locals that the code of path and array patterns uses
+const RESULTS : &str = "#results";
+const ARRAY : &str = "#array";
+
+// The variables of a path or array pattern in the order that they show up, which are the fields of its results.
+fn result_fields(expr : &ast::Expr) -> Vec<String> {
+    let mut names = vec![];
+    match &expr.kind {
+        ast::ExprKind::PathPattern(stages) => stages.iter().for_each(|p| visit::path_variables(p, &mut names)),
+        ast::ExprKind::ArrayPattern(items) => items.iter().for_each(|p| visit::array_variables(p, &mut names)),
+        _ => panic!("result_fields should only have path and array patterns"),
+    }
+    let mut ret : Vec<String> = vec![];
+    for name in names {
+        if !ret.contains(&name) {
+            ret.push(name);
+        }
+    }
+    ret
+}
+
+// An array of anon records with a field for each variable.  A variable under quantifiers collects into
+// an array for every quantifier that it is under.
+fn result_type(expr : &ast::Expr) -> Type {
+    let mut depths = vec![];
+    if let ast::ExprKind::ArrayPattern(items) = &expr.kind {
+        for item in items {
+            let (mut pattern, mut depth) = (item, 0);
+            while let ast::ArrayPatternKind::Quantified { pattern: inner, .. } = &pattern.kind {
+                pattern = inner;
+                depth += 1;
+            }
+            let mut names = vec![];
+            visit::array_variables(pattern, &mut names);
+            depths.extend(names.into_iter().map(|name| (name, depth)));
+        }
+    }
+    let rows = result_fields(expr).into_iter().map(|name| {
+        let depth = depths.iter().find(|(n, _)| *n == name).map_or(0, |(_, depth)| *depth);
+        RowType { t: (0..depth).fold(Type::Infer, |t, _| array_type(t)), name }
+    }).collect();
+    array_type(Type::Anon(rows))
+}
+
+fn array_type(t : Type) -> Type {
+    Type::Index { name: ConcreteType("Array".to_string()), params: vec![t] }
+}
+
+// Produces the statements that match the pattern against the value and add its results
+type Matches<'a> = dyn FnMut(Symbol, &mut Scope, &mut Vec<Statement>) -> Result<(), StaticError> + 'a;
+
+// A path or array pattern is a function from the value that it is matched against to an array with
+// an anon record of the bound variables for every way that the pattern matches.
+fn lower_pattern_fun( scope : &mut Scope
+                    , statements : &mut Vec<Statement>
+                    , matches : &mut Matches<'_>
+                    ) -> Result<Symbol, StaticError> {
+
+    lift_fun(1, scope, statements, &mut |scope, _, params, statements| {
+        let results = assign("results", Expr::Array(vec![]), statements);
+        scope.push(RESULTS, results.clone());
+        let ret = matches(params[0].clone(), scope, statements);
+        scope.pop();
+        ret.map(|()| results)
+    })
+}
+
+fn add_result(names : &[String], scope : &mut Scope, statements : &mut Vec<Statement>) -> Result<(), StaticError> {
+    let fields = names.iter().map(|name| (name.to_string(), Expr::Variable(scope.lookup(name)))).collect();
+    let record = assign("record", Expr::Anon(fields), statements);
+    let more = assign("array", Expr::Array(vec![Expr::Variable(record)]), statements);
+    add_results(more, scope, statements);
+    Ok(())
+}
+
+fn add_results(more : Symbol, scope : &mut Scope, statements : &mut Vec<Statement>) {
+    let results = scope.lookup(RESULTS);
+    statements.push(Statement::Assign { name: results.clone(), expr: Expr::FunCall { name: Symbol::User("concat".to_string()), params: vec![results, more] } });
+}
+
+// The first stage is matched against the value and the rest of the path continues from every value
+// that it marks with a '!'.  The last stage adds a result each time that it matches.
+fn lower_path_stages( stages : &[ast::PathPattern]
+                    , value : Symbol
+                    , names : &[String]
+                    , context : &Context
+                    , scope : &mut Scope
+                    , statements : &mut Vec<Statement>
+                    ) -> Result<(), StaticError> {
+
+    let (stage, rest) = match stages {
+        [] => return add_result(names, scope, statements),
+        [stage, rest @ ..] => (stage, rest),
+    };
+
+    let outer = mem::take(&mut scope.nexts);
+    let ret = lower_item(stage, value, context, scope, statements, &mut |scope, statements| {
+        if rest.is_empty() {
+            return add_result(names, scope, statements);
+        }
+        // Numbered '!'s are followed in the order of their numbers and then the rest in the order that they are written
+        let mut nexts = scope.nexts.clone();
+        nexts.sort_by_key(|(order, _)| order.unwrap_or(i64::MAX));
+        for (_, next) in nexts {
+            lower_path_stages(rest, next, names, context, scope, statements)?;
+        }
+        Ok(())
+    });
+    scope.nexts = outer;
+    ret
+}
+
+// Like Then, along with the index of the array right after what has been matched
+type SeqThen<'a> = dyn FnMut(&mut Scope, &mut Vec<Statement>, Symbol) -> Result<(), StaticError> + 'a;
+
+// The items of an array pattern match the whole array from the index in order.
+fn lower_seq( items : &[ast::ArrayPattern]
+            , index : Symbol
+            , context : &Context
+            , scope : &mut Scope
+            , statements : &mut Vec<Statement>
+            , then : &mut Then<'_> ) -> Result<(), StaticError> {
+
+    match items {
+        [] => {
+            let length = call("len", vec![scope.lookup(ARRAY)], statements);
+            let at_end = call("eq", vec![index, length], statements);
+            check(at_end, scope, statements, then)
+        },
+        [item, rest @ ..] => lower_seq_item(item, index, context, scope, statements, &mut |scope, statements, next| lower_seq(rest, next, context, scope, statements, then)),
+    }
+}
+
+fn lower_seq_item( item : &ast::ArrayPattern
+                 , index : Symbol
+                 , context : &Context
+                 , scope : &mut Scope
+                 , statements : &mut Vec<Statement>
+                 , then : &mut SeqThen<'_> ) -> Result<(), StaticError> {
+
+    let number = |n, statements : &mut Vec<Statement>| assign("number", Expr::Number(n), statements);
+    match &item.kind {
+        ast::ArrayPatternKind::WildcardZeroOrMore => {
+            let wildcard = ast::ArrayPattern { kind: ast::ArrayPatternKind::Wildcard, span: item.span };
+            let min = number(0, statements);
+            lower_repeat(&wildcard, (min, None), index, context, scope, statements, then)
+        },
+        ast::ArrayPatternKind::WildcardN(n) => {
+            let n = lower_expr(n, context, scope, statements)?;
+            let next = call("add", vec![index.clone(), n], statements);
+            let length = call("len", vec![scope.lookup(ARRAY)], statements);
+            let forward = call("lte", vec![index, next.clone()], statements);
+            let inside = call("lte", vec![next.clone(), length], statements);
+            let cond = call("and", vec![forward, inside], statements);
+            check(cond, scope, statements, &mut |scope, statements| then(scope, statements, next.clone()))
+        },
+        ast::ArrayPatternKind::Quantified { pattern, quantifier } => {
+            let (min, max) = match quantifier {
+                ast::Quantifier::ZeroOrMore => (number(0, statements), None),
+                ast::Quantifier::OneOrMore => (number(1, statements), None),
+                ast::Quantifier::ZeroOrOne => (number(0, statements), Some(number(1, statements))),
+                ast::Quantifier::Exactly(n) => {
+                    let n = lower_expr(n, context, scope, statements)?;
+                    (n.clone(), Some(n))
+                },
+                ast::Quantifier::Range { min, max } => {
+                    let min = match min {
+                        Some(min) => lower_expr(min, context, scope, statements)?,
+                        None => number(0, statements),
+                    };
+                    let max = match max {
+                        Some(max) => Some(lower_expr(max, context, scope, statements)?),
+                        None => None,
+                    };
+                    (min, max)
+                },
+            };
+            lower_repeat(pattern, (min, max), index, context, scope, statements, then)
+        },
+        // Alternatives are items of their own here so that they can be quantified
+        ast::ArrayPatternKind::Or(alternatives) => {
+            for alternative in alternatives {
+                lower_seq_item(alternative, index.clone(), context, scope, statements, then)?;
+            }
+            Ok(())
+        },
+        _ => {
+            let array = scope.lookup(ARRAY);
+            let length = call("len", vec![array.clone()], statements);
+            let inside = call("lt", vec![index.clone(), length], statements);
+            check(inside, scope, statements, &mut |scope, statements| {
+                let value = call("nth", vec![array.clone(), index.clone()], statements);
+                lower_item(item, value, context, scope, statements, &mut |scope, statements| {
+                    let one = assign("number", Expr::Number(1), statements);
+                    let next = call("add", vec![index.clone(), one], statements);
+                    then(scope, statements, next)
+                })
+            })
+        },
+    }
+}
+
+// A quantified item is a function of the index, the number of repeats so far and the values collected
+// so far for each variable of the repeated pattern.  It goes on with the rest of the array when there
+// have been enough repeats and then calls itself for one more repeat, as long as the repeat moves
+// forward, so the results for fewer repeats come first.
+fn lower_repeat( pattern : &ast::ArrayPattern
+               , (min, max) : (Symbol, Option<Symbol>)
+               , index : Symbol
+               , context : &Context
+               , scope : &mut Scope
+               , statements : &mut Vec<Statement>
+               , then : &mut SeqThen<'_> ) -> Result<(), StaticError> {
+
+    let mut names : Vec<String> = vec![];
+    visit::array_variables(pattern, &mut names);
+    names.sort();
+    names.dedup();
+
+    let min = hold(&min, scope);
+    let max = max.map(|max| hold(&max, scope));
+    let fun = lift_fun(2 + names.len(), scope, statements, &mut |scope, itself, params, statements| {
+        let results = assign("results", Expr::Array(vec![]), statements);
+        scope.push(RESULTS, results.clone());
+        let held = std::iter::once(itself).chain(params).map(|sym| hold(sym, scope)).collect::<Vec<_>>();
+        let (itself, index, count, collected) = (&held[0], &held[1], &held[2], &held[3..]);
+
+        let enough = call("gte", vec![scope.lookup(count), scope.lookup(&min)], statements);
+        check(enough, scope, statements, &mut |scope, statements| {
+            for (name, values) in names.iter().zip(collected) {
+                scope.push(name, scope.lookup(values));
+            }
+            let ret = then(scope, statements, scope.lookup(index));
+            names.iter().for_each(|_| scope.pop());
+            ret
+        })?;
+
+        let mut repeat = |scope : &mut Scope, statements : &mut Vec<Statement>| lower_seq_item(pattern, scope.lookup(index), context, scope, statements, &mut |scope, statements, next| {
+            let forward = call("gt", vec![next.clone(), scope.lookup(index)], statements);
+            check(forward, scope, statements, &mut |scope, statements| {
+                let one = assign("number", Expr::Number(1), statements);
+                let mut params = vec![next.clone(), call("add", vec![scope.lookup(count), one], statements)];
+                for (name, values) in names.iter().zip(collected) {
+                    let value = assign("array", Expr::Array(vec![Expr::Variable(scope.lookup(name))]), statements);
+                    params.push(call("concat", vec![scope.lookup(values), value], statements));
+                }
+                let more = assign("call", Expr::FunCall { name: scope.lookup(itself), params }, statements);
+                add_results(more, scope, statements);
+                Ok(())
+            })
+        });
+        match &max {
+            Some(max) => {
+                let below = call("lt", vec![scope.lookup(count), scope.lookup(max)], statements);
+                check(below, scope, statements, &mut repeat)?;
+            },
+            None => repeat(scope, statements)?,
+        }
+
+        held.iter().for_each(|_| scope.pop());
+        scope.pop();
+        Ok(results)
+    });
+    if max.is_some() {
+        scope.pop();
+    }
+    scope.pop();
+
+    let zero = assign("number", Expr::Number(0), statements);
+    let mut params = vec![index, zero];
+    params.extend(names.iter().map(|_| assign("array", Expr::Array(vec![]), statements)));
+    let more = assign("call", Expr::FunCall { name: fun?, params }, statements);
+    add_results(more, scope, statements);
+    Ok(())
+}
+
+// Puts the symbol in scope under a name of its own, so that the code that is lowered into a function
+// that is lifted out of the current one can still get to its value.
+fn hold(sym : &Symbol, scope : &mut Scope) -> String {
+    let name = format!("#{}", symbol_name(sym));
+    scope.push(&name, sym.clone());
+    name
+}
+
+// What a pattern looks like where it matches a single value.  Match, path and array patterns share
+// everything but the '!' and '&' of path patterns, the quantified items of array patterns and how
+// the alternatives of an or pattern are tried.
+enum Shape<'a, P> {
+    Number(i64),
+    Range { min : Option<i64>, max : Option<i64> },
+    Bool(bool),
+    String(&'a str),
+    Variable(&'a str),
+    Cons { name : &'a str, params : &'a [P] },
+    Record { name : &'a str, fields : &'a [(String, P)] },
+    Tuple(&'a [P]),
+    At { name : &'a str, pattern : &'a P },
+    Wildcard,
+    If { pattern : &'a P, predicate : &'a ast::Expr },
+    Array(&'a ast::StandardArrayPattern<P>),
+    Next(Option<i64>),
+    And { next : Option<Option<i64>>, name : &'a str, output : &'a str }, // Also a '!' when next is set
+    Or(&'a [P]), // Every alternative that matches goes on to the rest of the pattern
+    FirstOf(&'a [P]), // Only the first alternative that matches goes on, like the cases of a match
+    Misplaced(&'static str), // Only allowed somewhere else, with the error message for it
+}
+
+trait Item : Sized {
+    fn shape(&self) -> Shape<'_, Self>;
+    fn span(&self) -> Span;
+    fn variables(&self, out : &mut Vec<String>);
+}
+
+impl Item for ast::StandardPattern {
+    fn shape(&self) -> Shape<'_, Self> {
+        match &self.kind {
+            ast::StandardPatternKind::Number(n) => Shape::Number(*n),
+            ast::StandardPatternKind::Range { min, max } => Shape::Range { min: *min, max: *max },
+            ast::StandardPatternKind::Bool(b) => Shape::Bool(*b),
+            ast::StandardPatternKind::String(s) => Shape::String(s),
+            ast::StandardPatternKind::Variable(name) => Shape::Variable(name),
+            ast::StandardPatternKind::Cons { name, params } => Shape::Cons { name, params },
+            ast::StandardPatternKind::Record { name, fields } => Shape::Record { name, fields },
+            ast::StandardPatternKind::Tuple(items) => Shape::Tuple(items),
+            ast::StandardPatternKind::At { name, pattern } => Shape::At { name, pattern },
+            ast::StandardPatternKind::Wildcard => Shape::Wildcard,
+            ast::StandardPatternKind::If { pattern, predicate } => Shape::If { pattern, predicate },
+            ast::StandardPatternKind::StandardArray(array) => Shape::Array(array),
+            ast::StandardPatternKind::Or(alternatives) => Shape::FirstOf(alternatives),
+        }
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn variables(&self, out : &mut Vec<String>) {
+        visit::standard_variables(self, out);
+    }
+}
+
+impl Item for ast::ArrayPattern {
+    fn shape(&self) -> Shape<'_, Self> {
+        match &self.kind {
+            ast::ArrayPatternKind::Number(n) => Shape::Number(*n),
+            ast::ArrayPatternKind::Range { min, max } => Shape::Range { min: *min, max: *max },
+            ast::ArrayPatternKind::Bool(b) => Shape::Bool(*b),
+            ast::ArrayPatternKind::String(s) => Shape::String(s),
+            ast::ArrayPatternKind::Variable(name) => Shape::Variable(name),
+            ast::ArrayPatternKind::Cons { name, params } => Shape::Cons { name, params },
+            ast::ArrayPatternKind::Record { name, fields } => Shape::Record { name, fields },
+            ast::ArrayPatternKind::Tuple(items) => Shape::Tuple(items),
+            ast::ArrayPatternKind::At { name, pattern } => Shape::At { name, pattern },
+            ast::ArrayPatternKind::Wildcard => Shape::Wildcard,
+            ast::ArrayPatternKind::If { pattern, predicate } => Shape::If { pattern, predicate },
+            ast::ArrayPatternKind::StandardArray(array) => Shape::Array(array),
+            ast::ArrayPatternKind::Or(alternatives) => Shape::Or(alternatives),
+            ast::ArrayPatternKind::WildcardZeroOrMore
+            | ast::ArrayPatternKind::WildcardN(_)
+            | ast::ArrayPatternKind::Quantified { .. } => Shape::Misplaced("Quantified patterns can only be items directly inside of [| |]"),
+            ast::ArrayPatternKind::Named(_) => panic!("lower_item should not have any named patterns; they are inlined"),
+        }
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn variables(&self, out : &mut Vec<String>) {
+        visit::array_variables(self, out);
+    }
+}
+
+impl Item for ast::PathPattern {
+    fn shape(&self) -> Shape<'_, Self> {
+        match &self.kind {
+            ast::PathPatternKind::Number(n) => Shape::Number(*n),
+            ast::PathPatternKind::Range { min, max } => Shape::Range { min: *min, max: *max },
+            ast::PathPatternKind::Bool(b) => Shape::Bool(*b),
+            ast::PathPatternKind::String(s) => Shape::String(s),
+            ast::PathPatternKind::Variable(name) => Shape::Variable(name),
+            ast::PathPatternKind::Cons { name, params } => Shape::Cons { name, params },
+            ast::PathPatternKind::Record { name, fields } => Shape::Record { name, fields },
+            ast::PathPatternKind::Tuple(items) => Shape::Tuple(items),
+            ast::PathPatternKind::At { name, pattern } => Shape::At { name, pattern },
+            ast::PathPatternKind::Wildcard => Shape::Wildcard,
+            ast::PathPatternKind::Next(order) => Shape::Next(*order),
+            ast::PathPatternKind::And { name, output } => Shape::And { next: None, name, output },
+            ast::PathPatternKind::NextAnd { order, name, output } => Shape::And { next: Some(*order), name, output },
+            ast::PathPatternKind::If { pattern, predicate } => Shape::If { pattern, predicate },
+            ast::PathPatternKind::StandardArray(array) => Shape::Array(array),
+            ast::PathPatternKind::Or(alternatives) => Shape::Or(alternatives),
+            ast::PathPatternKind::Named(_) => panic!("lower_item should not have any named patterns; they are inlined"),
+        }
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    fn variables(&self, out : &mut Vec<String>) {
+        visit::path_variables(self, out);
+    }
+}
+
+// Every test guards the ones after it, so nothing looks inside of a value before its shape is known.  A '!'
+// marks the value for the next stage of the path and '&p:x' binds x to the results of the pattern p matched
+// against the value.
+fn lower_item<P : Item>( pattern : &P
+                       , value : Symbol
+                       , context : &Context
+                       , scope : &mut Scope
+                       , statements : &mut Vec<Statement>
+                       , then : &mut Then<'_> ) -> Result<(), StaticError> {
+
+    match pattern.shape() {
+        Shape::Number(n) => {
+            let cond = equals(value, Expr::Number(n), statements);
+            check(cond, scope, statements, then)
+        },
+        Shape::Range { min, max } => {
+            let cond = in_range(value, min, max, statements);
+            check(cond, scope, statements, then)
+        },
+        Shape::Bool(b) => {
+            let cond = equals(value, Expr::Bool(b), statements);
+            check(cond, scope, statements, then)
+        },
+        Shape::String(s) => {
+            let cond = equals(value, Expr::String(s.to_string()), statements);
+            check(cond, scope, statements, then)
+        },
+        Shape::Variable(name) => {
+            scope.push(name, value);
+            let ret = then(scope, statements);
+            scope.pop();
+            ret
+        },
+        Shape::Cons { name, params } => {
+            let info = cons_info(name, context, pattern.span())?;
+            if info.ts.len() != params.len() {
+                return Err(StaticError::Fatal(format!("Constructor {name} takes {} parameters but the pattern has {}", info.ts.len(), params.len()), pattern.span()));
+            }
+            for (p, t) in params.iter().zip(&info.ts) {
+                check_tuple_arity(p, t)?;
+            }
+            let cond = has_tag(&value, &info.tag, statements);
+            check(cond, scope, statements, &mut |scope, statements| {
+                let values = slot_values(params.iter().enumerate().map(|(i, p)| (p, i)).collect(), &value, statements);
+                lower_items(&values, context, scope, statements, then)
+            })
+        },
+        Shape::Record { name, fields } => {
+            let info = cons_info(name, context, pattern.span())?;
+            let declared = match &info.fields {
+                Some(declared) => declared,
+                None => return Err(StaticError::Fatal(format!("Constructor {name} does not have named fields"), pattern.span())),
+            };
+            let mut slots = vec![];
+            for (field, p) in fields {
+                match declared.iter().position(|d| d == field) {
+                    Some(i) => {
+                        check_tuple_arity(p, &info.ts[i])?;
+                        slots.push((p, i));
+                    },
+                    None => return Err(StaticError::Fatal(format!("Constructor {name} does not have field {field}"), p.span())),
+                }
+            }
+            let cond = has_tag(&value, &info.tag, statements);
+            check(cond, scope, statements, &mut |scope, statements| {
+                let values = slot_values(slots.clone(), &value, statements);
+                lower_items(&values, context, scope, statements, then)
+            })
+        },
+        // A tuple of another arity has another tag, so it does not match
+        Shape::Tuple(items) => {
+            let cond = has_tag(&value, &tuple_tag(items.len()), statements);
+            check(cond, scope, statements, &mut |scope, statements| {
+                let values = slot_values(items.iter().enumerate().map(|(i, p)| (p, i)).collect(), &value, statements);
+                lower_items(&values, context, scope, statements, then)
+            })
+        },
+        Shape::At { name, pattern } => {
+            scope.push(name, value.clone());
+            let ret = lower_item(pattern, value, context, scope, statements, then);
+            scope.pop();
+            ret
+        },
+        Shape::Wildcard => then(scope, statements),
+        Shape::If { pattern, predicate } => lower_item(pattern, value, context, scope, statements, &mut |scope, statements| {
+            let cond = lower_expr(predicate, context, scope, statements)?;
+            check(cond, scope, statements, then)
+        }),
+        Shape::Array(array) =>
+            lower_standard_array(array, &value, scope, statements, &mut |scope, statements, values| lower_items(&values, context, scope, statements, then)),
+        Shape::Next(order) => {
+            scope.nexts.push((order, value));
+            let ret = then(scope, statements);
+            scope.nexts.pop();
+            ret
+        },
+        Shape::And { next, name, output } => {
+            let results = assign("call", Expr::FunCall { name: scope.lookup(name), params: vec![value.clone()] }, statements);
+            if let Some(order) = next {
+                scope.nexts.push((order, value));
+            }
+            scope.push(output, results);
+            let ret = then(scope, statements);
+            scope.pop();
+            if next.is_some() {
+                scope.nexts.pop();
+            }
+            ret
+        },
+        // Every alternative that matches goes on to the rest of the pattern, in the order that they are written
+        Shape::Or(alternatives) => {
+            for alternative in alternatives {
+                lower_item(alternative, value.clone(), context, scope, statements, then)?;
+            }
+            Ok(())
+        },
+        // Every alternative binds the same variables, so whichever one matches copies its values into
+        // shared symbols before the rest of the pattern runs once with those in scope.
+        Shape::FirstOf(alternatives) => {
+            let mut names = vec![];
+            alternatives[0].variables(&mut names);
+            let shared = names.iter().map(|_| anon_sym("bound")).collect::<Vec<_>>();
+            let matched = assign("matched", Expr::Bool(false), statements);
+
+            for alternative in alternatives {
+                let not_matched = call("not", vec![matched.clone()], statements);
+                let mut alternative_statements = vec![];
+                lower_item(alternative, value.clone(), context, scope, &mut alternative_statements, &mut |scope, statements| {
+                    for (name, sym) in names.iter().zip(&shared) {
+                        statements.push(Statement::Assign { name: sym.clone(), expr: Expr::Variable(scope.lookup(name)) });
+                    }
+                    statements.push(Statement::Assign { name: matched.clone(), expr: Expr::Bool(true) });
+                    Ok(())
+                })?;
+                statements.push(Statement::If { target: not_matched, statements: alternative_statements });
+            }
+
+            for (name, sym) in names.iter().zip(&shared) {
+                scope.push(name, sym.clone());
+            }
+            let ret = check(matched, scope, statements, then);
+            names.iter().for_each(|_| scope.pop());
+            ret
+        },
+        Shape::Misplaced(message) => Err(StaticError::Fatal(message.to_string(), pattern.span())),
+    }
+}
+
+// Tuple patterns are checked against the tuple types that are known without inference, which are the
+// types of constructor slots and of function parameters.
+fn check_tuple_arity<P : Item>(pattern : &P, t : &Type) -> Result<(), StaticError> {
+    match (pattern.shape(), t) {
+        (Shape::Tuple(items), Type::Tuple(ts)) => {
+            if items.len() != ts.len() {
+                return Err(StaticError::Fatal(format!("Tuple pattern has {} items but the type has {}", items.len(), ts.len()), pattern.span()));
+            }
+            items.iter().zip(ts).try_for_each(|(p, t)| check_tuple_arity(p, t))
+        },
+        (Shape::At { pattern, .. } | Shape::If { pattern, .. }, t) => check_tuple_arity(pattern, t),
+        (Shape::Or(alternatives) | Shape::FirstOf(alternatives), t) => alternatives.iter().try_for_each(|p| check_tuple_arity(p, t)),
+        _ => Ok(()),
+    }
+}
+
+fn lower_items<P : Item>( patterns : &[(&P, Symbol)]
+                        , context : &Context
+                        , scope : &mut Scope
+                        , statements : &mut Vec<Statement>
+                        , then : &mut Then<'_> ) -> Result<(), StaticError> {
+
+    match patterns {
+        [] => then(scope, statements),
+        [(pattern, value), rest @ ..] =>
+            lower_item(*pattern, value.clone(), context, scope, statements, &mut |scope, statements| lower_items(rest, context, scope, statements, then)),
+    }
+}
+
+// Top level lets are left out; they are typed by their expression instead.
+fn fun_types(funs : &[Ast]) -> Result<HashMap<Symbol, Type>, StaticError> {
     let mut m = HashMap::new();
     for fun in funs {
-        let (name, params, return_type) = match fun {
-            Ast::FunDef { name, params, return_type, .. } => (Symbol::User(name.to_string()), params, return_type),
+        let (name, params, return_type, span) = match fun {
+            Ast::FunDef { name, params, return_type, span, .. } => (Symbol::User(name.to_string()), params, return_type, span),
+            Ast::LetDef { .. } => continue,
             _ => panic!("fun_types should not have any data defs"),
-        }; 
+        };
         if m.contains_key(&name) {
             let x = match name {
                 Symbol::User(n) => n,
                 _ => panic!("fun_types should only have user symbols at this point"),
             };
-            return Err(StaticError::Fatal(format!("Encountered already defined function {x}")));
+            return Err(StaticError::Fatal(format!("Encountered already defined function {x}"), *span));
         }
         let input = params.iter()
                           .map(|p| p.t.as_ref().expect("FunDef must have type on each param"))
                           .map(type_info::ast_to_ir_type)
                           .collect();
 
-        m.insert(name, Type::Fun { input, output: Box::new(type_info::ast_to_ir_type(return_type.clone()))});
+        m.insert(name, Type::Fun { input, output: Box::new(type_info::ast_to_ir_type(return_type))});
     }
     Ok(m)
 }
@@ -82,5 +1356,384 @@ fn fun_types(funs : &Vec<Ast>) -> Result<HashMap<Symbol, Type>, StaticError> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::parsing::parser::parse;
+
+    fn lower(input : &str) -> Vec<Ir> {
+        let asts = parse(input).expect("test input should parse");
+        generate(asts).expect("test input should lower").0.irs
+    }
 
-}
\ No newline at end of file
+    fn constants(input : &str) -> Result<Vec<String>, StaticError> {
+        let asts = parse(input).expect("test input should parse");
+        let (program, _) = generate(asts)?;
+        Ok(program.constants.iter().map(|c| symbol_name(c).to_string()).collect())
+    }
+
+    #[test]
+    fn should_desugar_pattern_params_to_match() {
+        let asts = parse("fun f(x : Number, Pair(a, _) : Pair) -> Number = a;").expect("test input should parse");
+        let (params, expr) = match &asts[0] {
+            Ast::FunDef { params, expr, .. } => (params, expr),
+            _ => panic!("expected fun def"),
+        };
+
+        let mut scope = Scope::new();
+        let (syms, body) = bind_params(params, expr, &mut scope).expect("params should bind");
+
+        assert_eq!( syms[0], Symbol::User("x".to_string()) );
+        assert!( matches!( syms[1], Symbol::Anon(_) ) );
+        let (scrutinee, cases) = match &body.kind {
+            ast::ExprKind::Match { expr, cases } => (expr, cases),
+            _ => panic!("expected match"),
+        };
+        assert!( matches!( &scrutinee.kind, ast::ExprKind::Variable(name) if scope.lookup(name) == syms[1] ) );
+        assert!( matches!( &cases[0].pattern.kind, ast::StandardPatternKind::Cons { name, .. } if name == "Pair" ) );
+        assert!( matches!( &cases[0].expr.kind, ast::ExprKind::Variable(a) if a == "a" ) );
+    }
+
+    #[test]
+    fn should_lower_fun_def() {
+        let irs = lower("fun f(a : Number) -> Number = a;");
+
+        assert_eq!( irs.len(), 1 );
+        assert_eq!( irs[0].name, Symbol::User("f".to_string()) );
+        assert_eq!( irs[0].params, vec![Symbol::User("a".to_string())] );
+        assert!( matches!( &irs[0].statements[..], [Statement::Return(Symbol::User(a))] if a == "a" ) );
+    }
+
+    #[test]
+    fn should_lower_if_to_if_statements() {
+        let irs = lower("fun f(a : Bool) -> Number = if a then 1 else 2;");
+
+        let statements = &irs[0].statements;
+        assert_eq!( statements.len(), 4 );
+
+        let (then_target, then_statements) = match &statements[0] {
+            Statement::If { target, statements } => (target, statements),
+            s => panic!("expected if statement, found {:?}", s),
+        };
+        assert_eq!( *then_target, Symbol::User("a".to_string()) );
+        assert!( matches!( &then_statements[..], [ Statement::Assign { expr: Expr::Number(1), .. }
+                                                 , Statement::Assign { expr: Expr::Variable(_), .. }
+                                                 ] ) );
+
+        let not_a = match &statements[1] {
+            Statement::Assign { name, expr: Expr::FunCall { name: Symbol::User(not), params } } if not == "not" => {
+                assert_eq!( *params, vec![Symbol::User("a".to_string())] );
+                name
+            },
+            s => panic!("expected not call, found {:?}", s),
+        };
+
+        let (else_target, else_statements) = match &statements[2] {
+            Statement::If { target, statements } => (target, statements),
+            s => panic!("expected if statement, found {:?}", s),
+        };
+        assert_eq!( else_target, not_a );
+        assert!( matches!( &else_statements[..], [ Statement::Assign { expr: Expr::Number(2), .. }
+                                                 , Statement::Assign { expr: Expr::Variable(_), .. }
+                                                 ] ) );
+
+        assert!( matches!( &statements[3], Statement::Return(Symbol::Anon(_)) ) );
+    }
+
+    #[test]
+    fn should_short_circuit_and() {
+        let irs = lower("fun f(a : Bool, b : Bool) -> Bool = a && b.not();");
+
+        let statements = &irs[0].statements;
+
+        // b.not() should only be called inside of the branch where a is true
+        let then_statements = match &statements[0] {
+            Statement::If { target: Symbol::User(a), statements } if a == "a" => statements,
+            s => panic!("expected if statement, found {:?}", s),
+        };
+        assert!( matches!( &then_statements[0], Statement::Assign { expr: Expr::FunCall { .. }, .. } ) );
+        assert!( !statements[1..].iter().any(|s| matches!( s, Statement::Assign { expr: Expr::FunCall { params, .. }, .. }
+                                                            if params.contains(&Symbol::User("b".to_string())) ) ) );
+    }
+
+    #[test]
+    fn should_lower_let_to_scoped_symbol() {
+        let irs = lower("fun f(a : Number) -> Number = let a = 5 in a;");
+
+        let statements = &irs[0].statements;
+        let five = match &statements[0] {
+            Statement::Assign { name, expr: Expr::Number(5) } => name,
+            s => panic!("expected assign, found {:?}", s),
+        };
+        assert!( matches!( &statements[1], Statement::Return(r) if r == five ) );
+    }
+
+    #[test]
+    fn should_lower_constructor_pattern_behind_tag_check() {
+        let irs = lower("
+            data List = Cons(Number, List) | Nil;
+            fun head(l : List) -> Number = match l { Cons(h, _) => h, Nil => 0 };
+        ");
+
+        let statements = &irs[0].statements;
+        let case = match &statements[2] {
+            Statement::If { statements, .. } => statements,
+            s => panic!("expected if statement, found {:?}", s),
+        };
+        assert!( matches!( &case[0], Statement::Assign { expr: Expr::SlotAccess { data: Symbol::User(l), slot: SlotAccessType::Tag }, .. } if l == "l" ) );
+        assert!( matches!( &case[1], Statement::Assign { expr: Expr::Tag(ConsTag::User(c)), .. } if c == "Cons" ) );
+
+        // The slots are only read once the tag is known
+        let matched = match &case[3] {
+            Statement::If { statements, .. } => statements,
+            s => panic!("expected if statement, found {:?}", s),
+        };
+        assert!( matches!( &matched[0], Statement::Assign { expr: Expr::SlotAccess { slot: SlotAccessType::Index(0), .. }, .. } ) );
+        assert!( matches!( statements.last(), Some(Statement::Return(Symbol::Anon(_))) ) );
+        assert!( matches!( &statements[statements.len() - 2], Statement::If { statements, .. } if matches!( &statements[..], [Statement::Fail(..)] ) ) );
+    }
+
+    #[test]
+    fn should_lower_destructuring_let_as_match() {
+        let irs = lower("data Pair = Pair(Number, Number); fun f(p : Pair) -> Number = let Pair(a, b) = p in a + b;");
+
+        let fail = irs[0].statements.iter().find_map(|s| match s {
+            Statement::If { statements, .. } => match &statements[..] {
+                [Statement::Fail(message, span)] => Some((message.as_str(), span.start.line, span.start.column)),
+                _ => None,
+            },
+            _ => None,
+        });
+        assert_eq!( fail, Some(("let pattern did not match", 1, 63)) );
+    }
+
+    #[test]
+    fn should_reject_constructor_pattern_with_wrong_arity() {
+        let asts = parse("data Pair = Pair(Number, Number); fun f(p : Pair) -> Number = match p { Pair(a) => a };").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Constructor Pair takes 2 parameters but the pattern has 1" ) );
+    }
+
+    #[test]
+    fn should_reject_tuple_param_pattern_with_wrong_arity() {
+        let asts = parse("fun f((a, b) : (Number, Number, Number)) -> Number = a;").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Tuple pattern has 2 items but the type has 3" ) );
+    }
+
+    #[test]
+    fn should_reject_tuple_slot_pattern_with_wrong_arity() {
+        let asts = parse("data Wrap = Wrap((Number, Number)); fun f(w : Wrap) -> Number = match w { Wrap((a, b, c)) => a };").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Tuple pattern has 3 items but the type has 2" ) );
+    }
+
+    #[test]
+    fn should_reject_unknown_record_pattern_field() {
+        let asts = parse("data Person = Person { name : String }; fun f(p : Person) -> Number = match p { Person { age } => age };").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Constructor Person does not have field age" ) );
+    }
+
+    #[test]
+    fn should_reject_call_with_wrong_number_of_params() {
+        let asts = parse("fun f(a : Number) -> Number = a; fun g(f : fun() -> Number) -> Number = f(); fun h() -> Number = f(1, 2);").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Function f takes 1 parameters but was given 2" ) );
+    }
+
+    #[test]
+    fn should_reject_duplicate_function() {
+        let asts = parse("fun f() -> Number = 1; fun f() -> Number = 2;").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Encountered already defined function f" ) );
+    }
+
+    #[test]
+    fn should_lower_record_in_declared_field_order() {
+        let irs = lower("data Person = Person { name : String, age : Number }; fun f() -> Person = Person { age : 5, name : \"x\" };");
+
+        let statements = &irs[0].statements;
+        let (age, name) = match &statements[..2] {
+            [ Statement::Assign { name: age, expr: Expr::Number(5) }
+            , Statement::Assign { name, expr: Expr::String(_) }
+            ] => (age, name),
+            s => panic!("expected field values in source order, found {:?}", s),
+        };
+        assert!( matches!( &statements[2], Statement::Assign { expr: Expr::Constructor { cons_tag: ConsTag::User(p), slots_assigns }, .. }
+            if p == "Person" && matches!( &slots_assigns[..], [Expr::Variable(n), Expr::Variable(a)] if n == name && a == age ) ) );
+    }
+
+    #[test]
+    fn should_reject_missing_record_field() {
+        let asts = parse("data Person = Person { name : String, age : Number }; fun f() -> Person = Person { age : 5 };").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Constructor Person is missing field name" ) );
+    }
+
+    #[test]
+    fn should_reject_unknown_record_field() {
+        let asts = parse("data Person = Person { name : String }; fun f() -> Person = Person { name : 1, age : 5 };").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Constructor Person does not have field age" ) );
+    }
+
+    #[test]
+    fn should_lower_field_access_to_slot_access() {
+        let irs = lower("data Person = Person { name : String }; fun f(p : Person) -> String = p.name;");
+
+        assert!( matches!( &irs[0].statements[0], Statement::Assign { expr: Expr::SlotAccess { data: Symbol::User(p), slot: SlotAccessType::Field(f) }, .. }
+            if p == "p" && f == "name" ) );
+    }
+
+    #[test]
+    fn should_reject_access_of_undeclared_field() {
+        let asts = parse("data Person = Person { name : String }; fun f(p : Person) -> String = p.age;").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "No record or anon type has a field named age" ) );
+    }
+
+    #[test]
+    fn should_lower_access_of_anon_type_field() {
+        let irs = lower("fun f(p : { a : Number, b : [{ c : String }] }) -> String = p.b.nth(0).c;");
+
+        let fields = irs[0].statements.iter().filter_map(|s| match s {
+            Statement::Assign { expr: Expr::SlotAccess { slot: SlotAccessType::Field(f), .. }, .. } => Some(f.as_str()),
+            _ => None,
+        }).collect::<Vec<_>>();
+        assert_eq!( fields, vec!["b", "c"] );
+    }
+
+    #[test]
+    fn should_lift_lambda_with_captured_environment() {
+        let irs = lower("fun f(a : Number, b : Number) -> fun(Number) -> Number = |x| add(a, x);");
+
+        assert_eq!( irs.len(), 2 );
+        let (env, closure) = match &irs[0].statements[..] {
+            [ Statement::Assign { name: env, expr: Expr::Environment(captured) }
+            , Statement::Assign { name: closure, expr: Expr::Closure { fun, env: closure_env } }
+            , Statement::Return(r)
+            ] if *captured == vec![Symbol::User("a".to_string())] && *fun == irs[1].name && closure_env == env => (env, r),
+            s => panic!("expected environment and closure, found {:?}", s),
+        };
+        assert_ne!( env, closure );
+
+        let lambda = &irs[1];
+        assert_eq!( lambda.params.len(), 2 );
+        assert_eq!( lambda.params[1], Symbol::User("x".to_string()) );
+        assert!( matches!( &lambda.statements[0], Statement::Assign { expr: Expr::SlotAccess { data, slot: SlotAccessType::Index(0) }, .. }
+            if *data == lambda.params[0] ) );
+    }
+
+    #[test]
+    fn should_share_environment_in_let_rec() {
+        let irs = lower("fun f(n : Number) -> Bool = even(n) where even = |i| if i == 0 then true else odd(i - 1), odd = |i| if i == 0 then false else even(i - 1);");
+
+        assert_eq!( irs.len(), 3 );
+        let envs = irs[0].statements.iter().filter(|s| matches!( s, Statement::Assign { expr: Expr::Environment(_), .. } )).count();
+        assert_eq!( envs, 1 );
+
+        // Each lambda rebuilds both closures from its own environment parameter
+        for lambda in &irs[1..] {
+            let closures = lambda.statements.iter().filter_map(|s| match s {
+                Statement::Assign { expr: Expr::Closure { fun, env }, .. } if *env == lambda.params[0] => Some(fun.clone()),
+                _ => None,
+            }).collect::<Vec<_>>();
+            assert_eq!( closures, vec![irs[1].name.clone(), irs[2].name.clone()] );
+        }
+    }
+
+    #[test]
+    fn should_lift_nested_lambdas() {
+        let irs = lower("fun f(a : Number) -> Number = (|x| (|y| add(a, y))(x))(1);");
+
+        assert_eq!( irs.len(), 3 );
+        // The inner lambda captures 'a' through the outer lambda's environment
+        assert!( matches!( &irs[0].statements[0], Statement::Assign { expr: Expr::Environment(captured), .. }
+            if *captured == vec![Symbol::User("a".to_string())] ) );
+    }
+
+    #[test]
+    fn should_order_constants_by_dependency() {
+        let result = constants("
+            let c = f(1);
+            let a = b.add(1);
+            let b = 1;
+            fun f(x : Number) -> Number = x.add(a);
+            let d = 2;
+        ").expect("should generate");
+
+        assert_eq!( result, vec!["b", "a", "c", "d"] );
+    }
+
+    #[test]
+    fn should_not_depend_on_shadowed_constant() {
+        let result = constants("
+            let a = f(1);
+            fun f(a : Number) -> Number = let b = 1 in a.add(b);
+            let b = 2;
+        ").expect("should generate");
+
+        assert_eq!( result, vec!["a", "b"] );
+    }
+
+    #[test]
+    fn should_reject_constant_cycle_through_function() {
+        let result = constants("
+            let a = b;
+            let b = f();
+            fun f() -> Number = a;
+        ");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, span)) if message == "Encountered top level let cycle a -> b -> a" && span.start.line == 2 ) );
+    }
+
+    #[test]
+    fn should_reject_duplicate_constant() {
+        let result = constants("let a = 1; fun a() -> Number = 2;");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Encountered already defined top level let a" ) );
+    }
+
+    #[test]
+    fn should_find_main_entry() {
+        let asts = parse("fun helper() -> Number = 1; fun main() -> Number = helper();").expect("should parse");
+        let (program, _) = generate(asts).expect("should generate");
+        assert_eq!( program.entry, Some(Symbol::User("main".to_string())) );
+
+        let asts = parse("fun main(x : Number) -> Number = x;").expect("should parse");
+        let result = generate(asts);
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "main must not take any parameters" ) );
+    }
+
+    #[test]
+    fn should_lower_tuple_to_anon_constructor() {
+        let irs = lower("fun f(a : Number) -> (Number, String, Bool) = (a, \"x\", true);");
+
+        assert!( matches!( &irs[0].statements[2], Statement::Assign { expr: Expr::Constructor { cons_tag: ConsTag::Anon(tag), slots_assigns }, .. }
+            if tag == "#tuple_3" && matches!( &slots_assigns[0], Expr::Variable(Symbol::User(a)) if a == "a" ) ) );
+    }
+
+    #[test]
+    fn should_type_quantified_variables_as_arrays() {
+        fn depth(t : &Type) -> usize {
+            match t {
+                Type::Index { name: ConcreteType(name), params } if name == "Array" => 1 + depth(&params[0]),
+                _ => 0,
+            }
+        }
+
+        let asts = parse("fun f() -> Number = [| x, (y{2})*, Pair(z, _)+ |];").expect("test input should parse");
+        let t = match &asts[0] {
+            Ast::FunDef { expr, .. } => result_type(expr),
+            _ => panic!("expected fun def"),
+        };
+        let rows = match &t {
+            Type::Index { params, .. } => match &params[0] {
+                Type::Anon(rows) => rows,
+                _ => panic!("expected anon results"),
+            },
+            _ => panic!("expected array of results"),
+        };
+
+        assert_eq!( depth(&t), 1 );
+        assert_eq!( rows.iter().map(|row| (row.name.as_str(), depth(&row.t))).collect::<Vec<_>>(), vec![("x", 0), ("y", 2), ("z", 1)] );
+    }
+}
@@ -1,5 +1,9 @@
 
 pub mod data;
 pub mod generator;
+pub mod emit;
 
-mod type_info;
\ No newline at end of file
+mod type_info;
+mod named_patterns;
+mod bindings;
+mod refutability;
@@ -0,0 +1,280 @@
+
+use std::collections::HashMap;
+
+use crate::ast::{ Ast
+                , Span
+                , Expr
+                , ExprKind
+                , NamedPattern
+                , ArrayPattern
+                , ArrayPatternKind
+                , PathPattern
+                , PathPatternKind
+                };
+
+use crate::visit::{self, VisitMut};
+
+use super::data::StaticError;
+
+// Replaces every reference to a top level pattern definition with the pattern that it names.
+// A reference directly inside of '{| ... |}' or '[| ... |]' is replaced with all of the named
+// pattern's items.  Anywhere else the named pattern must consist of exactly one item.
+pub fn inline(asts : Vec<Ast>) -> Result<Vec<Ast>, StaticError> {
+    let (defs, asts) : (Vec<Ast>, Vec<Ast>) = asts.into_iter().partition(|ast| matches!( ast, Ast::PatternDef { .. } ));
+
+    let mut inliner = Inliner { defs: HashMap::new(), resolved: HashMap::new(), stack: vec![] };
+
+    for def in defs {
+        let (name, pattern, span) = match def {
+            Ast::PatternDef { name, pattern, span } => (name, pattern, span),
+            _ => panic!("inline should only have pattern defs at this point"),
+        };
+        if inliner.defs.contains_key(&name) {
+            return Err(StaticError::Fatal(format!("Encountered duplicate pattern name {name}"), span));
+        }
+        inliner.defs.insert(name, (pattern, span));
+    }
+
+    // Definitions are resolved even if they are never referenced so that their errors are still reported
+    let mut names = inliner.defs.iter().map(|(name, (_, span))| (name.to_string(), *span)).collect::<Vec<_>>();
+    names.sort_by_key(|(_, span)| (span.start.line, span.start.column));
+    for (name, span) in names {
+        inliner.resolve(&name, span)?;
+    }
+
+    let mut asts = asts;
+    for ast in &mut asts {
+        match ast {
+            Ast::FunDef { params, expr, .. } => {
+                for param in params {
+                    inliner.standard(&mut param.pattern)?;
+                }
+                inliner.expr(expr)?;
+            },
+            Ast::LetDef { expr, .. } => inliner.expr(expr)?,
+            _ => { },
+        }
+    }
+    Ok(asts)
+}
+
+struct Inliner {
+    defs : HashMap<String, (NamedPattern, Span)>,
+    resolved : HashMap<String, NamedPattern>,
+    stack : Vec<String>,
+}
+
+impl Inliner {
+    fn resolve(&mut self, name : &str, span : Span) -> Result<NamedPattern, StaticError> {
+        if let Some(pattern) = self.resolved.get(name) {
+            return Ok(pattern.clone());
+        }
+
+        if let Some(index) = self.stack.iter().position(|n| n == name) {
+            let cycle = self.stack[index..].iter()
+                                           .map(|n| n.as_str())
+                                           .chain(std::iter::once(name))
+                                           .collect::<Vec<_>>()
+                                           .join(" -> ");
+            return Err(StaticError::Fatal(format!("Encountered pattern cycle {cycle}"), span));
+        }
+
+        let mut pattern = match self.defs.get(name) {
+            Some((pattern, _)) => pattern.clone(),
+            None => return Err(StaticError::Fatal(format!("Encountered unknown pattern name {name}"), span)),
+        };
+
+        self.stack.push(name.to_string());
+        match &mut pattern {
+            NamedPattern::Path(ps) => self.path_series(ps)?,
+            NamedPattern::Array(ps) => self.array_series(ps)?,
+        }
+        self.stack.pop();
+
+        self.resolved.insert(name.to_string(), pattern.clone());
+        Ok(pattern)
+    }
+
+    fn path_series(&mut self, ps : &mut Vec<PathPattern>) -> Result<(), StaticError> {
+        let mut ret = vec![];
+        for mut p in std::mem::take(ps) {
+            match &p.kind {
+                PathPatternKind::Named(name) => match self.resolve(name, p.span)? {
+                    NamedPattern::Path(mut items) => ret.append(&mut items),
+                    NamedPattern::Array(_) => return Err(mismatch(name, "an array", "a path", p.span)),
+                },
+                _ => {
+                    self.path(&mut p)?;
+                    ret.push(p);
+                },
+            }
+        }
+        *ps = ret;
+        Ok(())
+    }
+
+    fn array_series(&mut self, ps : &mut Vec<ArrayPattern>) -> Result<(), StaticError> {
+        let mut ret = vec![];
+        for mut p in std::mem::take(ps) {
+            match &p.kind {
+                ArrayPatternKind::Named(name) => match self.resolve(name, p.span)? {
+                    NamedPattern::Array(mut items) => ret.append(&mut items),
+                    NamedPattern::Path(_) => return Err(mismatch(name, "a path", "an array", p.span)),
+                },
+                _ => {
+                    self.array(&mut p)?;
+                    ret.push(p);
+                },
+            }
+        }
+        *ps = ret;
+        Ok(())
+    }
+}
+
+impl VisitMut for Inliner {
+    type Error = StaticError;
+
+    fn path(&mut self, pattern : &mut PathPattern) -> Result<(), StaticError> {
+        match &mut pattern.kind {
+            PathPatternKind::Named(name) => {
+                let name = name.to_string();
+                match self.resolve(&name, pattern.span)? {
+                    NamedPattern::Path(mut items) if items.len() == 1 => *pattern = items.remove(0),
+                    NamedPattern::Path(_) => return Err(not_single(&name, pattern.span)),
+                    NamedPattern::Array(_) => return Err(mismatch(&name, "an array", "a path", pattern.span)),
+                }
+            },
+            _ => visit::walk_path_mut(self, pattern)?,
+        }
+        Ok(())
+    }
+
+    fn array(&mut self, pattern : &mut ArrayPattern) -> Result<(), StaticError> {
+        match &mut pattern.kind {
+            ArrayPatternKind::Named(name) => {
+                let name = name.to_string();
+                match self.resolve(&name, pattern.span)? {
+                    NamedPattern::Array(mut items) if items.len() == 1 => *pattern = items.remove(0),
+                    NamedPattern::Array(_) => return Err(not_single(&name, pattern.span)),
+                    NamedPattern::Path(_) => return Err(mismatch(&name, "a path", "an array", pattern.span)),
+                }
+            },
+            _ => visit::walk_array_mut(self, pattern)?,
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr : &mut Expr) -> Result<(), StaticError> {
+        match &mut expr.kind {
+            ExprKind::NamedPattern(name) => {
+                expr.kind = match self.resolve(&name.to_string(), expr.span)? {
+                    NamedPattern::Path(ps) => ExprKind::PathPattern(ps),
+                    NamedPattern::Array(ps) => ExprKind::ArrayPattern(ps),
+                };
+            },
+            ExprKind::PathPattern(ps) => self.path_series(ps)?,
+            ExprKind::ArrayPattern(ps) => self.array_series(ps)?,
+            _ => visit::walk_expr_mut(self, expr)?,
+        }
+        Ok(())
+    }
+}
+
+fn mismatch(name : &str, is : &str, used_in : &str, span : Span) -> StaticError {
+    StaticError::Fatal(format!("Pattern {name} is {is} pattern and cannot be used in {used_in} pattern"), span)
+}
+
+fn not_single(name : &str, span : Span) -> StaticError {
+    StaticError::Fatal(format!("Pattern {name} has more than one item and can only be used directly inside of {{| |}} or [| |]"), span)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsing::parser::parse;
+
+    fn inline_input(input : &str) -> Result<Vec<Ast>, StaticError> {
+        inline(parse(input).expect("test input should parse"))
+    }
+
+    fn fun_expr(asts : &[Ast]) -> &Expr {
+        match asts.iter().find(|ast| matches!( ast, Ast::FunDef { .. } )) {
+            Some(Ast::FunDef { expr, .. }) => expr,
+            _ => panic!("expected fun def"),
+        }
+    }
+
+    #[test]
+    fn should_splice_named_pattern_into_path_pattern() {
+        let asts = inline_input("
+            pattern children = {| Node(!, !), Leaf(x) |};
+            pattern leaves = {| Root(!), $children |};
+            fun f() -> Number = $leaves;
+        ").expect("should inline");
+
+        assert_eq!( asts.len(), 1 );
+        let ps = match &fun_expr(&asts).kind {
+            ExprKind::PathPattern(ps) => ps,
+            e => panic!("expected path pattern, found {:?}", e),
+        };
+        assert_eq!( ps.len(), 3 );
+        assert!( matches!( &ps[0].kind, PathPatternKind::Cons { name, .. } if name == "Root" ) );
+        assert!( matches!( &ps[2].kind, PathPatternKind::Cons { name, .. } if name == "Leaf" ) );
+    }
+
+    #[test]
+    fn should_replace_nested_single_item_pattern() {
+        let asts = inline_input("
+            pattern one = [| Cons(a, _) |];
+            fun f() -> Number = [| Pair($one, b) |];
+        ").expect("should inline");
+
+        assert!( matches!( &fun_expr(&asts).kind, ExprKind::ArrayPattern(ps)
+            if matches!( &ps[0].kind, ArrayPatternKind::Cons { params, .. } if matches!( &params[0].kind, ArrayPatternKind::Cons { name, .. } if name == "Cons" ) ) ) );
+    }
+
+    #[test]
+    fn should_reject_nested_multiple_item_pattern() {
+        let result = inline_input("
+            pattern two = [| a, b |];
+            fun f() -> Number = [| Pair($two, b) |];
+        ");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message.starts_with("Pattern two has more than one item") ) );
+    }
+
+    #[test]
+    fn should_reject_pattern_kind_mismatch() {
+        let result = inline_input("
+            pattern p = {| Leaf(x) |};
+            fun f() -> Number = [| $p |];
+        ");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Pattern p is a path pattern and cannot be used in an array pattern" ) );
+    }
+
+    #[test]
+    fn should_reject_pattern_cycle() {
+        let result = inline_input("
+            pattern a = {| X(!), $b |};
+            pattern b = {| Y(!), $a |};
+        ");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, span)) if message == "Encountered pattern cycle a -> b -> a" && span.start.line == 3 ) );
+    }
+
+    #[test]
+    fn should_reject_unknown_pattern() {
+        let result = inline_input("fun f() -> Number = $missing;");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Encountered unknown pattern name missing" ) );
+    }
+
+    #[test]
+    fn should_reject_duplicate_pattern_name() {
+        let result = inline_input("pattern a = {| X |}; pattern a = {| Y |};");
+
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Encountered duplicate pattern name a" ) );
+    }
+}
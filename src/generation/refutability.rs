@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::ast::{ Ast
+                , ExprKind
+                , FunParam
+                , StandardPattern
+                , StandardPatternKind
+                };
+
+use crate::ir::ConsTag;
+
+use super::data::{ StaticWarning
+                 , ConcreteType
+                 , ConsInfo
+                 };
+
+use crate::visit;
+
+// A let or parameter with a pattern that might not match would fail at runtime, so each one is warned about.
+pub fn check(funcs : &[Ast], type_to_info : &HashMap<ConcreteType, Vec<ConsInfo>>) -> Vec<StaticWarning> {
+    // Constructor name to how many constructors its data type has
+    let siblings = type_to_info.values()
+                               .flat_map(|infos| infos.iter().filter_map(move |info| match &info.tag {
+                                   ConsTag::User(name) => Some((name.to_string(), infos.len())),
+                                   ConsTag::Anon(_) => None,
+                               }))
+                               .collect::<HashMap<_, _>>();
+
+    let mut warnings = vec![];
+    let check_params = |params : &[FunParam], warnings : &mut Vec<StaticWarning>| {
+        for param in params.iter().filter(|p| !irrefutable(&p.pattern, &siblings)) {
+            warnings.push(StaticWarning("parameter pattern might not match; use a match expression to handle the other cases".to_string(), param.pattern.span));
+        }
+    };
+    for fun in funcs {
+        let expr = match fun {
+            Ast::FunDef { params, expr, .. } => {
+                check_params(params, &mut warnings);
+                expr
+            },
+            Ast::LetDef { expr, .. } => expr,
+            _ => continue,
+        };
+        visit::exprs(expr, &mut |e| match &e.kind {
+            ExprKind::Let { pattern, .. } if !irrefutable(pattern, &siblings) =>
+                warnings.push(StaticWarning("let pattern might not match; use a match expression to handle the other cases".to_string(), pattern.span)),
+            ExprKind::Lambda { params, .. } => check_params(params, &mut warnings),
+            _ => { },
+        });
+    }
+    warnings
+}
+
+fn irrefutable(pattern : &StandardPattern, siblings : &HashMap<String, usize>) -> bool {
+    match &pattern.kind {
+        StandardPatternKind::Variable(_) | StandardPatternKind::Wildcard => true,
+        StandardPatternKind::At { pattern, .. } => irrefutable(pattern, siblings),
+        StandardPatternKind::Cons { name, params } =>
+            siblings.get(name) == Some(&1) && params.iter().all(|p| irrefutable(p, siblings)),
+        StandardPatternKind::Record { name, fields } =>
+            siblings.get(name) == Some(&1) && fields.iter().all(|(_, p)| irrefutable(p, siblings)),
+        StandardPatternKind::Tuple(items) => items.iter().all(|p| irrefutable(p, siblings)),
+        StandardPatternKind::Or(alternatives) => alternatives.iter().any(|p| irrefutable(p, siblings)),
+        // Arrays can always be too short or too long
+        StandardPatternKind::StandardArray(_)
+        | StandardPatternKind::Number(_)
+        | StandardPatternKind::Range { .. }
+        | StandardPatternKind::Bool(_)
+        | StandardPatternKind::String(_)
+        | StandardPatternKind::If { .. } => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsing::parser::parse;
+    use super::super::type_info;
+
+    fn warnings(input : &str) -> Vec<StaticWarning> {
+        let asts = parse(input).expect("test input should parse");
+        let (datas, funcs) : (Vec<Ast>, Vec<Ast>) = asts.into_iter().partition(|ast| matches!( ast, Ast::DataDef { .. } ));
+        let (_, type_to_info) = type_info::determine_type_info(datas).expect("test input should have valid data");
+        check(&funcs, &type_to_info)
+    }
+
+    #[test]
+    fn should_allow_irrefutable_lets() {
+        let result = warnings("
+            data Pair = Pair(Number, Number);
+            data Person = Person { name : String, age : Number };
+            fun f(p : Pair, q : Person) -> Number =
+                let Pair(a, _) = p in
+                let x @ Person { name, age : y } = q in
+                let z = 1 in
+                let (b, Pair(c, _)) = (z, p) in
+                a;
+        ");
+
+        assert!( result.is_empty() );
+    }
+
+    #[test]
+    fn should_warn_on_refutable_lets() {
+        let result = warnings("
+            data List = Cons(Number, List) | Nil;
+            data Pair = Pair(Number, Number);
+            fun f(l : List, p : Pair, xs : [Number]) -> Number =
+                let Cons(h, t) = l in
+                let Pair(1, b) = p in
+                let [a, b | rest] = xs in
+                let (1, d) = (a, b) in
+                match l { _ => let x if x.gt(0) = 5 in x };
+        ");
+
+        assert_eq!( result.len(), 5 );
+        assert_eq!( result[0].1.start.line, 5 );
+        assert_eq!( result[3].1.start.line, 8 );
+        assert_eq!( result[4].1.start.line, 9 );
+    }
+
+    #[test]
+    fn should_warn_on_refutable_params() {
+        let result = warnings("
+            data List = Cons(Number, List) | Nil;
+            data Pair = Pair(Number, Number);
+            fun first([x | _] : [Number], Pair(a, _) : Pair) -> Number =
+                fold(x, |Cons(h, _), (b, _), c| h);
+        ");
+
+        assert_eq!( result.len(), 2 );
+        assert_eq!( result[0].1.start.line, 4 );
+        assert_eq!( result[1].1.start.line, 5 );
+        assert!( result.iter().all(|w| w.0.starts_with("parameter pattern might not match")) );
+    }
+}
@@ -1,7 +1,7 @@
 
 use std::collections::HashMap;
 
-use crate::ast;
+use crate::ast::{self, Span};
 
 use crate::ir::ConsTag;
 
@@ -21,12 +21,28 @@ pub fn ast_to_ir_type(t : &ast::Type) -> Type {
 
     let array = ConcreteType("Array".to_string());
 
-    match t {
-        ast::Type::Generic(name) => super::data::Type::Generic(name.to_string()),
-        ast::Type::Concrete(name) => super::data::Type::Concrete(ConcreteType(name.to_string())),
-        ast::Type::Array(t) => super::data::Type::Index { name: array.clone(), params: vec![ast_to_ir_type(t)] },
-        ast::Type::Fun { input, output } => super::data::Type::Fun { input: m(input), output: Box::new(ast_to_ir_type(output)) },
-        ast::Type::Index { name, params } => super::data::Type::Index { name: ConcreteType(name.to_string()), params: m(params) },
+    match &t.kind {
+        ast::TypeKind::Generic(name) => super::data::Type::Generic(name.to_string()),
+        ast::TypeKind::Concrete(name) => super::data::Type::Concrete(ConcreteType(name.to_string())),
+        ast::TypeKind::Array(t) => super::data::Type::Index { name: array.clone(), params: vec![ast_to_ir_type(t)] },
+        ast::TypeKind::Fun { input, output } => super::data::Type::Fun { input: m(input), output: Box::new(ast_to_ir_type(output)) },
+        ast::TypeKind::Index { name, params } => super::data::Type::Index { name: ConcreteType(name.to_string()), params: m(params) },
+        ast::TypeKind::Anon(rows) => super::data::Type::Anon(rows.iter().map(|(name, t)| RowType { name: name.to_string(), t: ast_to_ir_type(t) }).collect()),
+        ast::TypeKind::Tuple(ts) => super::data::Type::Tuple(m(ts)),
+    }
+}
+
+fn generics(t : &ast::Type, out : &mut Vec<(String, Span)>) {
+    match &t.kind {
+        ast::TypeKind::Generic(name) => out.push((name.to_string(), t.span)),
+        ast::TypeKind::Concrete(_) => { },
+        ast::TypeKind::Array(t) => generics(t, out),
+        ast::TypeKind::Fun { input, output } => {
+            input.iter().for_each(|t| generics(t, out));
+            generics(output, out);
+        },
+        ast::TypeKind::Index { params, .. } | ast::TypeKind::Tuple(params) => params.iter().for_each(|t| generics(t, out)),
+        ast::TypeKind::Anon(rows) => rows.iter().for_each(|(_, t)| generics(t, out)),
     }
 }
 
@@ -38,20 +54,52 @@ pub fn determine_type_info( data_defs : Vec<ast::Ast> )
     let mut cons_lookup = HashMap::new();
 
     for data_def in data_defs {
-        let (concrete_type, cons_defs) = match data_def { 
-            ast::Ast::DataDef { name, cons_defs } => (ConcreteType(name), cons_defs),
+        let (concrete_type, type_params, cons_defs, span) = match data_def { 
+            ast::Ast::DataDef { name, type_params, cons_defs, span, .. } => (ConcreteType(name), type_params, cons_defs, span),
             _ => panic!( "Encountered non DataDef variant"),
         };
 
+        for (i, type_param) in type_params.iter().enumerate() {
+            if type_params[..i].contains(type_param) {
+                let ConcreteType(ct) = concrete_type;
+                return Err(StaticError::Fatal(format!("Encountered duplicate type parameter {type_param} in {ct}"), span));
+            }
+        }
+
+        for cons_def in &cons_defs {
+            let mut used = vec![];
+            for param in &cons_def.params {
+                generics(param, &mut used);
+            }
+
+            if let Some((generic, generic_span)) = used.into_iter().find(|(g, _)| !type_params.contains(g)) {
+                let ConcreteType(ct) = concrete_type;
+                return Err(StaticError::Fatal(format!("Constructor {} uses undeclared type parameter {generic} of {ct}", cons_def.name), generic_span));
+            }
+        }
+
+        for cons_def in &cons_defs {
+            let fields = match &cons_def.fields {
+                Some(fields) => fields,
+                None => continue,
+            };
+            for (i, field) in fields.iter().enumerate() {
+                if fields[..i].contains(field) {
+                    return Err(StaticError::Fatal(format!("Constructor {} has duplicate field {field}", cons_def.name), span));
+                }
+            }
+        }
+
         if cons_lookup.contains_key(&concrete_type) {
             let ConcreteType(ct) = concrete_type;
-            return Err(StaticError::Fatal(format!("Encountered duplicate type name {ct}")));
+            return Err(StaticError::Fatal(format!("Encountered duplicate type name {ct}"), span));
         }
 
         let cons_infos : Vec<ConsInfo> 
             = cons_defs.into_iter()
                        .map(|c| ConsInfo{ tag: ConsTag::User(c.name)
                                         , ts: c.params.iter().map(ast_to_ir_type).collect()
+                                        , fields: c.fields
                                         } ).collect();
 
 
@@ -63,7 +111,7 @@ pub fn determine_type_info( data_defs : Vec<ast::Ast> )
                     ConsTag::User(t) => t,
                     _ => panic!("determine_type_info all cons tags should be User tags at this point"),
                 };
-                return Err(StaticError::Fatal(format!("Encountered duplicate constructor name {tag}")));
+                return Err(StaticError::Fatal(format!("Encountered duplicate constructor name {tag}"), span));
             }
 
             type_lookup.insert( info.tag, concrete_type.clone() );
@@ -71,4 +119,92 @@ pub fn determine_type_info( data_defs : Vec<ast::Ast> )
     }
     
     Ok((type_lookup, cons_lookup))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsing::parser::parse;
+
+    type TypeInfo = ( HashMap<ConsTag, ConcreteType>
+                    , HashMap<ConcreteType, Vec<ConsInfo>> );
+
+    fn type_info(input : &str) -> Result<TypeInfo, StaticError> {
+        determine_type_info(parse(input).expect("test input should parse"))
+    }
+
+    #[test]
+    fn should_accept_declared_type_params() {
+        let result = type_info("data List<a> = Cons(a, List<a>) | Nil; data F<a, b> = F(fun(a) -> [b]);");
+        assert!( result.is_ok() );
+    }
+
+    #[test]
+    fn should_reject_undeclared_type_param() {
+        let result = type_info("data List = Cons(a, List<a>) | Nil;");
+        assert!( matches!( result, Err(StaticError::Fatal(message, span)) 
+            if message == "Constructor Cons uses undeclared type parameter a of List" && span.start.column == 18 ) );
+    }
+
+    #[test]
+    fn should_reject_nested_undeclared_type_param() {
+        let result = type_info("data F<a> = F(fun(a) -> [b]);");
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message.contains("type parameter b") ) );
+    }
+
+    #[test]
+    fn should_reject_duplicate_type_param() {
+        let result = type_info("data Pair<a, a> = Pair(a, a);");
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message.contains("duplicate type parameter a") ) );
+    }
+
+    #[test]
+    fn should_keep_record_field_names() {
+        let (_, cons_lookup) = type_info("data Person = Person { name : String, age : Number };").expect("should be ok");
+        let infos = &cons_lookup[&ConcreteType("Person".to_string())];
+        assert_eq!( infos[0].fields, Some(vec!["name".to_string(), "age".to_string()]) );
+    }
+
+    #[test]
+    fn should_reject_duplicate_record_field() {
+        let result = type_info("data Person = Person { name : String, name : Number };");
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message == "Constructor Person has duplicate field name" ) );
+    }
+
+    #[test]
+    fn should_convert_anon_type() {
+        let asts = parse("fun f(p : { a : x, b : [Number] }) -> Number = 1;").expect("test input should parse");
+        let t = match &asts[0] {
+            ast::Ast::FunDef { params, .. } => params[0].t.as_ref().expect("param should have type"),
+            _ => panic!("expected fun def"),
+        };
+        let rows = match ast_to_ir_type(t) {
+            Type::Anon(rows) => rows,
+            t => panic!("expected anon type, found {:?}", t),
+        };
+        assert_eq!( rows.len(), 2 );
+        assert!( rows[0].name == "a" && matches!( &rows[0].t, Type::Generic(x) if x == "x" ) );
+        assert!( rows[1].name == "b" && matches!( &rows[1].t, Type::Index { .. } ) );
+    }
+
+    #[test]
+    fn should_convert_tuple_type_structurally() {
+        let asts = parse("fun f(p : (Number, a)) -> Number = 1;").expect("test input should parse");
+        let t = match &asts[0] {
+            ast::Ast::FunDef { params, .. } => params[0].t.as_ref().expect("param should have type"),
+            _ => panic!("expected fun def"),
+        };
+        let ts = match ast_to_ir_type(t) {
+            Type::Tuple(ts) => ts,
+            t => panic!("expected tuple type, found {:?}", t),
+        };
+        assert_eq!( ts.len(), 2 );
+        assert!( matches!( &ts[1], Type::Generic(a) if a == "a" ) );
+    }
+
+    #[test]
+    fn should_reject_undeclared_type_param_in_anon_type() {
+        let result = type_info("data Wrap = Wrap({ a : b });");
+        assert!( matches!( result, Err(StaticError::Fatal(message, _)) if message.contains("type parameter b") ) );
+    }
+}
@@ -1,4 +1,16 @@
 
+use std::collections::HashMap;
+
+use crate::ast::Span;
+
+#[derive(Debug)]
+pub struct Program {
+    pub irs : Vec<Ir>,
+    pub constants : Vec<Symbol>, // Top level lets in the order that they are evaluated
+    pub entry : Option<Symbol>, // The 'main' function
+    pub fields : HashMap<ConsTag, Vec<String>>, // Record constructors in declared order, which field access looks up at runtime
+}
+
 #[derive(Debug)]
 pub struct Ir {
     pub name : Symbol,
@@ -11,22 +23,29 @@ pub enum Statement {
     Assign { name : Symbol, expr : Expr },
     If { target: Symbol, statements : Vec<Statement> },
     Return(Symbol),
+    Fail(String, Span), // Stops the program with an error about the code at the span, e.g. when no case of a match matches
 }
 
 #[derive(Debug)] 
 pub enum SlotAccessType {
     Tag,
     Index(usize),
+    Field(String),
 }
 
 #[derive(Debug)]
 pub enum Expr {
     Number(i64),
+    Float(f64),
     Bool(bool),
+    String(String),
     Variable(Symbol),
     Array(Vec<Expr>),
     Constructor { cons_tag : ConsTag, slots_assigns : Vec<Expr> },
+    Anon(Vec<(String, Expr)>), // A value of an anon type, e.g. the variables bound by one match of a path pattern
+    Tag(ConsTag), // Compared against the Tag slot of a constructed value
     Environment(Vec<Symbol>),
+    Closure { fun : Symbol, env : Symbol },
     SlotAccess { data : Symbol, slot : SlotAccessType }, 
     FunCall { name : Symbol, params : Vec<Symbol> },
 }
@@ -41,4 +60,56 @@ pub enum Symbol {
 pub enum ConsTag {
     User(String),
     Anon(String),
-}
\ No newline at end of file
+}
+
+impl Ir {
+    // Every symbol that the ir uses without defining it, which are the top level functions and lets
+    // (and builtins) that it refers to.
+    pub fn free_symbols(&self) -> Vec<Symbol> {
+        fn statements(ss : &[Statement], defined : &mut Vec<Symbol>, used : &mut Vec<Symbol>) {
+            for s in ss {
+                match s {
+                    Statement::Assign { name, expr: e } => {
+                        expr(e, used);
+                        defined.push(name.clone());
+                    },
+                    Statement::If { target, statements: ss } => {
+                        used.push(target.clone());
+                        statements(ss, defined, used);
+                    },
+                    Statement::Return(sym) => used.push(sym.clone()),
+                    Statement::Fail(..) => { },
+                }
+            }
+        }
+
+        fn expr(e : &Expr, used : &mut Vec<Symbol>) {
+            match e {
+                Expr::Number(_) | Expr::Float(_) | Expr::Bool(_) | Expr::String(_) | Expr::Tag(_) => { },
+                Expr::Variable(sym) => used.push(sym.clone()),
+                Expr::Array(es) => es.iter().for_each(|e| expr(e, used)),
+                Expr::Constructor { slots_assigns, .. } => slots_assigns.iter().for_each(|e| expr(e, used)),
+                Expr::Anon(fields) => fields.iter().for_each(|(_, e)| expr(e, used)),
+                Expr::Environment(syms) => used.extend(syms.iter().cloned()),
+                Expr::Closure { fun, env } => used.extend([fun.clone(), env.clone()]),
+                Expr::SlotAccess { data, .. } => used.push(data.clone()),
+                Expr::FunCall { name, params } => {
+                    used.push(name.clone());
+                    used.extend(params.iter().cloned());
+                },
+            }
+        }
+
+        let mut defined = self.params.clone();
+        let mut used = vec![];
+        statements(&self.statements, &mut defined, &mut used);
+
+        let mut ret : Vec<Symbol> = vec![];
+        for sym in used {
+            if !defined.contains(&sym) && !ret.contains(&sym) {
+                ret.push(sym);
+            }
+        }
+        ret
+    }
+}
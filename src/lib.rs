@@ -0,0 +1,7 @@
+pub mod ast;
+pub mod visit;
+pub mod parsing;
+pub mod ir;
+pub mod generation;
+pub mod execution;
+pub mod diagnostics;
@@ -1,9 +1,5 @@
 
-mod ast;
-mod parsing;
-mod ir;
-mod generation;
-mod execution;
+use pattern::{parsing, generation, execution, diagnostics};
 
 /* TODO buildin functions:
 
@@ -36,17 +32,16 @@ mod execution;
     nth : [a] -> Number -> a
 
     
-    anon types exist but are not parsable (atm)
+    anon types are written { a : Number, b : List<x> }
     path_pattern<anon>
     array_pattern<anon>
 
 */
 
 fn main() {
-    use parsing::parser;
-    use generation::generator;
-    use execution::vm::{VM, DefaultSystemCalls};
-    use execution::instr::InstructionAddress;
+    use parsing::loader;
+    use generation::{generator, emit};
+    use execution::vm::{VM, DefaultSystemCalls, Exit};
 
     /* TODO:  At runtime we're looking at:
                     Cons
@@ -60,19 +55,95 @@ fn main() {
 
     */
 
-    // TODO:  Need to add some syntax to access anon object fields
-    // TODO:  I suppose you can hijack dot syntax.  
+    // Anon object fields are accessed with dot syntax (same as record fields):
     // {| Blah(!, a), Cons(b, c) |}.path( some_object ) => [{a,b,c}]
-    // Then funcall(a, {a,b,c}) => access a
-
-
-    let asts = parser::parse("input").unwrap(); // TODO handle err case
-    let ir = generator::generate(asts).unwrap(); // TODO handle err case
+    // Then x.a => access a
+
+
+    let args = std::env::args().collect::<Vec<_>>();
+
+    if let [_, mode, file_name] = &args[..] {
+        if mode == "fmt" {
+            format(file_name);
+            return;
+        }
+        if mode == "doc" {
+            reference(file_name);
+            return;
+        }
+    }
+
+    let file_name = args.get(1).expect("usage: pattern [fmt|doc] <file>");
+
+    let (asts, files) = match loader::load(file_name) {
+        Ok(x) => x,
+        Err(e) => {
+            eprint!("{}", diagnostics::render_load_error(&e));
+            std::process::exit(1);
+        },
+    };
+
+    let program = match generator::generate(asts) {
+        Ok((program, warnings)) => {
+            for w in &warnings {
+                eprint!("{}", diagnostics::render_static_warning(&files, w));
+            }
+            program
+        },
+        Err(e) => {
+            eprint!("{}", diagnostics::render_static_error(&files, &e));
+            std::process::exit(1);
+        },
+    };
+
+    let (instructions, entry) = match emit::emit(&program, &files) {
+        Ok(x) => x,
+        Err(e) => {
+            eprint!("{}", diagnostics::render_static_error(&files, &e));
+            std::process::exit(1);
+        },
+    };
 
     let mut sys_calls = DefaultSystemCalls{ };
-    let mut vm = VM::new(vec![], InstructionAddress(0));
+    let mut vm = VM::new(instructions, entry);
 
-    vm.run(&mut sys_calls);
+    if vm.run(&mut sys_calls) == Exit::Failure {
+        std::process::exit(1);
+    }
+}
 
+// Prints the canonical layout of the file to stdout; the file itself is left alone.
+fn format(file_name : &str) {
+    use parsing::{parser, printer};
+
+    let input = match std::fs::read_to_string(file_name) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("could not read {file_name}: {e}");
+            std::process::exit(1);
+        },
+    };
+
+    match parser::parse_with_comments(&input) {
+        Ok((asts, comments)) => print!("{}", printer::print(&asts, &comments)),
+        Err(errors) => {
+            for e in &errors {
+                eprint!("{}", diagnostics::render_parse_error(file_name, &input, e));
+            }
+            std::process::exit(1);
+        },
+    }
+}
 
+// Prints a markdown reference of the file's data types and functions to stdout.
+fn reference(file_name : &str) {
+    use parsing::{loader, printer};
+
+    match loader::load(file_name) {
+        Ok((asts, _)) => print!("{}", printer::print_reference(&asts)),
+        Err(e) => {
+            eprint!("{}", diagnostics::render_load_error(&e));
+            std::process::exit(1);
+        },
+    }
 }
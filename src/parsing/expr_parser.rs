@@ -1,66 +1,149 @@
 
 use super::input::{Input, ParseError};
+use super::lexer::{Token, TokenKind};
 use super::util::{ into
                  , parse_series
                  , parse_array
                  , parse_params
                  , parse_symbol
+                 , parse_qualified_symbol
+                 , last_segment
                  , parse_number
+                 , parse_float
                  , parse_bool
+                 , parse_string
                  , keyword
                  , punct
                  , maybe
                  , fatal
+                 , fail
+                 , start_position
+                 , is_record_start
+                 , parse_record_fields
+                 , joined_punct
                  };
 use super::type_parser::parse_type;
-use super::pattern_parser::{parse_path_pattern, parse_array_pattern, parse_standard_pattern};
+use super::pattern_parser::{parse_path_pattern, parse_array_pattern, parse_standard_pattern, parse_standard_pattern_without_or, parse_named_pattern};
 use crate::ast::{ Expr
+                , ExprKind
+                , Span
                 , Case
                 , Type
                 , FunParam
+                , Binding
                 };
 
-fn parse_let(input : &mut Input) -> Result<Expr, ParseError> {
-    fn colon_and_type(input : &mut Input) -> Result<Type, ParseError> {
-        punct(input, ":")?;
-        parse_type(input)
+fn colon_and_type(input : &mut Input) -> Result<Type, ParseError> {
+    punct(input, ":")?;
+    parse_type(input)
+}
+
+// 'let a = 1, b = a in b' is the same as 'let a = 1 in let b = a in b'.
+fn parse_let(input : &mut Input) -> Result<ExprKind, ParseError> {
+    // 'rec' is only special when a binding name follows it, so it can still be used as a variable
+    fn is_rec(input : &Input) -> bool {
+        let mut input = input.clone();
+        matches!( input.next(), Some(Token { kind: TokenKind::Symbol(rec), .. }) if rec == "rec" )
+        && matches!( input.peek(), Some(Token { kind: TokenKind::Symbol(_), .. }) )
     }
 
     keyword(input, "let")?;
-    
-    let name = fatal(parse_symbol(input), "let must have name")?;
 
-    let t = maybe(colon_and_type(input))?;
+    if is_rec(input) {
+        input.next();
+        let bindings = fatal(parse_bindings(input), input, "let rec must have bindings")?;
+        fatal(keyword(input, "in"), input, "let must have 'in'")?;
+        let expr = Box::new(fatal(parse_expr(input), input, "let must have expr")?);
+        return Ok(ExprKind::LetRec { bindings, expr });
+    }
+
+    let mut bindings = vec![];
+    loop {
+        let start = start_position(input)?;
+
+        let pattern = fatal(parse_standard_pattern(parse_expr, input), input, "let must have a pattern")?;
+
+        let t = maybe(colon_and_type(input))?;
+
+        fatal(punct(input, "="), input, "let must have '='")?;
+
+        let value = Box::new(fatal(parse_expr(input), input, "let must have value")?);
+
+        bindings.push((start, pattern, t, value));
+
+        match punct(input, ",") {
+            Ok(_) => { },
+            Err(ParseError::Error) => break,
+            Err(e @ ParseError::Fatal(_)) => return Err(e),
+        }
+    }
+
+    fatal(keyword(input, "in"), input, "let must have 'in'")?;
+
+    let mut expr = Box::new(fatal(parse_expr(input), input, "let must have expr")?);
+
+    let (_, pattern, t, value) = bindings.remove(0);
+    for (start, pattern, t, value) in bindings.into_iter().rev() {
+        let span = Span { file: expr.span.file, start, end: expr.span.end };
+        expr = Box::new(Expr { kind: ExprKind::Let { pattern, t, value, expr }, span });
+    }
+
+    Ok(ExprKind::Let{pattern, t, value, expr})
+}
+
+// The comma separated bindings of 'let rec' and 'where'.  Only lambdas can be bound so that
+// every binding can refer to the others before they have all been evaluated.
+pub fn parse_bindings(input : &mut Input) -> Result<Vec<Binding>, ParseError> {
+    let mut bindings = vec![];
+    loop {
+        let name = fatal(parse_symbol(input), input, "binding must have a name")?;
+
+        let t = maybe(colon_and_type(input))?;
 
-    fatal(punct(input, "="), "let must have '='")?;
+        fatal(punct(input, "="), input, "binding must have '='")?;
 
-    let value = Box::new(fatal(parse_expr(input), "let must have value")?);
+        let value = fatal(parse_expr(input), input, "binding must have value")?;
 
-    fatal(keyword(input, "in"), "let must have 'in'")?;
+        if !matches!( value.kind, ExprKind::Lambda { .. } ) {
+            return Err(ParseError::Fatal(vec![("let rec and where can only bind lambdas".to_string(), value.span)]));
+        }
+
+        bindings.push(Binding { name, t, value });
+
+        match punct(input, ",") {
+            Ok(_) => { },
+            Err(ParseError::Error) => return Ok(bindings),
+            Err(e @ ParseError::Fatal(_)) => return Err(e),
+        }
+    }
+}
 
-    let expr = Box::new(fatal(parse_expr(input), "let must have expr")?);
+fn parse_bool_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+    into(input, parse_bool, |b| ExprKind::Bool(b))
+}
 
-    Ok(Expr::Let{name, t, value, expr})
+fn parse_number_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+    into(input, parse_number, |n| ExprKind::Number(n))
 }
 
-fn parse_bool_expr(input : &mut Input) -> Result<Expr, ParseError> {
-    into(input, parse_bool, |b| Expr::Bool(b))
+fn parse_float_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+    into(input, parse_float, ExprKind::Float)
 }
 
-fn parse_number_expr(input : &mut Input) -> Result<Expr, ParseError> {
-    into(input, parse_number, |n| Expr::Number(n))
+fn parse_string_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+    into(input, parse_string, ExprKind::String)
 }
 
-fn parse_variable_expr(input : &mut Input) -> Result<Expr, ParseError> {
+fn parse_variable_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
     let rp = input.clone();
 
-    let sym = parse_symbol(input)?;
+    let sym = parse_qualified_symbol(input)?;
 
-    let first = sym.chars().nth(0)
+    let first = last_segment(&sym).chars().nth(0)
         .expect("parse_expr::parse_variable_expr parse_symbol somehow returned zero length string");
 
     if first.is_lowercase() {
-        Ok(Expr::Variable(sym))
+        Ok(ExprKind::Variable(sym))
     }
     else {
         input.restore(rp);
@@ -68,11 +151,11 @@ fn parse_variable_expr(input : &mut Input) -> Result<Expr, ParseError> {
     }
 }
 
-fn parse_constructor_expr(input : &mut Input) -> Result<Expr, ParseError> {
+fn parse_constructor_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
     fn parse_name(input : &mut Input) -> Result<String, ParseError> {
         let rp = input.clone();
-        let sym = parse_symbol(input)?;
-        let first = sym.chars().nth(0)
+        let sym = parse_qualified_symbol(input)?;
+        let first = last_segment(&sym).chars().nth(0)
             .expect("parse_expr::parse_constructor_expr parse_symbol somehow returned zero length string");
         if first.is_uppercase() {
             Ok(sym)
@@ -85,22 +168,36 @@ fn parse_constructor_expr(input : &mut Input) -> Result<Expr, ParseError> {
 
     let name = parse_name(input)?;
 
+    if is_record_start(input) {
+        let fields = parse_record_fields(parse_expr, input)?
+            .into_iter()
+            .map(|(field, span, expr)| match expr {
+                Some(expr) => (field, expr),
+                None => (field.clone(), Expr { kind: ExprKind::Variable(field), span }),
+            })
+            .collect();
+        return Ok(ExprKind::Record { name, fields });
+    }
+
     match maybe(parse_params(parse_expr, input))? {
-        Some(params) => Ok(Expr::Cons { name, params }),
-        None => Ok(Expr::Cons {name, params: vec![]}),
+        Some(params) => Ok(ExprKind::Cons { name, params }),
+        None => Ok(ExprKind::Cons {name, params: vec![]}),
     }
 }
 
-fn parse_lambda(input : &mut Input) -> Result<Expr, ParseError> {
+// '||' where an operand is expected starts a lambda without params and anywhere else it is
+// boolean or, so 'f(|| x)' passes a lambda and '|| a || b' is a lambda that returns 'a || b'.
+fn parse_lambda(input : &mut Input) -> Result<ExprKind, ParseError> {
     fn params(input : &mut Input) -> Result<Vec<FunParam>, ParseError> {
         fn parse_lambda_param(input : &mut Input) -> Result<FunParam, ParseError> {
             fn parse_colon_type(input : &mut Input) -> Result<Type, ParseError> {
                 punct(input, ":")?;
-                fatal(parse_type(input), "lambda parameter must have type after ':'")
+                fatal(parse_type(input), input, "lambda parameter must have type after ':'")
             }
-            let name = parse_symbol(input)?;
+            // '|' closes the parameters, so each pattern can't have alternatives without parens
+            let pattern = parse_standard_pattern_without_or(parse_expr, input)?;
             let t = maybe(parse_colon_type(input))?;
-            Ok(FunParam { name, t })
+            Ok(FunParam { pattern, t })
         }
 
         parse_series( parse_lambda_param, "|", "|", input) 
@@ -108,81 +205,249 @@ fn parse_lambda(input : &mut Input) -> Result<Expr, ParseError> {
 
     fn return_type(input : &mut Input) -> Result<Type, ParseError> {
         punct(input, "->")?;
-        fatal(parse_type(input), "return_type must have a type after '->'")
+        fatal(parse_type(input), input, "return_type must have a type after '->'")
     }
 
     let params = params(input)?;
     let return_type = maybe(return_type(input))?;
-    let expr = Box::new(fatal(parse_expr(input), "lambda must have expr")?);
+    let expr = Box::new(fatal(parse_expr(input), input, "lambda must have expr")?);
     
-    Ok(Expr::Lambda { params, return_type, expr })
+    Ok(ExprKind::Lambda { params, return_type, expr })
 }
 
-fn parse_match(input : &mut Input) -> Result<Expr, ParseError> {
+fn parse_match(input : &mut Input) -> Result<ExprKind, ParseError> {
     fn parse_case(input : &mut Input) -> Result<Case, ParseError> {
         let pattern = parse_standard_pattern(parse_expr, input)?;
-        fatal(punct(input, "=>"), "pattern case must have an => after a pattern")?;
-        let expr = fatal(parse_expr(input), "pattern case must have an expr")?;
+        fatal(punct(input, "=>"), input, "pattern case must have an => after a pattern")?;
+        let expr = fatal(parse_expr(input), input, "pattern case must have an expr")?;
         Ok(Case { pattern, expr })
     }
 
     keyword(input, "match")?;
-    let expr = Box::new(fatal(parse_expr(input), "match statements must have an expression")?);
-    let cases = fatal(parse_series(parse_case, "{", "}", input), "match statements must have case body")?;
-    Ok(Expr::Match{ expr, cases })
+    let mut scrutinee = input.scrutinee(true);
+    let expr = Box::new(fatal(parse_expr(&mut scrutinee), &scrutinee, "match statements must have an expression")?);
+    input.restore(scrutinee);
+    let cases = fatal(parse_series(parse_case, "{", "}", input), input, "match statements must have case body")?;
+    Ok(ExprKind::Match{ expr, cases })
+}
+
+// How a binary operator desugars.  '&&' and '||' become ifs so that the right hand side is only
+// evaluated when it is needed.
+#[derive(Clone, Copy)]
+enum Desugar {
+    Call(&'static str),    // builtin(a, b)
+    NotCall(&'static str), // not(builtin(a, b))
+    And,                   // if a then b else false
+    Or,                    // if a then true else b
+}
+
+// (operator, precedence, what the operator desugars to)
+const BINARY_OPERATORS : [(&str, usize, Desugar); 14] = 
+    [ ("||", 1, Desugar::Or)
+    , ("&&", 2, Desugar::And)
+    , ("^", 3, Desugar::Call("xor"))
+    , ("==", 4, Desugar::Call("eq"))
+    , ("!=", 4, Desugar::NotCall("eq"))
+    , ("<=", 5, Desugar::Call("lte"))
+    , (">=", 5, Desugar::Call("gte"))
+    , ("<", 5, Desugar::Call("lt"))
+    , (">", 5, Desugar::Call("gt"))
+    , ("+", 6, Desugar::Call("add"))
+    , ("-", 6, Desugar::Call("sub"))
+    , ("*", 7, Desugar::Call("mul"))
+    , ("/", 7, Desugar::Call("div"))
+    , ("%", 7, Desugar::Call("rem"))
+    ];
+
+fn builtin_call(builtin : &str, builtin_span : Span, params : Vec<Expr>, span : Span) -> Expr {
+    let fun_expr = Box::new(Expr { kind: ExprKind::Variable(builtin.to_string()), span: builtin_span });
+    Expr { kind: ExprKind::FunCall { fun_expr, params }, span }
+}
+
+fn parse_if(input : &mut Input) -> Result<ExprKind, ParseError> {
+    keyword(input, "if")?;
+    let condition = Box::new(fatal(parse_expr(input), input, "if must have a condition")?);
+    fatal(keyword(input, "then"), input, "if must have 'then'")?;
+    let then_expr = Box::new(fatal(parse_expr(input), input, "if must have an expr after 'then'")?);
+    fatal(keyword(input, "else"), input, "if must have 'else'")?;
+    let else_expr = Box::new(fatal(parse_expr(input), input, "if must have an expr after 'else'")?);
+    Ok(ExprKind::If { condition, then_expr, else_expr })
 }
 
 pub fn parse_expr(input : &mut Input) -> Result<Expr, ParseError> {
+    parse_binary_expr(input, 0)
+}
 
-    fn parse_array_expr(input : &mut Input) -> Result<Expr, ParseError> {
-        into(input, |i| parse_array(parse_expr, i), |es| Expr::Array(es))
+fn parse_binary_expr(input : &mut Input, min_precedence : usize) -> Result<Expr, ParseError> {
+    fn parse_operator(input : &mut Input) -> Result<(&'static str, usize, Desugar), ParseError> {
+        for (op, precedence, desugar) in BINARY_OPERATORS {
+            let rp = input.clone();
+            match punct(input, op) {
+                // '-' should not eat the '->' of a return type
+                Ok(_) if op == "-" && joined_punct(input, ">") => input.restore(rp),
+                Ok(_) => return Ok((op, precedence, desugar)),
+                Err(ParseError::Error) => { },
+                Err(e @ ParseError::Fatal(_)) => return Err(e),
+            }
+        }
+        Err(ParseError::Error)
     }
 
-    fn parse_path_pattern_expr(input : &mut Input) -> Result<Expr, ParseError> {
-        into( input
-            , |j| parse_series( |i| parse_path_pattern(parse_expr, i)
-                              , "{|"
-                              , "|}"
-                              , j
-                              )
-            , |patterns| Expr::PathPattern(patterns))
+    let start = start_position(input)?;
+
+    let mut left = parse_unary_expr(input)?;
+
+    loop {
+        let rp = input.clone();
+        let op_start = start_position(input)?;
+
+        let (op, precedence, desugar) = match parse_operator(input) {
+            Ok(op) => op,
+            Err(ParseError::Error) => return Ok(left),
+            Err(e @ ParseError::Fatal(_)) => return Err(e),
+        };
+
+        if precedence < min_precedence {
+            input.restore(rp);
+            return Ok(left);
+        }
+
+        let op_span = input.span_from(op_start);
+
+        let right = fatal( parse_binary_expr(input, precedence + 1)
+                         , input
+                         , &format!("operator '{op}' must have an expr on its right hand side"))?;
+
+        let span = input.span_from(start);
+
+        left = match desugar {
+            Desugar::And => {
+                let false_expr = Box::new(Expr { kind: ExprKind::Bool(false), span: op_span });
+                Expr { kind: ExprKind::If { condition: Box::new(left), then_expr: Box::new(right), else_expr: false_expr }, span }
+            },
+            Desugar::Or => {
+                let true_expr = Box::new(Expr { kind: ExprKind::Bool(true), span: op_span });
+                Expr { kind: ExprKind::If { condition: Box::new(left), then_expr: true_expr, else_expr: Box::new(right) }, span }
+            },
+            Desugar::NotCall(builtin) => builtin_call("not", op_span, vec![builtin_call(builtin, op_span, vec![left, right], span)], span),
+            Desugar::Call(builtin) => builtin_call(builtin, op_span, vec![left, right], span),
+        };
+    }
+}
+
+fn parse_unary_expr(input : &mut Input) -> Result<Expr, ParseError> {
+    let start = start_position(input)?;
+
+    let rp = input.clone();
+    match punct(input, "!") {
+        Ok(_) => {
+            let op_span = input.span_from(start);
+            let expr = fatal(parse_unary_expr(input), input, "operator '!' must have an expr")?;
+            return Ok(builtin_call("not", op_span, vec![expr], input.span_from(start)));
+        },
+        Err(ParseError::Error) => { },
+        Err(e @ ParseError::Fatal(_)) => return Err(e),
+    }
+
+    match punct(input, "-") {
+        // Negative number literals are handled by parse_number
+        Ok(_) if input.joined() && matches!(input.peek(), Some(Token { kind: TokenKind::Number(_) | TokenKind::Float(_), .. })) => input.restore(rp),
+        Ok(_) => {
+            let op_span = input.span_from(start);
+            let expr = fatal(parse_unary_expr(input), input, "operator '-' must have an expr")?;
+            let zero = Expr { kind: ExprKind::Number(0), span: op_span };
+            return Ok(builtin_call("sub", op_span, vec![zero, expr], input.span_from(start)));
+        },
+        Err(ParseError::Error) => { },
+        Err(e @ ParseError::Fatal(_)) => return Err(e),
+    }
+
+    parse_postfix_expr(input)
+}
+
+pub fn parse_path_pattern_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+    into( input
+        , |j| parse_series( |i| parse_path_pattern(parse_expr, i)
+                          , "{|"
+                          , "|}"
+                          , j
+                          )
+        , |patterns| ExprKind::PathPattern(patterns))
+}
+
+pub fn parse_array_pattern_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+    into( input
+        , |j| parse_series( |i| parse_array_pattern(parse_expr, i)
+                          , "[|"
+                          , "|]"
+                          , j
+                          )
+        , |patterns| ExprKind::ArrayPattern(patterns))
+}
+
+fn parse_postfix_expr(input : &mut Input) -> Result<Expr, ParseError> {
+
+    // '(a)' is just 'a' but '(a, b, ...)' is a tuple
+    fn parse_group_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+        punct(input, "(")?;
+        let mut inner = input.scrutinee(false);
+        let mut exprs = vec![];
+        loop {
+            exprs.push(fatal(parse_expr(&mut inner), &inner, "parentheses must have an expr")?);
+            match punct(&mut inner, ",") {
+                Ok(_) => continue,
+                Err(ParseError::Error) => break,
+                Err(e @ ParseError::Fatal(_)) => return Err(e),
+            }
+        }
+        input.restore(inner);
+        fatal(punct(input, ")"), input, "parentheses must have an ending ')'")?;
+        match exprs.len() {
+            1 => Ok(exprs.pop().unwrap().kind),
+            _ => Ok(ExprKind::Tuple(exprs)),
+        }
     }
 
-    fn parse_array_pattern_expr(input : &mut Input) -> Result<Expr, ParseError> {
-        into( input
-            , |j| parse_series( |i| parse_array_pattern(parse_expr, i)
-                              , "[|"
-                              , "|]"
-                              , j
-                              )
-            , |patterns| Expr::ArrayPattern(patterns))
+    fn parse_array_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+        into(input, |i| parse_array(parse_expr, i), |es| ExprKind::Array(es))
+    }
+
+    fn parse_named_pattern_expr(input : &mut Input) -> Result<ExprKind, ParseError> {
+        into(input, parse_named_pattern, ExprKind::NamedPattern)
     }
 
     let ps = [ parse_bool_expr
              , parse_number_expr
+             , parse_float_expr
+             , parse_string_expr
+             , parse_group_expr
              , parse_let
              , parse_constructor_expr
              , parse_lambda
+             , parse_array_pattern_expr // Needs to come before parse_array_expr so that '[|' isn't read as '[' 
              , parse_array_expr
              , parse_path_pattern_expr 
-             , parse_array_pattern_expr
+             , parse_named_pattern_expr
              , parse_match
+             , parse_if
 
              , parse_variable_expr // This should probably be last to avoid eating up keywords, etc
              ];
 
+    let start = start_position(input)?;
+
     let mut expr = None;
     
     for p in ps {
         match p(input) {
             Ok(e) => { expr = Some(e); break; },
-            e @ Err(ParseError::Fatal(_)) => return e,
+            Err(e @ ParseError::Fatal(_)) => return Err(e),
             _ => { },
         }
     }
 
     let mut ret = match expr {
-        Some(expr) => expr, 
+        Some(kind) => Expr { kind, span: input.span_from(start) }, 
         None => return Err(ParseError::Error),
     };
 
@@ -190,8 +455,8 @@ pub fn parse_expr(input : &mut Input) -> Result<Expr, ParseError> {
         match parse_params(parse_expr, input) {
             Ok(params) => 
             { 
-                let temp = Expr::FunCall { fun_expr : Box::new(ret), params };
-                ret = temp;
+                let temp = ExprKind::FunCall { fun_expr : Box::new(ret), params };
+                ret = Expr { kind: temp, span: input.span_from(start) };
                 continue;
             },
             Err(ParseError::Error) => { },
@@ -200,14 +465,25 @@ pub fn parse_expr(input : &mut Input) -> Result<Expr, ParseError> {
 
         match punct(input, ".") {
             Ok(_) => {
-                let name = Box::new(Expr::Variable(fatal(parse_symbol(input), "there must exist a symbol after .")?));
+                let name_start = start_position(input)?;
+                let name = fatal(parse_qualified_symbol(input), input, "there must exist a symbol after .")?;
+
+                // Without a parameter list this is a field access rather than a dot function call
+                let mut params = match maybe(parse_params(parse_expr, input))? {
+                    Some(params) => params,
+                    None if name.contains("::") => return fail(input, "qualified name after . must be called"),
+                    None => {
+                        ret = Expr { kind: ExprKind::FieldAccess { expr: Box::new(ret), field: name }, span: input.span_from(start) };
+                        continue;
+                    },
+                };
 
-                let mut params = fatal(parse_params(parse_expr, input), "dot function must have parameter list")?;
+                let name = Box::new(Expr { kind: ExprKind::Variable(name), span: input.span_from(name_start) });
 
                 params.insert(0, ret);
 
-                let temp = Expr::FunCall { fun_expr : name, params };
-                ret = temp;
+                let temp = ExprKind::FunCall { fun_expr : name, params };
+                ret = Expr { kind: temp, span: input.span_from(start) };
             },
             Err(ParseError::Error) => return Ok(ret),
             Err(e @ ParseError::Fatal(_)) => return Err(e),
@@ -218,21 +494,95 @@ pub fn parse_expr(input : &mut Input) -> Result<Expr, ParseError> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ast::{ArrayPatternKind, StandardPattern, StandardPatternKind};
+
+    fn show(expr : &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Number(n) => n.to_string(),
+            ExprKind::Float(f) => format!("{:?}", f),
+            ExprKind::Bool(b) => b.to_string(),
+            ExprKind::Variable(v) => v.to_string(),
+            ExprKind::FunCall { fun_expr, params } => 
+                format!("{}({})", show(fun_expr), params.iter().map(show).collect::<Vec<_>>().join(", ")),
+            ExprKind::If { condition, then_expr, else_expr } => 
+                format!("if {} then {} else {}", show(condition), show(then_expr), show(else_expr)),
+            ExprKind::FieldAccess { expr, field } => format!("{}.{field}", show(expr)),
+            ExprKind::Tuple(items) => format!("({})", items.iter().map(show).collect::<Vec<_>>().join(", ")),
+            ExprKind::Lambda { params, expr, .. } if params.is_empty() => format!("|| {}", show(expr)),
+            ExprKind::Record { name, fields } => 
+                format!("{name} {{ {} }}", fields.iter().map(|(f, e)| format!("{f} : {}", show(e))).collect::<Vec<_>>().join(", ")),
+            e => panic!("show does not support {:?}", e),
+        }
+    }
+
+    fn parse_and_show(s : &str) -> Result<String, ParseError> {
+        let mut input = Input::new(s);
+        let result = parse_expr(&mut input)?;
+        Ok(show(&result))
+    }
 
     #[test]
     fn let_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("let x = 5 in x");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Let { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Let { .. } ) );
         // TODO add more details 
         Ok(())
     }
 
+    #[test]
+    fn let_should_parse_destructuring_patterns() -> Result<(), ParseError> {
+        let mut input = Input::new("let Cons(h, t) = xs in let [a, b | rest] : [Number] = arr in h");
+        let result = parse_expr(&mut input)?;
+        let inner = match result.kind {
+            ExprKind::Let { pattern: StandardPattern { kind: StandardPatternKind::Cons { .. }, .. }, expr, .. } => expr,
+            x => panic!("expected let with cons pattern, found {:?}", x),
+        };
+        assert!( matches!( inner.kind, ExprKind::Let { pattern: StandardPattern { kind: StandardPatternKind::StandardArray(_), .. }, t: Some(_), .. } ) );
+        Ok(())
+    }
+
+    #[test]
+    fn let_should_nest_multiple_bindings() -> Result<(), ParseError> {
+        let mut input = Input::new("let a = 1, b = a in b");
+        let result = parse_expr(&mut input)?;
+        let inner = match result.kind {
+            ExprKind::Let { pattern: StandardPattern { kind: StandardPatternKind::Variable(a), .. }, expr, .. } if a == "a" => expr,
+            x => panic!("expected let a, found {:?}", x),
+        };
+        assert!( matches!( inner.kind, ExprKind::Let { pattern: StandardPattern { kind: StandardPatternKind::Variable(b), .. }, .. } if b == "b" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn let_rec_should_parse_bindings() -> Result<(), ParseError> {
+        let mut input = Input::new("let rec f = |x| g(x), g : fun(Number) -> Number = |x| f(x) in f(1)");
+        let result = parse_expr(&mut input)?;
+        assert!( matches!( &result.kind, ExprKind::LetRec { bindings, .. } 
+            if bindings.len() == 2 && bindings[0].name == "f" && bindings[1].name == "g" && bindings[1].t.is_some() ) );
+        Ok(())
+    }
+
+    #[test]
+    fn let_should_allow_rec_as_variable() -> Result<(), ParseError> {
+        let mut input = Input::new("let rec = 1 in rec");
+        let result = parse_expr(&mut input)?;
+        assert!( matches!( result.kind, ExprKind::Let { pattern: StandardPattern { kind: StandardPatternKind::Variable(r), .. }, .. } if r == "rec" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn let_rec_should_only_bind_lambdas() {
+        let mut input = Input::new("let rec x = 1 in x");
+        let result = parse_expr(&mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(fs)) if fs[0].0 == "let rec and where can only bind lambdas" ) );
+    }
+
     #[test]
     fn cons_should_parse_non_param_cons() -> Result<(), ParseError> {
         let mut input = Input::new("SomeCons");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Cons { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Cons { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -241,7 +591,7 @@ mod test {
     fn cons_should_parse_params_cons() -> Result<(), ParseError> {
         let mut input = Input::new("SomeCons(1, 2, 3)");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Cons { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Cons { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -250,7 +600,7 @@ mod test {
     fn lambda_should_parse_no_param_lambda() -> Result<(), ParseError> {
         let mut input = Input::new("|| 5");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Lambda { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Lambda { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -259,7 +609,7 @@ mod test {
     fn lambda_should_parse_no_type_param_lambda() -> Result<(), ParseError> {
         let mut input = Input::new("|x| x");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Lambda { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Lambda { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -268,7 +618,7 @@ mod test {
     fn lambda_should_parse_no_type_params_lambda() -> Result<(), ParseError> {
         let mut input = Input::new("|x, y, z| Cons(x, y, z)");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Lambda { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Lambda { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -277,7 +627,7 @@ mod test {
     fn lambda_should_parse_type_param_lambda() -> Result<(), ParseError> {
         let mut input = Input::new("|x : Type| x");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Lambda { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Lambda { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -286,7 +636,7 @@ mod test {
     fn lambda_should_parse_type_params_lambda() -> Result<(), ParseError> {
         let mut input = Input::new("|x : Type, y : Type, z : Number| x");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Lambda { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Lambda { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -295,7 +645,7 @@ mod test {
     fn lambda_should_parse_type_params_lambda_with_return_type() -> Result<(), ParseError> {
         let mut input = Input::new("|x : Type, y : Type, z : Number| -> Type x");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Lambda { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Lambda { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -304,7 +654,7 @@ mod test {
     fn lambda_should_parse_lambda_with_return_type() -> Result<(), ParseError> {
         let mut input = Input::new("|| -> Number 5");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Lambda { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Lambda { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -313,7 +663,7 @@ mod test {
     fn lambda_should_parse_lambda_with_fun_return_type() -> Result<(), ParseError> {
         let mut input = Input::new("|| -> fun(Number) -> Number |x| x");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Lambda { .. } ) );
+        assert!( matches!( result.kind, ExprKind::Lambda { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -322,7 +672,7 @@ mod test {
     fn array_should_parse_empty_array() -> Result<(), ParseError> {
         let mut input = Input::new("[]");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Array(_) ) );
+        assert!( matches!( result.kind, ExprKind::Array(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -331,7 +681,7 @@ mod test {
     fn array_should_parse_array_with_one_item() -> Result<(), ParseError> {
         let mut input = Input::new("[4]");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Array(_) ) );
+        assert!( matches!( result.kind, ExprKind::Array(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -340,7 +690,7 @@ mod test {
     fn array_should_parse_array() -> Result<(), ParseError> {
         let mut input = Input::new("[4, 6, 7]");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Array(_) ) );
+        assert!( matches!( result.kind, ExprKind::Array(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -349,7 +699,7 @@ mod test {
     fn should_parse_match() -> Result<(), ParseError> {
         let mut input = Input::new("match 7 { x => x }");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Match{ .. } ) );
+        assert!( matches!( result.kind, ExprKind::Match{ .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -361,8 +711,251 @@ mod test {
             _ => 0
         }");
         let result = parse_expr(&mut input)?;
-        assert!( matches!( result, Expr::Match{ .. } ) );
+        assert!( matches!( result.kind, ExprKind::Match{ .. } ) );
         // TODO add more details 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_record_expr_spans() -> Result<(), ParseError> {
+        let mut input = Input::new("  x.add(
+            1)");
+        let result = parse_expr(&mut input)?;
+        assert_eq!( result.span.start.line, 1 );
+        assert_eq!( result.span.start.column, 3 );
+        assert_eq!( result.span.end.line, 2 );
+        assert_eq!( result.span.end.column, 15 );
+
+        let params = match result.kind {
+            ExprKind::FunCall { params, .. } => params,
+            _ => panic!("expected fun call"),
+        };
+        assert_eq!( params[1].span.start.line, 2 );
+        assert_eq!( params[1].span.start.column, 13 );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_string() -> Result<(), ParseError> {
+        let mut input = Input::new(r#""some \"name\"".concat("other")"#);
+        let result = parse_expr(&mut input)?;
+        let params = match result.kind {
+            ExprKind::FunCall { params, .. } => params,
+            _ => panic!("expected fun call"),
+        };
+        assert!( matches!( &params[0].kind, ExprKind::String(s) if s == "some \"name\"" ) );
+        assert!( matches!( &params[1].kind, ExprKind::String(s) if s == "other" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_operators_with_precedence() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("a + b * c")?, "add(a, mul(b, c))" );
+        assert_eq!( parse_and_show("a * b + c")?, "add(mul(a, b), c)" );
+        assert_eq!( parse_and_show("a - b - c")?, "sub(sub(a, b), c)" );
+        assert_eq!( parse_and_show("a < b || c >= d && e")?, "if lt(a, b) then true else if gte(c, d) then e else false" );
+        assert_eq!( parse_and_show("a == b ^ c <= d")?, "xor(eq(a, b), lte(c, d))" );
+        assert_eq!( parse_and_show("a % b / c > 1")?, "gt(div(rem(a, b), c), 1)" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_parentheses() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("(a + b) * c")?, "mul(add(a, b), c)" );
+        assert_eq!( parse_and_show("((a))")?, "a" );
+        assert_eq!( parse_and_show("(f)(a).g(b + 1)")?, "g(f(a), add(b, 1))" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_tuples() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("(a, (b), c + 1)")?, "(a, b, add(c, 1))" );
+        assert_eq!( parse_and_show("((a, b), c).x")?, "((a, b), c).x" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_not_equal() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("a != b + 1")?, "not(eq(a, add(b, 1)))" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_unary_operators() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("!a && b")?, "if not(a) then b else false" );
+        assert_eq!( parse_and_show("-a.abs() * 2")?, "mul(sub(0, abs(a)), 2)" );
+        assert_eq!( parse_and_show("-5 - -5")?, "sub(-5, -5)" );
+        assert_eq!( parse_and_show("a-1")?, "sub(a, 1)" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_float_literals() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("1.5 * -0.25")?, "mul(1.5, -0.25)" );
+        assert_eq!( parse_and_show("-x.y - 2.0")?, "sub(sub(0, x.y), 2.0)" );
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_range_literal_should_be_fatal() {
+        let mut input = Input::new("1 + 99999999999999999999");
+        let result = parse_expr(&mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(_)) ) );
+    }
+
+    #[test]
+    fn should_not_parse_arrow_as_minus() -> Result<(), ParseError> {
+        let mut input = Input::new("a -> b");
+        let result = parse_expr(&mut input)?;
+        assert_eq!( show(&result), "a" );
+        Ok(())
+    }
+
+    #[test]
+    fn operator_should_require_right_hand_side() {
+        let mut input = Input::new("a + ");
+        let result = parse_expr(&mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(_)) ) );
+    }
+
+    #[test]
+    fn should_parse_operators_in_lambda_body() -> Result<(), ParseError> {
+        let mut input = Input::new("|a, n| a + n * 2");
+        let result = parse_expr(&mut input)?;
+        let expr = match result.kind {
+            ExprKind::Lambda { expr, .. } => expr,
+            _ => panic!("expected lambda"),
+        };
+        assert_eq!( show(&expr), "add(a, mul(n, 2))" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_patterns_in_lambda_params() -> Result<(), ParseError> {
+        let mut input = Input::new("|Cons(h, _), (a, b) : (Number, Number), x| h");
+        let result = parse_expr(&mut input)?;
+        let params = match result.kind {
+            ExprKind::Lambda { params, .. } => params,
+            _ => panic!("expected lambda"),
+        };
+        assert_eq!( params.len(), 3 );
+        assert!( matches!( &params[0].pattern.kind, StandardPatternKind::Cons { name, .. } if name == "Cons" ) );
+        assert!( matches!( (&params[1].pattern.kind, &params[1].t), (StandardPatternKind::Tuple(_), Some(_)) ) );
+        assert!( matches!( &params[2].pattern.kind, StandardPatternKind::Variable(x) if x == "x" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_if() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("if a < 1 then b else c + 1")?, "if lt(a, 1) then b else add(c, 1)" );
+        assert_eq!( parse_and_show("if a then if b then 1 else 2 else 3")?, "if a then if b then 1 else 2 else 3" );
+        Ok(())
+    }
+
+    #[test]
+    fn if_should_require_else() {
+        let mut input = Input::new("if a then b");
+        let result = parse_expr(&mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(_)) ) );
+    }
+
+    #[test]
+    fn should_desugar_boolean_operators_to_if() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("a && b")?, "if a then b else false" );
+        assert_eq!( parse_and_show("a || b")?, "if a then true else b" );
+        assert_eq!( parse_and_show("a || b || c")?, "if if a then true else b then true else c" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_tell_empty_lambda_from_boolean_or() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("f(|| x)")?, "f(|| x)" );
+        assert_eq!( parse_and_show("f(|| x, y)")?, "f(|| x, y)" );
+        assert_eq!( parse_and_show("a || b")?, "if a then true else b" );
+        assert_eq!( parse_and_show("|| a || b")?, "|| if a then true else b" );
+        assert_eq!( parse_and_show("a || || b")?, "if a then true else || b" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_record_construction() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("Person { name : n, age : 1 + 2 }")?, "Person { name : n, age : add(1, 2) }" );
+        assert_eq!( parse_and_show("Person { name, age : 5 }")?, "Person { name : name, age : 5 }" );
+        assert_eq!( parse_and_show("Empty {}")?, "Empty {  }" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_parse_match_body_as_record() -> Result<(), ParseError> {
+        let mut input = Input::new("match Nil { x => x }");
+        let result = parse_expr(&mut input)?;
+        assert!( matches!( result.kind, ExprKind::Match { .. } ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_constructor_as_match_scrutinee() -> Result<(), ParseError> {
+        for (s, cases) in [ ("match None {}", 0), ("match Foo { x => x }", 1), ("match Foo { Foo { a } => a, _ => 1 }", 2) ] {
+            let mut input = Input::new(s);
+            match parse_expr(&mut input)?.kind {
+                ExprKind::Match { expr, cases: cs } => {
+                    assert!( matches!( expr.kind, ExprKind::Cons { .. } ) );
+                    assert_eq!( cs.len(), cases );
+                },
+                e => panic!("expected match, found {:?}", e),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_record_in_match_scrutinee_only_when_it_starts_with_a_field() -> Result<(), ParseError> {
+        for s in [ "match Person { name : n } { x => x }", "match (Person { name }) { x => x }", "match f(Person { name }) { x => x }" ] {
+            let mut input = Input::new(s);
+            assert!( matches!( parse_expr(&mut input)?.kind, ExprKind::Match { cases, .. } if cases.len() == 1 ), "{s}" );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_field_access() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("x.name")?, "x.name" );
+        assert_eq!( parse_and_show("x.a.b.len()")?, "len(x.a.b)" );
+        assert_eq!( parse_and_show("x.friend.name.eq(y) && x.age > 5")?, "if eq(x.friend.name, y) then gt(x.age, 5) else false" );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_qualified_names() -> Result<(), ParseError> {
+        assert_eq!( parse_and_show("list::sum(xs)")?, "list::sum(xs)" );
+        assert_eq!( parse_and_show("xs.list::sum()")?, "list::sum(xs)" );
+
+        let mut input = Input::new("list::Cons(1, list::Nil)");
+        let result = parse_expr(&mut input)?;
+        assert!( matches!( result.kind, ExprKind::Cons { name, params } if name == "list::Cons" 
+            && matches!( &params[1].kind, ExprKind::Cons { name, .. } if name == "list::Nil" ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_array_pattern_expr() -> Result<(), ParseError> {
+        let mut input = Input::new("[| Cons(a, _), $rest |]");
+        let result = parse_expr(&mut input)?;
+        let patterns = match result.kind {
+            ExprKind::ArrayPattern(patterns) => patterns,
+            _ => panic!("expected array pattern"),
+        };
+        assert_eq!( patterns.len(), 2 );
+        assert!( matches!( &patterns[1].kind, ArrayPatternKind::Named(n) if n == "rest" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_named_pattern_expr() -> Result<(), ParseError> {
+        let mut input = Input::new("$queries::find_leaves.path(tree)");
+        let result = parse_expr(&mut input)?;
+        assert!( matches!( result.kind, ExprKind::FunCall { params, .. } 
+            if matches!( &params[0].kind, ExprKind::NamedPattern(n) if n == "queries::find_leaves" ) ) );
+        Ok(())
+    }
+}
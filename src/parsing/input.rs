@@ -1,47 +1,126 @@
+use std::rc::Rc;
 
-use std::str::CharIndices;
-use std::iter::Peekable; 
+use crate::ast::{Position, Span, Comment};
+use super::lexer::{self, Token};
 
+// Cloning an Input only copies a cursor into the shared token stream, so restoring
+// after a failed alternative is cheap.
 #[derive(Clone)]
-pub struct Input<'a> {
-    cs : Peekable<CharIndices<'a>>
+pub struct Input {
+    tokens : Rc<[Token]>,
+    docs : Rc<[Comment]>,
+    index : usize,
+    end : Position,
+    scrutinee : bool, // Parsing the expr right before the cases of a match
+    file : usize,
 }
 
 #[derive(Debug)]
 pub enum ParseError {
     Error,
-    Fatal(Vec<String>),
+    Fatal(Vec<(String, Span)>),
 }
 
 impl ParseError {
     pub fn display(&self) -> String {
         match self {
             ParseError::Error => "Error".to_string(),
-            ParseError::Fatal(fs) => format!( "Fatal:\n {}", fs.join("\n") ),
+            ParseError::Fatal(fs) =>
+                format!( "Fatal:\n {}"
+                       , fs.iter()
+                           .map(|(message, span)| format!("{}:{} {message}", span.start.line, span.start.column))
+                           .collect::<Vec<_>>()
+                           .join("\n") ),
         }
     }
 }
 
-impl<'a> Input<'a> {
+impl Input {
     pub fn new(s : &str) -> Input {
-        Input { cs: s.char_indices().peekable() }
+        Input::with_comments(s).0
     }
 
-    pub fn restore(&mut self, r : Input<'a>) {
-        self.cs = r.cs;
+    pub fn with_comments(s : &str) -> (Input, Vec<Comment>) {
+        Input::in_file(s, 0)
     }
 
-    pub fn next(&mut self) -> Result<char, ParseError> {
-        match self.cs.next() {
-            Some((_, c)) => Ok(c),
-            None => Err(ParseError::Error),
+    // Every span points into the file that the loader numbered as file.
+    pub fn in_file(s : &str, file : usize) -> (Input, Vec<Comment>) {
+        let end = s.chars().fold(Position { line: 1, column: 1 }, |p, c| match c {
+            '\n' => Position { line: p.line + 1, column: 1 },
+            _ => Position { line: p.line, column: p.column + 1 },
+        });
+        let (tokens, comments) = lexer::lex_with_comments(s, file);
+        let docs = comments.iter().filter(|c| c.text.starts_with('#')).cloned().collect::<Vec<_>>();
+        (Input { tokens: tokens.into(), docs: docs.into(), index: 0, end, scrutinee: false, file }, comments)
+    }
+
+    pub fn in_scrutinee(&self) -> bool {
+        self.scrutinee
+    }
+
+    // A copy of the cursor that is or isn't parsing a match scrutinee.  Use restore to
+    // move this input past whatever the copy parsed.
+    pub fn scrutinee(&self, scrutinee : bool) -> Input {
+        Input { scrutinee, ..self.clone() }
+    }
+
+    pub fn restore(&mut self, r : Input) {
+        self.index = r.index;
+    }
+
+    // The start of the next token.
+    pub fn position(&self) -> Position {
+        match self.tokens.get(self.index) {
+            Some(token) => token.span.start,
+            None => self.end,
+        }
+    }
+
+    // From start until the end of the last token that was consumed.
+    pub fn span_from(&self, start : Position) -> Span {
+        match self.index.checked_sub(1).and_then(|i| self.tokens.get(i)) {
+            Some(token) if (token.span.end.line, token.span.end.column) >= (start.line, start.column) => Span { file: self.file, start, end: token.span.end },
+            _ => Span { file: self.file, start, end: start },
         }
     }
 
-    pub fn peek(&mut self) -> Result<char, ParseError> {
-        match self.cs.peek() {
-            Some((_, c)) => Ok(*c),
-            None => Err(ParseError::Error),
+    // Whether the next token starts right where the last consumed token ended.
+    pub fn joined(&self) -> bool {
+        match (self.index.checked_sub(1).and_then(|i| self.tokens.get(i)), self.tokens.get(self.index)) {
+            (Some(previous), Some(next)) => previous.span.end == next.span.start,
+            _ => false,
         }
     }
-}
\ No newline at end of file
+
+    // The '##' doc comments on the lines between the last consumed token and the next token joined
+    // into one string with the '##' and the space after it taken off each line.  A doc comment
+    // trailing the last consumed token on its line doesn't count.
+    pub fn doc(&self) -> Option<String> {
+        let after = self.index.checked_sub(1).and_then(|i| self.tokens.get(i)).map(|t| t.span.end);
+        let before = self.position();
+
+        let lines = self.docs.iter()
+            .filter(|c| after.is_none_or(|a| c.span.start.line > a.line))
+            .filter(|c| (c.span.start.line, c.span.start.column) < (before.line, before.column))
+            .map(|c| { let line = &c.text[1..]; line.strip_prefix(' ').unwrap_or(line) })
+            .collect::<Vec<_>>();
+
+        if lines.is_empty() {
+            None
+        }
+        else {
+            Some(lines.join("\n"))
+        }
+    }
+
+    pub fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.index)?;
+        self.index += 1;
+        Some(token)
+    }
+
+    pub fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+}
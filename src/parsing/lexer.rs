@@ -0,0 +1,235 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::ast::{Position, Span, Comment};
+
+pub const KEYWORDS : [&str; 14] =
+    [ "fun"
+    , "data"
+    , "pattern"
+    , "import"
+    , "as"
+    , "let"
+    , "in"
+    , "match"
+    , "if"
+    , "then"
+    , "else"
+    , "where"
+    , "true"
+    , "false"
+    ];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Symbol(String),
+    Keyword(&'static str),
+    Number(String), // Still has its '0x'/'0b' prefix and '_' separators
+    Float(String),
+    String(String),
+    Punct(char),
+    Error(String), // Malformed literals are reported by the parser so that it can recover from them
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind : TokenKind,
+    pub span : Span,
+}
+
+struct Chs<'a> {
+    cs : Peekable<Chars<'a>>,
+    line : usize,
+    column : usize,
+}
+
+impl<'a> Chs<'a> {
+    fn position(&self) -> Position {
+        Position { line: self.line, column: self.column }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.cs.peek().copied()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.cs.clone().nth(1)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.cs.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        }
+        else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn take_while(&mut self, p : fn(char) -> bool) -> String {
+        let mut cs = String::new();
+        while let Some(c) = self.peek() {
+            if !p(c) {
+                break;
+            }
+            cs.push(c);
+            self.next();
+        }
+        cs
+    }
+}
+
+// Whitespace is dropped and '#' comments are kept to the side so that the parser never
+// has to skip over them.  Punctuation is always a single character; the parser decides
+// whether adjacent punctuation forms an operator like '->' or '|}'.
+pub fn lex_with_comments(s : &str, file : usize) -> (Vec<Token>, Vec<Comment>) {
+    fn is_symbol_char(c : char) -> bool { c.is_alphanumeric() || c == '_' }
+
+    let mut input = Chs { cs: s.chars().peekable(), line: 1, column: 1 };
+    let mut tokens = vec![];
+    let mut comments = vec![];
+
+    while let Some(c) = input.peek() {
+        let start = input.position();
+
+        let kind = match c {
+            c if c.is_whitespace() => { input.next(); continue; },
+            '#' => {
+                input.next();
+                let text = input.take_while(|c| c != '\n' && c != '\r');
+                comments.push(Comment { text, span: Span { file, start, end: input.position() } });
+                continue;
+            },
+            c if c.is_alphabetic() || c == '_' => {
+                let sym = input.take_while(is_symbol_char);
+                match KEYWORDS.iter().find(|k| **k == sym) {
+                    Some(k) => TokenKind::Keyword(k),
+                    None => TokenKind::Symbol(sym),
+                }
+            },
+            c if c.is_ascii_digit() => lex_number(&mut input),
+            '"' => lex_string(&mut input),
+            c => { input.next(); TokenKind::Punct(c) },
+        };
+
+        tokens.push(Token { kind, span: Span { file, start, end: input.position() } });
+    }
+
+    (tokens, comments)
+}
+
+// Letters are taken along with the digits so that a literal like '12ab' is reported as malformed
+// instead of being split into a number and a symbol.
+fn lex_number(input : &mut Chs) -> TokenKind {
+    fn is_number_char(c : char) -> bool { c.is_alphanumeric() || c == '_' }
+
+    let mut cs = input.take_while(is_number_char);
+
+    // '1..5' is a range and not a float
+    if input.peek() == Some('.') && input.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+        input.next();
+        cs.push('.');
+        cs.push_str(&input.take_while(is_number_char));
+        return TokenKind::Float(cs);
+    }
+
+    TokenKind::Number(cs)
+}
+
+fn lex_string(input : &mut Chs) -> TokenKind {
+    input.next();
+
+    let mut cs = String::new();
+
+    loop {
+        match input.next() {
+            Some('"') => return TokenKind::String(cs),
+            Some('\\') => match input.next() {
+                Some('n') => cs.push('\n'),
+                Some('r') => cs.push('\r'),
+                Some('t') => cs.push('\t'),
+                Some('0') => cs.push('\0'),
+                Some('\\') => cs.push('\\'),
+                Some('"') => cs.push('"'),
+                Some(_) => {
+                    // Skip the rest of the string so that its contents aren't lexed as code
+                    loop {
+                        match input.next() {
+                            Some('\\') => { input.next(); },
+                            Some('"') | None => break,
+                            Some(_) => { },
+                        }
+                    }
+                    return TokenKind::Error("unknown escape sequence in string".to_string());
+                },
+                None => return TokenKind::Error("string must have ending '\"'".to_string()),
+            },
+            Some(c) => cs.push(c),
+            None => return TokenKind::Error("string must have ending '\"'".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(s : &str) -> Vec<TokenKind> {
+        lex_with_comments(s, 0).0.into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn should_lex_keywords_as_keywords() {
+        assert_eq!( kinds("let letter in"), vec![ TokenKind::Keyword("let")
+                                                , TokenKind::Symbol("letter".to_string())
+                                                , TokenKind::Keyword("in")
+                                                ] );
+    }
+
+    #[test]
+    fn should_drop_whitespace_and_comments() {
+        assert_eq!( kinds("a # comment ; b\n  1"), vec![ TokenKind::Symbol("a".to_string())
+                                                       , TokenKind::Number("1".to_string())
+                                                       ] );
+    }
+
+    #[test]
+    fn should_keep_comments_to_the_side() {
+        let (tokens, comments) = lex_with_comments("a # comment\nb", 0);
+        assert_eq!( tokens.len(), 2 );
+        assert_eq!( comments.len(), 1 );
+        assert_eq!( comments[0].text, " comment" );
+        assert_eq!( comments[0].span.start, Position { line: 1, column: 3 } );
+    }
+
+    #[test]
+    fn should_lex_punctuation_one_character_at_a_time() {
+        assert_eq!( kinds("->|}"), vec![ TokenKind::Punct('-'), TokenKind::Punct('>'), TokenKind::Punct('|'), TokenKind::Punct('}') ] );
+    }
+
+    #[test]
+    fn should_lex_floats_but_not_ranges() {
+        assert_eq!( kinds("1.5 1..2 0x1F"), vec![ TokenKind::Float("1.5".to_string())
+                                                , TokenKind::Number("1".to_string())
+                                                , TokenKind::Punct('.')
+                                                , TokenKind::Punct('.')
+                                                , TokenKind::Number("2".to_string())
+                                                , TokenKind::Number("0x1F".to_string())
+                                                ] );
+    }
+
+    #[test]
+    fn should_record_token_spans() {
+        let (tokens, _) = lex_with_comments("fun\n  \"a b\"", 0);
+        assert_eq!( tokens[1].span, Span { file: 0, start: Position { line: 2, column: 3 }, end: Position { line: 2, column: 8 } } );
+    }
+
+    #[test]
+    fn should_not_lex_string_contents_after_bad_escape() {
+        assert_eq!( kinds(r#""\q ; fun" x"#), vec![ TokenKind::Error("unknown escape sequence in string".to_string())
+                                                  , TokenKind::Symbol("x".to_string())
+                                                  ] );
+    }
+}
@@ -0,0 +1,525 @@
+
+use std::convert::Infallible;
+use std::path::{Path, PathBuf, Component};
+
+use crate::ast::{ Ast
+                , Span
+                , Expr
+                , ExprKind
+                , Type
+                , TypeKind
+                , StandardPattern
+                , StandardPatternKind
+                , ArrayPattern
+                , ArrayPatternKind
+                , PathPattern
+                , PathPatternKind
+                , NamedPattern
+                };
+use crate::visit::{self, VisitMut, standard_variables};
+use super::input::ParseError;
+use super::parser::parse_file;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io { file : String, message : String },
+    Parse { file : String, source : String, errors : Vec<ParseError> },
+    Fatal { file : String, source : String, message : String, span : Span },
+}
+
+// The file of a span is its index into the loaded files.
+#[derive(Debug)]
+pub struct SourceFile {
+    pub name : String,
+    pub source : String,
+}
+
+pub fn load(file_name : &str) -> Result<(Vec<Ast>, Vec<SourceFile>), LoadError> {
+    load_with(file_name, |path| std::fs::read_to_string(path).map_err(|e| e.to_string()))
+}
+
+// Loads the file and everything that it imports.  Imports are resolved relative to the
+// importing file and every definition from an imported module is renamed to 'alias::name'
+// (along with everything which refers to it).  A module imported from several places is
+// only loaded once and keeps the name of the alias chain it was first imported under.
+pub fn load_with<R : Fn(&Path) -> Result<String, String>>(file_name : &str, read : R) -> Result<(Vec<Ast>, Vec<SourceFile>), LoadError> {
+    let mut loader = Loader { read: &read, files: vec![], modules: vec![], loading: vec![], asts: vec![] };
+    loader.load_module(normalize(Path::new(file_name)), None)?;
+    Ok((loader.asts, loader.files))
+}
+
+struct Module {
+    path : PathBuf,
+    names : Names,
+    imports : Vec<(String, usize)>,
+}
+
+struct Loader<'a, R> {
+    read : &'a R,
+    files : Vec<SourceFile>,
+    modules : Vec<Module>, // Same order as files
+    loading : Vec<PathBuf>,
+    asts : Vec<Ast>,
+}
+
+impl<R : Fn(&Path) -> Result<String, String>> Loader<'_, R> {
+    fn load_module(&mut self, path : PathBuf, prefix : Option<String>) -> Result<usize, LoadError> {
+        let file = path.display().to_string();
+
+        let source = (self.read)(&path).map_err(|message| LoadError::Io { file: file.clone(), message })?;
+
+        let index = self.files.len();
+        let mut asts = parse_file(&source, index).map_err(|errors| LoadError::Parse { file: file.clone(), source: source.clone(), errors })?;
+
+        self.files.push(SourceFile { name: file.clone(), source: source.clone() });
+        self.modules.push(Module { path: path.clone(), names: Names::new(prefix, &asts), imports: vec![] });
+        self.loading.push(path.clone());
+
+        for ast in &asts {
+            let (import, alias, span) = match ast {
+                Ast::Import { path, alias, span } => (path, alias, *span),
+                _ => continue,
+            };
+
+            let fatal = |message : String| LoadError::Fatal { file: file.clone(), source: source.clone(), message, span };
+
+            if self.modules[index].imports.iter().any(|(a, _)| a == alias) {
+                return Err(fatal(format!("Encountered duplicate import alias {alias}")));
+            }
+
+            let import_path = normalize(&path.parent().unwrap_or(Path::new("")).join(import));
+
+            if let Some(start) = self.loading.iter().position(|p| *p == import_path) {
+                let cycle = self.loading[start..].iter()
+                                                 .chain(std::iter::once(&import_path))
+                                                 .map(|p| p.display().to_string())
+                                                 .collect::<Vec<_>>()
+                                                 .join(" -> ");
+                return Err(fatal(format!("Encountered import cycle {cycle}")));
+            }
+
+            let module = match self.modules.iter().position(|m| m.path == import_path) {
+                Some(module) => module,
+                None => {
+                    let prefix = self.modules[index].names.qualified(alias);
+                    self.load_module(import_path, Some(prefix))?
+                },
+            };
+            self.modules[index].imports.push((alias.to_string(), module));
+        }
+
+        self.loading.pop();
+
+        for ast in &mut asts {
+            let mut q = Qualifier { modules: &self.modules, module: index, locals: vec![] };
+            let Ok(()) = qualify_ast(ast, &mut q);
+        }
+
+        self.asts.extend(asts.into_iter().filter(|ast| !matches!( ast, Ast::Import { .. } )));
+        Ok(index)
+    }
+}
+
+// Removes '.' and 'a/..' from the path so that the same file is always found under the same name.
+fn normalize(path : &Path) -> PathBuf {
+    let mut ret = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => { },
+            Component::ParentDir if matches!( ret.components().next_back(), Some(Component::Normal(_)) ) => { ret.pop(); },
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+// The main file has no prefix, so its definitions keep their names.
+struct Names {
+    prefix : Option<String>,
+    funs : Vec<String>, // Along with the top level lets
+    types : Vec<String>,
+    cons : Vec<String>,
+    patterns : Vec<String>,
+}
+
+impl Names {
+    fn new(prefix : Option<String>, asts : &[Ast]) -> Names {
+        let mut names = Names { prefix, funs: vec![], types: vec![], cons: vec![], patterns: vec![] };
+        for ast in asts {
+            match ast {
+                Ast::FunDef { name, .. } | Ast::LetDef { name, .. } => names.funs.push(name.to_string()),
+                Ast::DataDef { name, cons_defs, .. } => {
+                    names.types.push(name.to_string());
+                    names.cons.extend(cons_defs.iter().map(|c| c.name.to_string()));
+                },
+                Ast::PatternDef { name, .. } => names.patterns.push(name.to_string()),
+                Ast::Import { .. } => { },
+            }
+        }
+        names
+    }
+
+    fn qualified(&self, name : &str) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}::{name}"),
+            None => name.to_string(),
+        }
+    }
+
+    fn qualify(&self, name : &mut String) {
+        *name = self.qualified(name);
+    }
+}
+
+fn qualify_ast(ast : &mut Ast, q : &mut Qualifier) -> Result<(), Infallible> {
+    match ast {
+        Ast::FunDef { name, params, return_type, expr, .. } => {
+            q.names().qualify(name);
+            for param in params {
+                if let Some(t) = &mut param.t {
+                    q.ty(t)?;
+                }
+                standard_variables(&param.pattern, &mut q.locals);
+                q.standard(&mut param.pattern)?;
+            }
+            q.ty(return_type)?;
+            q.expr(expr)?;
+        },
+        Ast::LetDef { name, t, expr, .. } => {
+            q.names().qualify(name);
+            if let Some(t) = t {
+                q.ty(t)?;
+            }
+            q.expr(expr)?;
+        },
+        Ast::DataDef { name, cons_defs, .. } => {
+            q.names().qualify(name);
+            for cons_def in cons_defs {
+                q.names().qualify(&mut cons_def.name);
+                for t in &mut cons_def.params {
+                    q.ty(t)?;
+                }
+            }
+        },
+        Ast::PatternDef { name, pattern, .. } => {
+            q.names().qualify(name);
+            match pattern {
+                NamedPattern::Path(ps) => {
+                    for p in ps {
+                        q.path(p)?;
+                    }
+                },
+                NamedPattern::Array(ps) => {
+                    for p in ps {
+                        q.array(p)?;
+                    }
+                },
+            }
+        },
+        Ast::Import { .. } => { },
+    }
+    Ok(())
+}
+
+// Locals are the variables in scope which shadow any function of the same name.
+struct Qualifier<'a> {
+    modules : &'a [Module],
+    module : usize,
+    locals : Vec<String>,
+}
+
+impl Qualifier<'_> {
+    fn names(&self) -> &Names {
+        &self.modules[self.module].names
+    }
+
+    // Follows the 'alias::' segments of the name through the imports and then renames
+    // it to the definition of that kind in the module it ends up at (if there is one).
+    fn resolve(&self, kind : fn(&Names) -> &[String], name : &mut String) {
+        let mut segments = name.split("::").collect::<Vec<_>>();
+        let last = segments.pop().expect("split should always yield a segment");
+
+        let mut module = self.module;
+        for alias in segments {
+            match self.modules[module].imports.iter().find(|(a, _)| a == alias) {
+                Some((_, m)) => module = *m,
+                None => return,
+            }
+        }
+
+        let names = &self.modules[module].names;
+        if kind(names).iter().any(|n| n == last) {
+            *name = names.qualified(last);
+        }
+    }
+
+    // The variables of the pattern are in scope for whatever runs after it
+    fn bind(&mut self, pattern : &mut StandardPattern) -> Result<(), Infallible> {
+        standard_variables(pattern, &mut self.locals);
+        self.standard(pattern)
+    }
+}
+
+impl VisitMut for Qualifier<'_> {
+    type Error = Infallible;
+
+    fn ty(&mut self, t : &mut Type) -> Result<(), Infallible> {
+        match &mut t.kind {
+            TypeKind::Concrete(name) | TypeKind::Index { name, .. } => self.resolve(|n| &n.types, name),
+            _ => { },
+        }
+        visit::walk_ty_mut(self, t)
+    }
+
+    fn expr(&mut self, expr : &mut Expr) -> Result<(), Infallible> {
+        let count = self.locals.len();
+        match &mut expr.kind {
+            ExprKind::Variable(name) => {
+                if !self.locals.contains(name) {
+                    self.resolve(|n| &n.funs, name);
+                }
+            },
+            ExprKind::Cons { name, .. } | ExprKind::Record { name, .. } => {
+                self.resolve(|n| &n.cons, name);
+                visit::walk_expr_mut(self, expr)?;
+            },
+            ExprKind::NamedPattern(name) => self.resolve(|n| &n.patterns, name),
+            ExprKind::Let { pattern, t, value, expr } => {
+                if let Some(t) = t {
+                    self.ty(t)?;
+                }
+                self.expr(value)?;
+                self.bind(pattern)?;
+                self.expr(expr)?;
+            },
+            ExprKind::LetRec { bindings, .. } => {
+                self.locals.extend(bindings.iter().map(|b| b.name.to_string()));
+                visit::walk_expr_mut(self, expr)?;
+            },
+            ExprKind::Lambda { params, return_type, expr } => {
+                for param in params {
+                    if let Some(t) = &mut param.t {
+                        self.ty(t)?;
+                    }
+                    self.bind(&mut param.pattern)?;
+                }
+                if let Some(t) = return_type {
+                    self.ty(t)?;
+                }
+                self.expr(expr)?;
+            },
+            ExprKind::Match { expr, cases } => {
+                self.expr(expr)?;
+                for case in cases {
+                    self.bind(&mut case.pattern)?;
+                    self.expr(&mut case.expr)?;
+                    self.locals.truncate(count);
+                }
+            },
+            _ => visit::walk_expr_mut(self, expr)?,
+        }
+        self.locals.truncate(count);
+        Ok(())
+    }
+
+    fn standard(&mut self, pattern : &mut StandardPattern) -> Result<(), Infallible> {
+        if let StandardPatternKind::Cons { name, .. } | StandardPatternKind::Record { name, .. } = &mut pattern.kind {
+            self.resolve(|n| &n.cons, name);
+        }
+        visit::walk_standard_mut(self, pattern)
+    }
+
+    fn array(&mut self, pattern : &mut ArrayPattern) -> Result<(), Infallible> {
+        match &mut pattern.kind {
+            ArrayPatternKind::Cons { name, .. } | ArrayPatternKind::Record { name, .. } => self.resolve(|n| &n.cons, name),
+            ArrayPatternKind::Named(name) => self.resolve(|n| &n.patterns, name),
+            _ => { },
+        }
+        visit::walk_array_mut(self, pattern)
+    }
+
+    fn path(&mut self, pattern : &mut PathPattern) -> Result<(), Infallible> {
+        match &mut pattern.kind {
+            PathPatternKind::Cons { name, .. } | PathPatternKind::Record { name, .. } => self.resolve(|n| &n.cons, name),
+            PathPatternKind::Named(name) => self.resolve(|n| &n.patterns, name),
+            _ => { },
+        }
+        visit::walk_path_mut(self, pattern)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn load_sources(files : &[(&str, &str)]) -> Result<(Vec<Ast>, Vec<SourceFile>), LoadError> {
+        let files = files.iter().map(|(n, s)| (PathBuf::from(n), s.to_string())).collect::<HashMap<_, _>>();
+        load_with("main.pat", |path| files.get(path).cloned().ok_or(format!("{} does not exist", path.display())))
+    }
+
+    fn load_files(files : &[(&str, &str)]) -> Result<Vec<Ast>, LoadError> {
+        load_sources(files).map(|(asts, _)| asts)
+    }
+
+    fn names(asts : &[Ast]) -> Vec<String> {
+        asts.iter().map(|ast| match ast {
+            Ast::FunDef { name, .. } => name.to_string(),
+            Ast::LetDef { name, .. } => name.to_string(),
+            Ast::DataDef { name, .. } => name.to_string(),
+            Ast::Import { alias, .. } => alias.to_string(),
+            Ast::PatternDef { name, .. } => name.to_string(),
+        }).collect()
+    }
+
+    #[test]
+    fn should_qualify_imported_top_level_lets() {
+        let asts = load_files(&[ ("main.pat", "import \"lib/consts.pat\" as consts; fun main() -> Number = consts::two;")
+                               , ("lib/consts.pat", "let one = 1; let two = one.add(one);")
+                               ]).expect("should load");
+
+        assert_eq!( names(&asts), vec!["consts::one", "consts::two", "main"] );
+        assert!( matches!( &asts[1], Ast::LetDef { expr: Expr { kind: ExprKind::FunCall { params, .. }, .. }, .. }
+            if matches!( &params[0].kind, ExprKind::Variable(one) if one == "consts::one" ) ) );
+    }
+
+    #[test]
+    fn should_load_imported_definitions_with_qualified_names() {
+        let asts = load_files(&[ ("main.pat", "import \"lib/list.pat\" as list; fun main() -> Number = list::sum(list::Nil);")
+                               , ("lib/list.pat", "data List = Cons(Number, List) | Nil; fun sum(l : List) -> Number = sum(l);")
+                               ]).expect("should load");
+
+        assert_eq!( names(&asts), vec!["list::List", "list::sum", "main"] );
+
+        let (params, expr) = match &asts[1] {
+            Ast::FunDef { params, expr, .. } => (params, expr),
+            _ => panic!("expected fun def"),
+        };
+        assert!( matches!( &params[0].t, Some(Type { kind: TypeKind::Concrete(t), .. }) if t == "list::List" ) );
+        assert!( matches!( &expr.kind, ExprKind::FunCall { fun_expr, .. } if matches!( &fun_expr.kind, ExprKind::Variable(f) if f == "list::sum" ) ) );
+
+        let cons_defs = match &asts[0] {
+            Ast::DataDef { cons_defs, .. } => cons_defs,
+            _ => panic!("expected data def"),
+        };
+        assert_eq!( cons_defs[0].name, "list::Cons" );
+    }
+
+    #[test]
+    fn should_resolve_imports_relative_to_importing_file() {
+        let asts = load_files(&[ ("main.pat", "import \"lib/a.pat\" as a;")
+                               , ("lib/a.pat", "import \"../util/b.pat\" as b; fun f() -> Number = b::g();")
+                               , ("util/b.pat", "fun g() -> Number = 1;")
+                               ]).expect("should load");
+
+        assert_eq!( names(&asts), vec!["a::b::g", "a::f"] );
+        assert!( matches!( &asts[1], Ast::FunDef { expr, .. } 
+            if matches!( &expr.kind, ExprKind::FunCall { fun_expr, .. } if matches!( &fun_expr.kind, ExprKind::Variable(g) if g == "a::b::g" ) ) ) );
+    }
+
+    #[test]
+    fn should_load_diamond_import_once() {
+        let asts = load_files(&[ ("main.pat", "import \"a.pat\" as a; import \"d.pat\" as d; fun main() -> Number = d::one.add(a::f());")
+                               , ("a.pat", "import \"d.pat\" as x; fun f() -> Number = x::one;")
+                               , ("d.pat", "let one = 1;")
+                               ]).expect("should load");
+
+        assert_eq!( names(&asts), vec!["a::x::one", "a::f", "main"] );
+        assert!( matches!( &asts[1], Ast::FunDef { expr, .. } if matches!( &expr.kind, ExprKind::Variable(one) if one == "a::x::one" ) ) );
+        assert!( matches!( &asts[2], Ast::FunDef { expr: Expr { kind: ExprKind::FunCall { params, .. }, .. }, .. }
+            if matches!( &params[0].kind, ExprKind::Variable(one) if one == "a::x::one" ) ) );
+    }
+
+    #[test]
+    fn should_record_file_of_spans() {
+        let (asts, files) = load_sources(&[ ("main.pat", "import \"lib/a.pat\" as a; fun main() -> Number = 1;")
+                                          , ("lib/a.pat", "fun f() -> Number = 1;")
+                                          ]).expect("should load");
+
+        assert_eq!( files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["main.pat", "lib/a.pat"] );
+        assert_eq!( files[1].source, "fun f() -> Number = 1;" );
+        assert!( matches!( &asts[0], Ast::FunDef { span, expr, .. } if span.file == 1 && expr.span.file == 1 ) );
+        assert!( matches!( &asts[1], Ast::FunDef { span, .. } if span.file == 0 ) );
+    }
+
+    #[test]
+    fn should_not_qualify_shadowing_local() {
+        let asts = load_files(&[ ("main.pat", "import \"a.pat\" as a;")
+                               , ("a.pat", "fun g() -> Number = 1; fun f(g : Number) -> Number = g; fun h() -> Number = let x = 1 in g();")
+                               ]).expect("should load");
+
+        assert!( matches!( &asts[1], Ast::FunDef { expr, .. } if matches!( &expr.kind, ExprKind::Variable(g) if g == "g" ) ) );
+
+        let call = match &asts[2] {
+            Ast::FunDef { expr: Expr { kind: ExprKind::Let { expr, .. }, .. }, .. } => expr,
+            _ => panic!("expected fun def with let"),
+        };
+        assert!( matches!( &call.kind, ExprKind::FunCall { fun_expr, .. } if matches!( &fun_expr.kind, ExprKind::Variable(g) if g == "a::g" ) ) );
+    }
+
+    #[test]
+    fn should_not_qualify_let_pattern_variables() {
+        let asts = load_files(&[ ("main.pat", "import \"a.pat\" as a;")
+                               , ("a.pat", "data P = P(Number); fun g() -> Number = 1; fun f(p : P) -> Number = let P(g) = p in g;")
+                               ]).expect("should load");
+
+        let (pattern, body) = match &asts[2] {
+            Ast::FunDef { expr: Expr { kind: ExprKind::Let { pattern, expr, .. }, .. }, .. } => (pattern, expr),
+            _ => panic!("expected fun def with let"),
+        };
+        assert!( matches!( &pattern.kind, StandardPatternKind::Cons { name, .. } if name == "a::P" ) );
+        assert!( matches!( &body.kind, ExprKind::Variable(g) if g == "g" ) );
+    }
+
+    #[test]
+    fn should_not_qualify_param_pattern_variables() {
+        let asts = load_files(&[ ("main.pat", "import \"a.pat\" as a;")
+                               , ("a.pat", "data P = P(Number); fun g() -> Number = 1; fun f(P(g) : P) -> Number = g;")
+                               ]).expect("should load");
+
+        let (params, body) = match &asts[2] {
+            Ast::FunDef { params, expr, .. } => (params, expr),
+            _ => panic!("expected fun def"),
+        };
+        assert!( matches!( &params[0].pattern.kind, StandardPatternKind::Cons { name, .. } if name == "a::P" ) );
+        assert!( matches!( &body.kind, ExprKind::Variable(g) if g == "g" ) );
+    }
+
+    #[test]
+    fn should_detect_import_cycle() {
+        let result = load_files(&[ ("main.pat", "import \"a.pat\" as a;")
+                                 , ("a.pat", "import \"b.pat\" as b;")
+                                 , ("b.pat", "import \"./a.pat\" as a;")
+                                 ]);
+
+        assert!( matches!( result, Err(LoadError::Fatal { file, message, .. }) 
+            if file == "b.pat" && message == "Encountered import cycle a.pat -> b.pat -> a.pat" ) );
+    }
+
+    #[test]
+    fn should_report_missing_import() {
+        let result = load_files(&[ ("main.pat", "import \"a.pat\" as a;") ]);
+
+        assert!( matches!( result, Err(LoadError::Io { file, .. }) if file == "a.pat" ) );
+    }
+
+    #[test]
+    fn should_report_parse_errors_with_imported_file() {
+        let result = load_files(&[ ("main.pat", "import \"a.pat\" as a;"), ("a.pat", "fun") ]);
+
+        assert!( matches!( result, Err(LoadError::Parse { file, .. }) if file == "a.pat" ) );
+    }
+
+    #[test]
+    fn should_qualify_pattern_references() {
+        let asts = load_files(&[ ("main.pat", "import \"q.pat\" as q;")
+                               , ("q.pat", "pattern leaf = {| Leaf(x) |}; pattern leaves = {| Node(_, !), $leaf |}; fun f(t : Tree) -> [Number] = $leaves.path(t);")
+                               ]).expect("should load");
+
+        assert_eq!( names(&asts), vec!["q::leaf", "q::leaves", "q::f"] );
+        assert!( matches!( &asts[1], Ast::PatternDef { pattern: NamedPattern::Path(ps), .. } 
+            if matches!( &ps[1].kind, PathPatternKind::Named(n) if n == "q::leaf" ) ) );
+        assert!( matches!( &asts[2], Ast::FunDef { expr: Expr { kind: ExprKind::FunCall { params, .. }, .. }, .. } 
+            if matches!( &params[0].kind, ExprKind::NamedPattern(n) if n == "q::leaves" ) ) );
+    }
+}
@@ -1,4 +1,5 @@
 
+mod lexer;
 mod input;
 mod util;
 
@@ -6,5 +7,7 @@ mod type_parser;
 mod expr_parser;
 mod pattern_parser;
 pub mod parser;
+pub mod printer;
+pub mod loader;
 
 pub use input::ParseError;
\ No newline at end of file
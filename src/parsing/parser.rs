@@ -1,30 +1,140 @@
 
 use crate::ast::{ Ast
+                , Comment
+                , Expr
+                , ExprKind
+                , NamedPattern
                 , FunParam
                 , ConsDef
+                , Type
+                , StandardPattern
+                , ArrayPattern
+                , PathPattern
                 };
 use super::input::{Input, ParseError};
+use super::lexer::{Token, TokenKind};
 use super::util::{ parse_symbol
+                 , parse_series
                  , parse_params
+                 , parse_string
                  , keyword
                  , maybe
                  , punct
                  , fatal
                  , fail
+                 , start_position
+                 , at_end
                  };
 use super::type_parser::parse_type;
-use super::expr_parser::parse_expr;
+use super::pattern_parser::{parse_standard_pattern, parse_array_pattern, parse_path_pattern};
+use super::expr_parser::{parse_expr, parse_bindings, parse_path_pattern_expr, parse_array_pattern_expr};
 
-pub fn parse(input : &str) -> Result<Vec<Ast>, ParseError> {
+pub fn parse(input : &str) -> Result<Vec<Ast>, Vec<ParseError>> {
+    parse_file(input, 0)
+}
+
+// Like parse but the spans point into the file that the loader numbered as file.
+pub fn parse_file(input : &str, file : usize) -> Result<Vec<Ast>, Vec<ParseError>> {
+    parse_top_levels(Input::in_file(input, file).0)
+}
+
+// Comments are not part of the ast; the printer puts them back in by their spans.
+pub fn parse_with_comments(input : &str) -> Result<(Vec<Ast>, Vec<Comment>), Vec<ParseError>> {
+    let (input, comments) = Input::with_comments(input);
+    Ok((parse_top_levels(input)?, comments))
+}
+
+// Parsers for a single expression, type or pattern, such as a query typed in by the user of a host
+// application.  All of the input has to be consumed.
+pub fn parse_single_expr(input : &str) -> Result<Expr, ParseError> {
+    parse_single(input, "expression", parse_expr)
+}
+
+pub fn parse_single_type(input : &str) -> Result<Type, ParseError> {
+    parse_single(input, "type", parse_type)
+}
+
+pub fn parse_single_standard_pattern(input : &str) -> Result<StandardPattern, ParseError> {
+    parse_single(input, "pattern", |i| parse_standard_pattern(parse_expr, i))
+}
+
+pub fn parse_single_array_pattern(input : &str) -> Result<ArrayPattern, ParseError> {
+    parse_single(input, "array pattern", |i| parse_array_pattern(parse_expr, i))
+}
+
+pub fn parse_single_path_pattern(input : &str) -> Result<PathPattern, ParseError> {
+    parse_single(input, "path pattern", |i| parse_path_pattern(parse_expr, i))
+}
+
+fn parse_single<T, F : Fn(&mut Input) -> Result<T, ParseError>>(input : &str, what : &str, p : F) -> Result<T, ParseError> {
     let mut input = Input::new(input);
 
+    let result = match p(&mut input) {
+        Err(ParseError::Error) => fail(&input, &format!("expected {what}")),
+        result => result,
+    }?;
+
+    if !at_end(&mut input)? {
+        return fail(&input, &format!("expected end of input after {what}"));
+    }
+
+    Ok(result)
+}
+
+fn parse_top_levels(mut input : Input) -> Result<Vec<Ast>, Vec<ParseError>> {
+
     let mut tls = vec![];
+    let mut errors = vec![];
     loop {
-        match parse_top_level(&mut input) {
+        match at_end(&mut input) {
+            Ok(true) => break,
+            Ok(false) => { },
+            Err(e) => { errors.push(e); break; },
+        }
+
+        let start = input.position();
+
+        let result = match parse_top_level(&mut input) {
+            Err(ParseError::Error) => fail(&input, "expected 'fun', 'let', 'data', 'pattern' or 'import' definition"),
+            result => result,
+        };
+
+        match result {
             Ok(tl) => tls.push(tl),
-            Err(ParseError::Error) => return Ok(tls),
-            Err(e @ ParseError::Fatal(_)) => return Err(e),
+            Err(e) => {
+                errors.push(e);
+                let no_progress = input.position() == start;
+                recover(&mut input, no_progress);
+            },
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(tls)
+    }
+    else {
+        Err(errors)
+    }
+}
+
+// Skips input until just after the next ';' or until the start of the next
+// 'fun', 'data', 'pattern' or 'import' definition so that parsing can continue after an error.
+fn recover(input : &mut Input, mut must_advance : bool) {
+    fn starts_definition(input : &Input) -> bool {
+        matches!( input.peek(), Some(Token { kind: TokenKind::Keyword("fun" | "data" | "import" | "pattern"), .. }) )
+    }
+
+    loop {
+        if !must_advance && starts_definition(input) {
+            return;
+        }
+
+        match input.next() {
+            Some(Token { kind: TokenKind::Punct(';'), .. }) | None => return,
+            Some(_) => { },
         }
+
+        must_advance = false;
     }
 }
 
@@ -47,38 +157,90 @@ fn parse_fun_def(input : &mut Input) -> Result<Ast, ParseError> {
     }
 
     fn params(input : &mut Input) -> Result<FunParam, ParseError> {
-        let name = parse_symbol(input)?;
-        fatal(punct(input, ":"), "fun parameter needs :")?;
-        let t = Some(fatal(parse_type(input), "fun parameter needs types")?);
-        Ok(FunParam{ name, t })
+        let pattern = parse_standard_pattern(parse_expr, input)?;
+        fatal(punct(input, ":"), input, "fun parameter needs :")?;
+        let t = Some(fatal(parse_type(input), input, "fun parameter needs types")?);
+        Ok(FunParam{ pattern, t })
     }
 
+    let doc = input.doc();
+
+    let start = start_position(input)?;
+
     keyword(input, "fun")?;
 
-    let name = fatal(parse_fun_name(input), "fun must have a name")?;
+    let name = fatal(parse_fun_name(input), input, "fun must have a name")?;
+
+    let params = fatal(parse_params(|i| params(i), input), input, "fun must have parameters")?;
+
+    fatal(punct(input, "->"), input, "fun must have ->")?;
+
+    let return_type = fatal(parse_type(input), input, "fun must have type")?;
+
+    fatal(punct(input, "="), input, "fun must have =")?;
+
+    let expr = fatal(parse_expr(input), input, "fun must have an expr")?;
+
+    // 'where' bindings are in scope for the whole body and for each other
+    let expr = match maybe(keyword(input, "where"))? {
+        Some(_) => {
+            let bindings = fatal(parse_bindings(input), input, "where must have bindings")?;
+            let span = input.span_from(expr.span.start);
+            Expr { kind: ExprKind::LetRec { bindings, expr: Box::new(expr) }, span }
+        },
+        None => expr,
+    };
+
+    fatal(punct(input, ";"), input, "fun must have an ending ';'")?;
+
+    Ok(Ast::FunDef { name, params, return_type, expr, doc, span: input.span_from(start) })
+}
+
+fn parse_let_def(input : &mut Input) -> Result<Ast, ParseError> {
+    let doc = input.doc();
 
-    let params = fatal(parse_params(|i| params(i), input), "fun must have parameters")?;
+    let start = start_position(input)?;
 
-    fatal(punct(input, "->"), "fun must have ->")?;
+    keyword(input, "let")?;
 
-    let return_type = fatal(parse_type(input), "fun must have type")?;
+    let name = fatal(parse_symbol(input), input, "top level let must have a name")?;
 
-    fatal(punct(input, "="), "fun must have =")?;
+    let t = match maybe(punct(input, ":"))? {
+        Some(_) => Some(fatal(parse_type(input), input, "top level let must have a type after ':'")?),
+        None => None,
+    };
 
-    let expr = fatal(parse_expr(input), "fun must have an expr")?;
+    fatal(punct(input, "="), input, "top level let must have '='")?;
 
-    fatal(punct(input, ";"), "fun must have an ending ';'")?;
+    let expr = fatal(parse_expr(input), input, "top level let must have an expr")?;
 
-    Ok(Ast::FunDef { name, params, return_type, expr })
+    fatal(punct(input, ";"), input, "top level let must have an ending ';'")?;
+
+    Ok(Ast::LetDef { name, t, expr, doc, span: input.span_from(start) })
 }
 
 fn parse_data_def(input : &mut Input) -> Result<Ast, ParseError> {
     fn parse_cons_def(input : &mut Input) -> Result<ConsDef, ParseError> {
+        fn parse_field(input : &mut Input) -> Result<(String, Type), ParseError> {
+            let name = parse_symbol(input)?;
+            fatal(punct(input, ":"), input, "record field definition must have a ':'")?;
+            let t = fatal(parse_type(input), input, "record field definition must have a type")?;
+            Ok((name, t))
+        }
+
+        let doc = input.doc();
+
+        let start = start_position(input)?;
+
         let name = parse_type_name(input)?;
-        match maybe(parse_params(parse_type, input))? {
-            Some(params) => Ok(ConsDef { name, params }),
-            None => Ok(ConsDef { name, params: vec![] }),
+
+        if let Some(fields) = maybe(parse_series(parse_field, "{", "}", input))? {
+            let (fields, params) = fields.into_iter().unzip();
+            return Ok(ConsDef { name, params, fields: Some(fields), doc, span: input.span_from(start) });
         }
+
+        let params = maybe(parse_params(parse_type, input))?.unwrap_or_default();
+        Ok(ConsDef { name, params, fields: None, doc, span: input.span_from(start) })
     }
     
     fn parse_cons_defs(input : &mut Input) -> Result<Vec<ConsDef>, ParseError> {
@@ -89,7 +251,7 @@ fn parse_data_def(input : &mut Input) -> Result<Ast, ParseError> {
         }
         let mut ps = vec![];
         loop {
-            ps.push(fatal(parse_cons_def(input), "data must have valid constructor definitions")?);
+            ps.push(fatal(parse_cons_def(input), input, "data must have valid constructor definitions")?);
             match punct(input, "|") {
                 Ok(_) => continue,
                 Err(ParseError::Error) => { },
@@ -97,7 +259,7 @@ fn parse_data_def(input : &mut Input) -> Result<Ast, ParseError> {
             }
             match punct(input, ";") {
                 Ok(_) => break,
-                Err(ParseError::Error) => return fail("data definition must end with ;"),
+                Err(ParseError::Error) => return fail(input, "data definition must end with ;"),
                 Err(e @ ParseError::Fatal(_)) => return Err(e),
             }
         }
@@ -122,21 +284,87 @@ fn parse_data_def(input : &mut Input) -> Result<Ast, ParseError> {
         }
     }
 
+    fn parse_type_param(input : &mut Input) -> Result<String, ParseError> {
+        let rp = input.clone();
+
+        let sym = parse_symbol(input)?;
+
+        let first = sym.chars().next()
+            .expect("parse_type_param parse_symbol somehow returned zero length string");
+
+        if first.is_lowercase() {
+            Ok(sym)
+        }
+        else {
+            input.restore(rp);
+            Err(ParseError::Error)
+        }
+    }
+
+    let doc = input.doc();
+
+    let start = start_position(input)?;
+
     keyword(input, "data")?;
 
-    let name = fatal(parse_type_name(input), "data definition must have a name")?;
+    let name = fatal(parse_type_name(input), input, "data definition must have a name")?;
+
+    let type_params = maybe(parse_series(parse_type_param, "<", ">", input))?.unwrap_or_default();
+
+    fatal(punct(input, "="), input, "data definition must have a =")?;
+
+    let cons_defs = fatal(parse_cons_defs(input), input, "data definition must have data defs")?;
 
-    fatal(punct(input, "="), "data definition must have a =")?;
+    Ok(Ast::DataDef{ name, type_params, cons_defs, doc, span: input.span_from(start) })
+}
+
+fn parse_import(input : &mut Input) -> Result<Ast, ParseError> {
+    let start = start_position(input)?;
+
+    keyword(input, "import")?;
+
+    let path = fatal(parse_string(input), input, "import must have a file path")?;
+
+    fatal(keyword(input, "as"), input, "import must have 'as'")?;
+
+    let alias = fatal(parse_symbol(input), input, "import must have an alias")?;
+
+    fatal(punct(input, ";"), input, "import must have an ending ';'")?;
+
+    Ok(Ast::Import { path, alias, span: input.span_from(start) })
+}
+
+fn parse_pattern_def(input : &mut Input) -> Result<Ast, ParseError> {
+    let start = start_position(input)?;
+
+    keyword(input, "pattern")?;
+
+    let name = fatal(parse_symbol(input), input, "pattern must have a name")?;
+
+    fatal(punct(input, "="), input, "pattern must have =")?;
 
-    let cons_defs = fatal(parse_cons_defs(input), "data definition must have data defs")?;
+    let pattern = match maybe(parse_path_pattern_expr(input))? {
+        Some(ExprKind::PathPattern(ps)) => NamedPattern::Path(ps),
+        Some(_) => unreachable!("parse_path_pattern_expr should only return path patterns"),
+        None => match maybe(parse_array_pattern_expr(input))? {
+            Some(ExprKind::ArrayPattern(ps)) => NamedPattern::Array(ps),
+            Some(_) => unreachable!("parse_array_pattern_expr should only return array patterns"),
+            None => return fail(input, "pattern must be a path pattern {| ... |} or an array pattern [| ... |]"),
+        },
+    };
 
-    Ok(Ast::DataDef{ name, cons_defs })
+    fatal(punct(input, ";"), input, "pattern must have an ending ';'")?;
+
+    Ok(Ast::PatternDef { name, pattern, span: input.span_from(start) })
 }
 
 fn parse_top_level(input : &mut Input) -> Result<Ast, ParseError> {
 
     let ps = [ parse_fun_def 
+             , parse_let_def
              , parse_data_def
+             , parse_import
+             , parse_pattern_def
              ];
 
     let mut tl = None;
@@ -158,9 +386,10 @@ fn parse_top_level(input : &mut Input) -> Result<Ast, ParseError> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ast::{TypeKind, StandardPatternKind, ArrayPatternKind, PathPatternKind};
 
     #[test]
-    fn should_parse_function() -> Result<(), ParseError> {
+    fn should_parse_function() -> Result<(), Vec<ParseError>> {
         let result = parse("
             fun name ( a : T, b : T ) -> Number = 8;
         ")?;
@@ -172,7 +401,22 @@ mod test {
     }
 
     #[test]
-    fn should_parse_data() -> Result<(), ParseError> {
+    fn should_parse_function_with_pattern_params() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            fun first([x | _] : [a], Pair(_, y) | Other(y) : Pair) -> a = x;
+        ")?;
+
+        let params = match &result[0] {
+            Ast::FunDef { params, .. } => params,
+            _ => panic!("expected fun def"),
+        };
+        assert!( matches!( params[0].pattern.kind, StandardPatternKind::StandardArray(_) ) );
+        assert!( matches!( params[1].pattern.kind, StandardPatternKind::Or(_) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_data() -> Result<(), Vec<ParseError>> {
         let result = parse("
             data X = H(X, Number) | Nil;
         ")?;
@@ -184,9 +428,65 @@ mod test {
     }
 
     #[test]
-    fn should_parse_simple_program() -> Result<(), ParseError> {
+    fn should_parse_top_level_let() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            ## The answer
+            let answer : Number = 42;
+            let twice = answer.add(answer);
+        ")?;
+
+        assert!( matches!( &result[0], Ast::LetDef { name, t: Some(_), doc: Some(doc), .. } if name == "answer" && doc == "The answer" ) );
+        assert!( matches!( &result[1], Ast::LetDef { name, t: None, expr: Expr { kind: ExprKind::FunCall { .. }, .. }, .. } if name == "twice" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_top_level_let_with_in() {
+        let result = parse("let a = 1 in a;");
+
+        assert!( matches!( result, Err(errors) if matches!( &errors[0], ParseError::Fatal(fs) if fs[0].0 == "top level let must have an ending ';'" ) ) );
+    }
+
+    #[test]
+    fn should_parse_where_as_let_rec() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            fun f(n : Number) -> Number = go(n) where go = |i| go(i), stop = |i| i;
+        ")?;
+
+        assert!( matches!( &result[0], Ast::FunDef { expr: Expr { kind: ExprKind::LetRec { bindings, expr }, .. }, .. }
+            if bindings.len() == 2 && matches!( expr.kind, ExprKind::FunCall { .. } ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_attach_doc_comments() -> Result<(), Vec<ParseError>> {
         let result = parse("
-            data List = Cons( a, List<a> ) | Nil ;
+            # not a doc comment
+            ## Makes a list
+            ##   longer
+            fun name() -> Number = 8; ## not for data
+            ## Lists
+            data List =
+                ## Has an item
+                Cons(Number, List) |
+                # not a doc comment
+                Nil;
+        ")?;
+
+        assert!( matches!( &result[0], Ast::FunDef { doc: Some(doc), .. } if doc == "Makes a list\n  longer" ) );
+        let cons_defs = match &result[1] {
+            Ast::DataDef { doc: Some(doc), cons_defs, .. } if doc == "Lists" => cons_defs,
+            x => panic!("expected documented data, found {:?}", x),
+        };
+        assert_eq!( cons_defs[0].doc.as_deref(), Some("Has an item") );
+        assert_eq!( cons_defs[1].doc, None );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_simple_program() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            data List<a> = Cons( a, List<a> ) | Nil ;
 
             fun sum( input : List<Number> ) -> Number = input.fold( 0, |a, n| a.add(n) ) ;
 
@@ -199,4 +499,224 @@ mod test {
         Ok(())
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn should_record_definition_spans() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            data X = Nil;
+            fun name() -> X = Nil;
+        ")?;
+
+        let spans = result.iter().map(|tl| match tl {
+            Ast::DataDef { span, .. } => *span,
+            Ast::FunDef { span, .. } => *span,
+            Ast::LetDef { span, .. } => *span,
+            Ast::Import { span, .. } => *span,
+            Ast::PatternDef { span, .. } => *span,
+        }).collect::<Vec<_>>();
+
+        assert_eq!( spans[0].start.line, 2 );
+        assert_eq!( spans[0].start.column, 13 );
+        assert_eq!( spans[0].end.line, 2 );
+        assert_eq!( spans[0].end.column, 26 );
+        assert_eq!( spans[1].start.line, 3 );
+        assert_eq!( spans[1].start.column, 13 );
+        Ok(())
+    }
+
+    #[test]
+    fn should_attach_span_to_fatal_error() {
+        let result = parse("
+            fun name() -> Number = 
+                blah.;
+        ");
+
+        let errors = match result {
+            Err(mut errors) if errors.len() == 1 => match errors.pop() {
+                Some(ParseError::Fatal(errors)) => errors,
+                _ => panic!("expected fatal error"),
+            },
+            _ => panic!("expected single error"),
+        };
+
+        let (message, span) = &errors[0];
+        assert_eq!( message, "there must exist a symbol after ." );
+        assert_eq!( span.start.line, 3 );
+        assert_eq!( span.start.column, 22 );
+        assert_eq!( span.end.column, 23 );
+    }
+
+    #[test]
+    fn should_reject_trailing_input() {
+        let result = parse("
+            data X = Nil;
+            blah
+        ");
+
+        let errors = match result {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected trailing input to fail"),
+        };
+
+        assert_eq!( errors.len(), 1 );
+        assert!( matches!( &errors[0], ParseError::Fatal(fs) if fs[0].0 == "expected 'fun', 'let', 'data', 'pattern' or 'import' definition" && fs[0].1.start.line == 3 ) );
+    }
+
+    #[test]
+    fn should_parse_single_items() -> Result<(), ParseError> {
+        let expr = parse_single_expr("{| Cons(a, _), Cons(b, _) |}")?;
+        let t = parse_single_type("fun([a]) -> (a, Number)")?;
+        let standard = parse_single_standard_pattern("Cons(h, _) | Nil")?;
+        let array = parse_single_array_pattern("_*")?;
+        let path = parse_single_path_pattern("Cons(a, _)")?;
+
+        assert!( matches!( expr.kind, ExprKind::PathPattern(ps) if ps.len() == 2 ) );
+        assert!( matches!( t.kind, TypeKind::Fun { .. } ) );
+        assert!( matches!( standard.kind, StandardPatternKind::Or(_) ) );
+        assert!( matches!( array.kind, ArrayPatternKind::WildcardZeroOrMore ) );
+        assert!( matches!( path.kind, PathPatternKind::Cons { .. } ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_single_item_with_trailing_input() {
+        let result = parse_single_expr("1 + 2 3");
+
+        assert!( matches!( result, Err(ParseError::Fatal(fs)) if fs[0].0 == "expected end of input after expression" && fs[0].1.start.column == 7 ) );
+    }
+
+    #[test]
+    fn should_reject_empty_single_item() {
+        let result = parse_single_type("");
+
+        assert!( matches!( result, Err(ParseError::Fatal(fs)) if fs[0].0 == "expected type" ) );
+    }
+
+    #[test]
+    fn should_report_every_broken_definition() {
+        let result = parse("
+            fun a() -> Number = ;
+            data X = Nil;
+            fun b() -> Number = 5
+            # fun c() -> Number = ;
+            fun c() -> = 5;
+            fun d() -> Number = 5;
+        ");
+
+        let errors = match result {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected errors"),
+        };
+
+        let lines = errors.iter().map(|e| match e {
+            ParseError::Fatal(fs) => fs[0].1.start.line,
+            ParseError::Error => panic!("expected fatal errors"),
+        }).collect::<Vec<_>>();
+
+        assert_eq!( lines, vec![2, 6, 6] );
+    }
+
+    #[test]
+    fn should_resync_after_semicolon() {
+        let result = parse("
+            data = ; data Y = Nil;
+        ");
+
+        let errors = match result {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected errors"),
+        };
+
+        assert_eq!( errors.len(), 1 );
+    }
+
+    #[test]
+    fn should_not_resync_inside_string() {
+        let result = parse(r#"
+            fun a() -> = "; fun b() -> String = ";
+            fun c() -> String = "c";
+        "#);
+
+        let errors = match result {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected errors"),
+        };
+
+        assert_eq!( errors.len(), 1 );
+    }
+
+    #[test]
+    fn should_parse_data_type_params() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            data Pair<a, b> = Pair(a, b);
+            data X = X;
+        ")?;
+
+        assert!( matches!( &result[0], Ast::DataDef { type_params, .. } if *type_params == vec!["a", "b"] ) );
+        assert!( matches!( &result[1], Ast::DataDef { type_params, .. } if type_params.is_empty() ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_upper_case_data_type_param() {
+        let result = parse("
+            data Pair<A> = Pair(A);
+        ");
+
+        assert!( result.is_err() );
+    }
+
+    #[test]
+    fn should_parse_record_data() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            data Person = Person { name : String, age : Number } | Nobody;
+        ")?;
+
+        let cons_defs = match &result[0] {
+            Ast::DataDef { cons_defs, .. } => cons_defs,
+            _ => panic!("expected data def"),
+        };
+
+        assert_eq!( cons_defs[0].fields, Some(vec!["name".to_string(), "age".to_string()]) );
+        assert_eq!( cons_defs[0].params.len(), 2 );
+        assert_eq!( cons_defs[1].fields, None );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_import() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            import \"lib/list.pat\" as list;
+
+            fun f( l : list::List<Number> ) -> Number = list::sum(l);
+        ")?;
+
+        assert_eq!( result.len(), 2 );
+        assert!( matches!( &result[0], Ast::Import { path, alias, .. } if path == "lib/list.pat" && alias == "list" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_import_without_alias() {
+        let result = parse("import \"list.pat\";");
+        assert!( matches!( result, Err(errors) if matches!( &errors[0], ParseError::Fatal(fs) if fs[0].0 == "import must have 'as'" ) ) );
+    }
+
+    #[test]
+    fn should_parse_pattern_defs() -> Result<(), Vec<ParseError>> {
+        let result = parse("
+            pattern leaves = {| Node(_, !), $leaf |};
+            pattern pairs = [| a, b |];
+        ")?;
+
+        assert!( matches!( &result[0], Ast::PatternDef { name, pattern: NamedPattern::Path(ps), .. } if name == "leaves" && ps.len() == 2 ) );
+        assert!( matches!( &result[1], Ast::PatternDef { name, pattern: NamedPattern::Array(ps), .. } if name == "pairs" && ps.len() == 2 ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_pattern_def_without_pattern() {
+        let result = parse("pattern p = 5;");
+        assert!( matches!( result, Err(errors) 
+            if matches!( &errors[0], ParseError::Fatal(fs) if fs[0].0 == "pattern must be a path pattern {| ... |} or an array pattern [| ... |]" ) ) );
+    }
+}
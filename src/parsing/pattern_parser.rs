@@ -1,19 +1,32 @@
 use super::input::{Input, ParseError};
 use super::util::{ into
+                 , fail
                  , parse_params
                  , parse_symbol
+                 , parse_qualified_symbol
+                 , last_segment
                  , parse_number
                  , parse_bool
+                 , parse_string
                  , keyword
                  , punct
                  , maybe
                  , fatal
+                 , start_position
+                 , is_record_start
+                 , parse_record_fields
+                 , joined_punct
                  };
 use crate::ast::{ Expr
+                , Span
                 , StandardPattern
+                , StandardPatternKind
                 , PathPattern
+                , PathPatternKind
                 , ArrayPattern
+                , ArrayPatternKind
                 , StandardArrayPattern
+                , Quantifier
                 };
 
 fn parse_variable(input : &mut Input) -> Result<String, ParseError> {
@@ -33,25 +46,25 @@ fn parse_variable(input : &mut Input) -> Result<String, ParseError> {
     }
 }
 
-fn parse_constructor<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, input : &mut Input) -> Result<(String, Vec<T>), ParseError> {
-    fn parse_name(input : &mut Input) -> Result<String, ParseError> {
-        let rp = input.clone();
+fn parse_cons_name(input : &mut Input) -> Result<String, ParseError> {
+    let rp = input.clone();
 
-        let sym = parse_symbol(input)?;
+    let sym = parse_qualified_symbol(input)?;
 
-        let first = sym.chars().nth(0)
-            .expect("pattern parse_symbol somehow returned zero length string");
+    let first = last_segment(&sym).chars().nth(0)
+        .expect("pattern parse_symbol somehow returned zero length string");
 
-        if first.is_uppercase() {
-            Ok(sym)
-        }
-        else {
-            input.restore(rp);
-            Err(ParseError::Error)
-        }
+    if first.is_uppercase() {
+        Ok(sym)
+    }
+    else {
+        input.restore(rp);
+        Err(ParseError::Error)
     }
+}
 
-    let name = parse_name(input)?;
+fn parse_constructor<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, input : &mut Input) -> Result<(String, Vec<T>), ParseError> {
+    let name = parse_cons_name(input)?;
 
     match maybe(parse_params(p, input))? {
         Some(params) => Ok( (name, params) ),
@@ -59,6 +72,30 @@ fn parse_constructor<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, inpu
     }
 }
 
+// A field without a pattern ('Person { name }') binds a variable with the same name as the field.
+fn parse_record<T, F, V>(p : F, pun : V, input : &mut Input) -> Result<(String, Vec<(String, T)>), ParseError> 
+    where F : Fn(&mut Input) -> Result<T, ParseError>
+        , V : Fn(String, Span) -> T {
+
+    let rp = input.clone();
+    let name = parse_cons_name(input)?;
+
+    if !is_record_start(input) {
+        input.restore(rp);
+        return Err(ParseError::Error);
+    }
+
+    let fields = parse_record_fields(p, input)?
+        .into_iter()
+        .map(|(field, span, pattern)| match pattern {
+            Some(pattern) => (field, pattern),
+            None => (field.clone(), pun(field, span)),
+        })
+        .collect();
+
+    Ok((name, fields))
+}
+
 fn parse_at<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, input : &mut Input) -> Result<(String, Box<T>), ParseError> {
     let rp = input.clone();
     let name = parse_symbol(input)?;
@@ -67,7 +104,7 @@ fn parse_at<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, input : &mut
         Err(ParseError::Error) => { input.restore(rp); return Err(ParseError::Error); },
         Err(e @ ParseError::Fatal(_)) => return Err(e),
     }
-    let pattern = Box::new(fatal(p(input), "@ pattern is missing a target pattern")?);
+    let pattern = Box::new(fatal(p(input), input, "@ pattern is missing a target pattern")?);
     Ok((name, pattern))
 }
 
@@ -85,7 +122,7 @@ fn parse_standard_array<P, F>( parser : F, input : &mut Input) -> Result<Standar
     let mut items = vec![];
 
     loop {
-        let item = fatal(parser(input), "standard array pattern must have patterns after [")?;
+        let item = fatal(parser(input), input, "standard array pattern must have patterns after [")?;
         items.push(item);
 
         match punct(input, ",") {
@@ -96,12 +133,12 @@ fn parse_standard_array<P, F>( parser : F, input : &mut Input) -> Result<Standar
 
         break match punct(input, "|") {
             Ok(_) => {
-                let rest = Some(Box::new(fatal(parser(input), "standard array pattern must have rest pattern after |")?));
-                fatal(punct(input, "]"), "end of standard array pattern must be ]")?;
+                let rest = Some(Box::new(fatal(parser(input), input, "standard array pattern must have rest pattern after |")?));
+                fatal(punct(input, "]"), input, "end of standard array pattern must be ]")?;
                 Ok(StandardArrayPattern::Array{ items, rest })
             },
             Err(ParseError::Error) => {
-                fatal(punct(input, "]"), "end of standard array pattern must be ]")?;
+                fatal(punct(input, "]"), input, "end of standard array pattern must be ]")?;
                 Ok(StandardArrayPattern::Array{ items, rest: None})
             },
             Err(e @ ParseError::Fatal(_)) => Err(e),
@@ -109,46 +146,243 @@ fn parse_standard_array<P, F>( parser : F, input : &mut Input) -> Result<Standar
     }
 }
 
+// Inclusive minimum and maximum
+type Bounds = (Option<i64>, Option<i64>);
+type Comparison = fn(i64, &Input) -> Result<Bounds, ParseError>;
+
+// 'a..b', 'a..=b', 'a..', '..b', '..=b', '<n', '<=n', '>n' and '>=n'.  The bounds are 
+// normalized to be inclusive on both sides.
+fn parse_range(input : &mut Input) -> Result<Bounds, ParseError> {
+    fn bound(n : i64, offset : i64, input : &Input) -> Result<i64, ParseError> {
+        match n.checked_add(offset) {
+            Some(n) => Ok(n),
+            None => fail(input, "range pattern can never match"),
+        }
+    }
+
+    fn end(input : &mut Input, required : bool) -> Result<Option<i64>, ParseError> {
+        // '1.. => ...' is an open range followed by the rest of a match case
+        let rp = input.clone();
+        let inclusive = match punct(input, "=") {
+            Ok(_) if joined_punct(input, ">") => { input.restore(rp); false },
+            Ok(_) => true,
+            Err(_) => false,
+        };
+        let n = if required || inclusive {
+            fatal(parse_number(input), input, "range pattern must have an end")?
+        }
+        else {
+            match maybe(parse_number(input))? {
+                Some(n) => n,
+                None => return Ok(None),
+            }
+        };
+        if inclusive { Ok(Some(n)) } else { Ok(Some(bound(n, -1, input)?)) }
+    }
+
+    let comparisons : [(&str, Comparison); 4] = 
+        [ ("<=", |n, _| Ok((None, Some(n))))
+        , (">=", |n, _| Ok((Some(n), None)))
+        , ("<", |n, i| Ok((None, Some(bound(n, -1, i)?))))
+        , (">", |n, i| Ok((Some(bound(n, 1, i)?), None)))
+        ];
+
+    for (op, range) in comparisons {
+        match punct(input, op) {
+            Ok(_) => {
+                let n = fatal(parse_number(input), input, &format!("'{op}' pattern must have a number"))?;
+                return range(n, input);
+            },
+            Err(ParseError::Error) => { },
+            Err(e @ ParseError::Fatal(_)) => return Err(e),
+        }
+    }
+
+    if punct(input, "..").is_ok() {
+        return Ok((None, end(input, true)?));
+    }
+
+    let rp = input.clone();
+    let min = parse_number(input)?;
+    match punct(input, "..") {
+        Ok(_) => { },
+        Err(ParseError::Error) => { input.restore(rp); return Err(ParseError::Error); },
+        Err(e @ ParseError::Fatal(_)) => return Err(e),
+    }
+    let max = end(input, false)?;
+
+    match max {
+        Some(max) if max < min => fail(input, "range pattern can never match"),
+        _ => Ok((Some(min), max)),
+    }
+}
+
+// A '|' that separates alternatives rather than ending a '{| |}' or '[| |]' or starting a '||'.
+fn parse_or_bar(input : &mut Input) -> Result<(), ParseError> {
+    let rp = input.clone();
+    punct(input, "|")?;
+    if joined_punct(input, "|") || joined_punct(input, "}") || joined_punct(input, "]") {
+        input.restore(rp);
+        Err(ParseError::Error)
+    }
+    else {
+        Ok(())
+    }
+}
+
+// Alternatives are not allowed directly inside of a standard array pattern because '|' 
+// already separates the items from the rest pattern.  They can be grouped with '( )' there.
+fn parse_alternatives<T, F, O>(p : F, or : O, allow_or : bool, input : &mut Input) -> Result<T, ParseError> 
+    where F : Fn(&mut Input) -> Result<T, ParseError>
+        , O : Fn(Vec<T>, Span) -> T {
+
+    let start = start_position(input)?;
+    let first = p(input)?;
+
+    if !allow_or {
+        return Ok(first);
+    }
+
+    let mut alternatives = vec![first];
+
+    while maybe(parse_or_bar(input))?.is_some() {
+        alternatives.push(fatal(p(input), input, "| pattern must have an alternative after |")?);
+    }
+
+    if alternatives.len() == 1 {
+        Ok(alternatives.pop().unwrap())
+    }
+    else {
+        Ok(or(alternatives, input.span_from(start)))
+    }
+}
+
+// '(p)' is a group with just one pattern and '(p, q, ...)' is a tuple.
+fn parse_group<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, input : &mut Input) -> Result<Vec<T>, ParseError> {
+    punct(input, "(")?;
+    let mut patterns = vec![];
+    loop {
+        patterns.push(fatal(p(input), input, "parentheses must have a pattern")?);
+        match punct(input, ",") {
+            Ok(_) => continue,
+            Err(ParseError::Error) => break,
+            Err(e @ ParseError::Fatal(_)) => return Err(e),
+        }
+    }
+    fatal(punct(input, ")"), input, "parentheses must have an ending ')'")?;
+    Ok(patterns)
+}
+
+// 'p*', 'p+', 'p?', 'p{n}', 'p{m,n}', 'p{m,}' and 'p{,n}' following an array pattern item.  After a
+// constructor name a '{' is read as a record first (see is_record_start), so 'Foo{n}' and 'Foo { n }' are
+// both the record pattern that binds field n, while 'Foo{2}' and 'Foo{n + 1}' are quantified.  A count
+// that is only a variable needs the constructor in parens: '(Foo){n}'.
+fn parse_quantifier(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<Quantifier, ParseError> {
+    fn bound(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<Option<Box<Expr>>, ParseError> {
+        Ok(maybe(parse_expr(input))?.map(Box::new))
+    }
+
+    if punct(input, "*").is_ok() {
+        return Ok(Quantifier::ZeroOrMore);
+    }
+    if punct(input, "+").is_ok() {
+        return Ok(Quantifier::OneOrMore);
+    }
+    if punct(input, "?").is_ok() {
+        return Ok(Quantifier::ZeroOrOne);
+    }
+
+    punct(input, "{")?;
+    let min = bound(parse_expr, input)?;
+
+    match (min, punct(input, ",")) {
+        (Some(n), Err(ParseError::Error)) => {
+            fatal(punct(input, "}"), input, "quantifier must have ending }")?;
+            Ok(Quantifier::Exactly(n))
+        },
+        (None, Err(ParseError::Error)) => fail(input, "quantifier must have a count"),
+        (min, Ok(_)) => {
+            let max = bound(parse_expr, input)?;
+            if min.is_none() && max.is_none() {
+                return fail(input, "quantifier must have a minimum or a maximum");
+            }
+            fatal(punct(input, "}"), input, "quantifier must have ending }")?;
+            Ok(Quantifier::Range { min, max })
+        },
+        (_, Err(e @ ParseError::Fatal(_))) => Err(e),
+    }
+}
+
+// A reference to a top level 'pattern name = ...;' definition.
+pub fn parse_named_pattern(input : &mut Input) -> Result<String, ParseError> {
+    punct(input, "$")?;
+    fatal(parse_qualified_symbol(input), input, "$ must be followed by a pattern name")
+}
+
 pub fn parse_path_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
-    fn parse_number_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
-        into(input, parse_number, |n| PathPattern::Number(n))
+    path_pattern(parse_expr, true, input)
+}
+
+fn path_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, allow_or : bool, input : &mut Input) -> Result<PathPattern, ParseError> {
+    fn parse_range_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, parse_range, |(min, max)| PathPatternKind::Range { min, max })
+    }
+
+    fn parse_number_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, parse_number, |n| PathPatternKind::Number(n))
     }
     
-    fn parse_bool_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
-        into(input, parse_bool, |b| PathPattern::Bool(b))
+    fn parse_bool_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, parse_bool, |b| PathPatternKind::Bool(b))
     }
 
-    fn parse_var_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
-        into(input, parse_variable, |v| PathPattern::Variable(v))
+    fn parse_string_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, parse_string, PathPatternKind::String)
     }
 
-    fn parse_cons_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
-        into(input, |i| parse_constructor(|x| parse_path_pattern(parse_expr, x), i), |(name, params)| PathPattern::Cons{name, params})
+    fn parse_var_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, parse_variable, |v| PathPatternKind::Variable(v))
     }
 
-    fn parse_at_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
-        into(input, |i| parse_at(|x| parse_path_pattern(parse_expr, x), i), |(name, pattern)| PathPattern::At{name, pattern})
+    fn parse_cons_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, |i| parse_constructor(|x| parse_path_pattern(parse_expr, x), i), |(name, params)| PathPatternKind::Cons{name, params})
     }
 
-    fn parse_wildcard_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
+    fn parse_record_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into( input
+            , |i| parse_record( |x| parse_path_pattern(parse_expr, x)
+                              , |name, span| PathPattern { kind: PathPatternKind::Variable(name), span }
+                              , i )
+            , |(name, fields)| PathPatternKind::Record{name, fields} )
+    }
+
+    fn parse_named_pattern_ref(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, parse_named_pattern, PathPatternKind::Named)
+    }
+
+    fn parse_at_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, |i| parse_at(|x| parse_path_pattern(parse_expr, x), i), |(name, pattern)| PathPatternKind::At{name, pattern})
+    }
+
+    fn parse_wildcard_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
         punct(input, "_")?;
-        Ok(PathPattern::Wildcard)
+        Ok(PathPatternKind::Wildcard)
     }
 
-    fn parse_next_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
+    fn parse_next_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
         punct(input, "!")?;
-        into(input, |i| maybe(parse_number(i)), |number| PathPattern::Next(number))
+        into(input, |i| maybe(parse_number(i)), |number| PathPatternKind::Next(number))
     }
 
-    fn parse_and_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
+    fn parse_and_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
         punct(input, "&")?;
-        let name = fatal(parse_symbol(input), "& pattern must have a path name")?;
-        fatal(punct(input, ":"), "& pattern must have a ':'")?;
-        let output = fatal(parse_symbol(input), "& pattern must have an output")?;
-        Ok(PathPattern::And{ name, output })
+        let name = fatal(parse_symbol(input), input, "& pattern must have a path name")?;
+        fatal(punct(input, ":"), input, "& pattern must have a ':'")?;
+        let output = fatal(parse_symbol(input), input, "& pattern must have an output")?;
+        Ok(PathPatternKind::And{ name, output })
     }
 
-    fn parse_next_and_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
+    fn parse_next_and_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
         let rp = input.clone();
         punct(input, "!")?;
         let order = maybe(parse_number(input))?;
@@ -157,18 +391,28 @@ pub fn parse_path_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError
             Err(ParseError::Error) => { input.restore(rp); return Err(ParseError::Error); },
             Err(e @ ParseError::Fatal(_)) => return Err(e),
         }
-        let name = fatal(parse_symbol(input), "& pattern must have a path name")?;
-        fatal(punct(input, ":"), "& pattern must have a ':'")?;
-        let output = fatal(parse_symbol(input), "& pattern must have an output")?;
-        Ok(PathPattern::NextAnd{ order, name, output })
+        let name = fatal(parse_symbol(input), input, "& pattern must have a path name")?;
+        fatal(punct(input, ":"), input, "& pattern must have a ':'")?;
+        let output = fatal(parse_symbol(input), input, "& pattern must have an output")?;
+        Ok(PathPatternKind::NextAnd{ order, name, output })
     }
 
-    fn parse_path_standard_array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPattern, ParseError> {
-        into(input, |i| parse_standard_array(|x| parse_path_pattern(parse_expr, x), i), |array| PathPattern::StandardArray(array))
+    fn parse_path_standard_array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, |i| parse_standard_array(|x| path_pattern(parse_expr, false, x), i), |array| PathPatternKind::StandardArray(array))
     }
 
-    let ps = [ parse_number_pattern
+    fn parse_group_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<PathPatternKind, ParseError> {
+        into(input, |i| parse_group(|x| parse_path_pattern(parse_expr, x), i), |mut patterns| match patterns.len() {
+            1 => patterns.pop().unwrap().kind,
+            _ => PathPatternKind::Tuple(patterns),
+        })
+    }
+
+    let ps = [ parse_range_pattern      // Needs to come before parse_number_pattern
+             , parse_number_pattern
              , parse_bool_pattern
+             , parse_string_pattern
+             , parse_record_pattern     // Needs to come before parse_cons_pattern
              , parse_cons_pattern
              , parse_at_pattern
              , parse_wildcard_pattern
@@ -176,188 +420,306 @@ pub fn parse_path_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError
              , parse_next_and_pattern
              , parse_next_pattern
              , parse_path_standard_array_pattern
+             , parse_named_pattern_ref
+             , parse_group_pattern
              , parse_var_pattern// This should probably be last to avoid eating up keywords, etc
              ];
 
-    let mut pattern = None;
-    
-    for p in ps {
-        match p(parse_expr, input) {
-            Ok(e) => { pattern = Some(e); break; },
-            e @ Err(ParseError::Fatal(_)) => return e,
-            _ => { },
+    let primary = |input : &mut Input| {
+        let start = start_position(input)?;
+
+        for p in ps {
+            match p(parse_expr, input) {
+                Ok(kind) => return Ok(PathPattern { kind, span: input.span_from(start) }),
+                Err(e @ ParseError::Fatal(_)) => return Err(e),
+                _ => { },
+            }
         }
-    }
 
-    let pattern = match pattern {
-        Some(pattern) => pattern, 
-        None => return Err(ParseError::Error),
+        Err(ParseError::Error)
     };
 
+    let start = start_position(input)?;
+
+    let pattern = parse_alternatives( primary
+                                    , |alternatives, span| PathPattern { kind: PathPatternKind::Or(alternatives), span }
+                                    , allow_or
+                                    , input )?;
+
     match keyword(input, "if") {
         Ok(_) => { },
         Err(ParseError::Error) => return Ok(pattern),
         Err(e @ ParseError::Fatal(_)) => return Err(e),
     }
 
-    let predicate = Box::new(fatal(parse_expr(input), "pattern must have expression after if")?);
+    let predicate = Box::new(fatal(parse_expr(input), input, "pattern must have expression after if")?);
 
-    Ok(PathPattern::If { pattern: Box::new(pattern), predicate })
+    Ok(PathPattern { kind: PathPatternKind::If { pattern: Box::new(pattern), predicate }, span: input.span_from(start) })
 }
 
 pub fn parse_standard_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
-    fn parse_number_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
-        into(input, parse_number, |n| StandardPattern::Number(n))
+    standard_pattern(parse_expr, true, input)
+}
+
+pub fn parse_standard_pattern_without_or(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
+    standard_pattern(parse_expr, false, input)
+}
+
+fn standard_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, allow_or : bool, input : &mut Input) -> Result<StandardPattern, ParseError> {
+    fn parse_range_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, parse_range, |(min, max)| StandardPatternKind::Range { min, max })
+    }
+
+    fn parse_number_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, parse_number, |n| StandardPatternKind::Number(n))
     }
     
-    fn parse_bool_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
-        into(input, parse_bool, |b| StandardPattern::Bool(b))
+    fn parse_bool_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, parse_bool, |b| StandardPatternKind::Bool(b))
     }
 
-    fn parse_var_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
-        into(input, parse_variable, |v| StandardPattern::Variable(v))
+    fn parse_string_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, parse_string, StandardPatternKind::String)
     }
 
-    fn parse_cons_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
-        into(input, |i| parse_constructor(|x| parse_standard_pattern(parse_expr, x), i), |(name, params)| StandardPattern::Cons{name, params})
+    fn parse_var_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, parse_variable, |v| StandardPatternKind::Variable(v))
     }
 
-    fn parse_at_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
-        into(input, |i| parse_at(|x| parse_standard_pattern(parse_expr, x), i), |(name, pattern)| StandardPattern::At{name, pattern})
+    fn parse_cons_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, |i| parse_constructor(|x| parse_standard_pattern(parse_expr, x), i), |(name, params)| StandardPatternKind::Cons{name, params})
     }
 
-    fn parse_wildcard_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
+    fn parse_record_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into( input
+            , |i| parse_record( |x| parse_standard_pattern(parse_expr, x)
+                              , |name, span| StandardPattern { kind: StandardPatternKind::Variable(name), span }
+                              , i )
+            , |(name, fields)| StandardPatternKind::Record{name, fields} )
+    }
+
+    fn parse_at_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, |i| parse_at(|x| parse_standard_pattern(parse_expr, x), i), |(name, pattern)| StandardPatternKind::At{name, pattern})
+    }
+
+    fn parse_wildcard_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
         punct(input, "_")?;
-        Ok(StandardPattern::Wildcard)
+        Ok(StandardPatternKind::Wildcard)
+    }
+
+    fn parse_standard_standard_array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, |i| parse_standard_array(|x| standard_pattern(parse_expr, false, x), i), |array| StandardPatternKind::StandardArray(array))
     }
 
-    fn parse_standard_standard_array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPattern, ParseError> {
-        into(input, |i| parse_standard_array(|x| parse_standard_pattern(parse_expr, x), i), |array| StandardPattern::StandardArray(array))
+    fn parse_group_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<StandardPatternKind, ParseError> {
+        into(input, |i| parse_group(|x| parse_standard_pattern(parse_expr, x), i), |mut patterns| match patterns.len() {
+            1 => patterns.pop().unwrap().kind,
+            _ => StandardPatternKind::Tuple(patterns),
+        })
     }
 
-    let ps = [ parse_number_pattern
+    let ps = [ parse_range_pattern      // Needs to come before parse_number_pattern
+             , parse_number_pattern
              , parse_bool_pattern
+             , parse_string_pattern
+             , parse_record_pattern     // Needs to come before parse_cons_pattern
              , parse_cons_pattern
              , parse_at_pattern
              , parse_wildcard_pattern
              , parse_standard_standard_array_pattern
+             , parse_group_pattern
              , parse_var_pattern// This should probably be last to avoid eating up keywords, etc
              ];
 
-    let mut pattern = None;
-    
-    for p in ps {
-        match p(parse_expr, input) {
-            Ok(e) => { pattern = Some(e); break; },
-            e @ Err(ParseError::Fatal(_)) => return e,
-            _ => { },
+    let primary = |input : &mut Input| {
+        let start = start_position(input)?;
+
+        for p in ps {
+            match p(parse_expr, input) {
+                Ok(kind) => return Ok(StandardPattern { kind, span: input.span_from(start) }),
+                Err(e @ ParseError::Fatal(_)) => return Err(e),
+                _ => { },
+            }
         }
-    }
 
-    let pattern = match pattern {
-        Some(pattern) => pattern, 
-        None => return Err(ParseError::Error),
+        Err(ParseError::Error)
     };
 
+    let start = start_position(input)?;
+
+    let pattern = parse_alternatives( primary
+                                    , |alternatives, span| StandardPattern { kind: StandardPatternKind::Or(alternatives), span }
+                                    , allow_or
+                                    , input )?;
+
     match keyword(input, "if") {
         Ok(_) => { },
         Err(ParseError::Error) => return Ok(pattern),
         Err(e @ ParseError::Fatal(_)) => return Err(e),
     }
 
-    let predicate = Box::new(fatal(parse_expr(input), "pattern must have expression after if")?);
+    let predicate = Box::new(fatal(parse_expr(input), input, "pattern must have expression after if")?);
 
-    Ok(StandardPattern::If { pattern: Box::new(pattern), predicate })
+    Ok(StandardPattern { kind: StandardPatternKind::If { pattern: Box::new(pattern), predicate }, span: input.span_from(start) })
 }
 
-pub fn parse_array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> { 
-    fn parse_number_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
-        into(input, parse_number, |n| ArrayPattern::Number(n))
+pub fn parse_array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
+    array_pattern(parse_expr, true, input)
+}
+
+fn array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, allow_or : bool, input : &mut Input) -> Result<ArrayPattern, ParseError> { 
+    fn parse_range_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, parse_range, |(min, max)| ArrayPatternKind::Range { min, max })
+    }
+
+    fn parse_number_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, parse_number, |n| ArrayPatternKind::Number(n))
     }
     
-    fn parse_bool_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
-        into(input, parse_bool, |b| ArrayPattern::Bool(b))
+    fn parse_bool_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, parse_bool, |b| ArrayPatternKind::Bool(b))
+    }
+
+    fn parse_string_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, parse_string, ArrayPatternKind::String)
+    }
+
+    fn parse_var_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, parse_variable, |v| ArrayPatternKind::Variable(v))
     }
 
-    fn parse_var_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
-        into(input, parse_variable, |v| ArrayPattern::Variable(v))
+    fn parse_cons_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, |i| parse_constructor(|x| parse_array_pattern(parse_expr, x), i), |(name, params)| ArrayPatternKind::Cons{name, params})
     }
 
-    fn parse_cons_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
-        into(input, |i| parse_constructor(|x| parse_array_pattern(parse_expr, x), i), |(name, params)| ArrayPattern::Cons{name, params})
+    fn parse_record_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into( input
+            , |i| parse_record( |x| parse_array_pattern(parse_expr, x)
+                              , |name, span| ArrayPattern { kind: ArrayPatternKind::Variable(name), span }
+                              , i )
+            , |(name, fields)| ArrayPatternKind::Record{name, fields} )
     }
 
-    fn parse_at_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
-        into(input, |i| parse_at(|x| parse_array_pattern(parse_expr, x), i), |(name, pattern)| ArrayPattern::At{name, pattern})
+    fn parse_named_pattern_ref(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, parse_named_pattern, ArrayPatternKind::Named)
     }
 
-    fn parse_wildcard_zero_or_more_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
+    fn parse_at_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, |i| parse_at(|x| parse_array_pattern(parse_expr, x), i), |(name, pattern)| ArrayPatternKind::At{name, pattern})
+    }
+
+    fn parse_wildcard_zero_or_more_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
         punct(input, "_*")?;
-        Ok(ArrayPattern::WildcardZeroOrMore)
+        Ok(ArrayPatternKind::WildcardZeroOrMore)
     }
 
-    fn parse_wildcard_n_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
+    fn parse_wildcard_n_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        let rp = input.clone();
         punct(input, "_{")?;
-        let expr = Box::new(fatal(parse_expr(input), "wildcard N pattern must have expression")?);
-        fatal(punct(input, "}"), "wildcard N pattern must have ending }")?;
-        Ok(ArrayPattern::WildcardN(expr))
+        let expr = Box::new(fatal(parse_expr(input), input, "wildcard N pattern must have expression")?);
+        match punct(input, "}") {
+            Ok(_) => Ok(ArrayPatternKind::WildcardN(expr)),
+            // '_{m,n}' is a wildcard with a range quantifier
+            Err(ParseError::Error) => { input.restore(rp); Err(ParseError::Error) },
+            Err(e @ ParseError::Fatal(_)) => Err(e),
+        }
     }
 
-    fn parse_wildcard_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
+    fn parse_wildcard_pattern(_ : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
         punct(input, "_")?;
-        Ok(ArrayPattern::Wildcard)
+        Ok(ArrayPatternKind::Wildcard)
     }
 
-    fn parse_array_standard_array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPattern, ParseError> {
-        into(input, |i| parse_standard_array(|x| parse_array_pattern(parse_expr, x), i), |array| ArrayPattern::StandardArray(array))
+    fn parse_array_standard_array_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, |i| parse_standard_array(|x| array_pattern(parse_expr, false, x), i), |array| ArrayPatternKind::StandardArray(array))
     }
 
-    let ps = [ parse_number_pattern
+    fn parse_group_pattern(parse_expr : fn(&mut Input) -> Result<Expr, ParseError>, input : &mut Input) -> Result<ArrayPatternKind, ParseError> {
+        into(input, |i| parse_group(|x| parse_array_pattern(parse_expr, x), i), |mut patterns| match patterns.len() {
+            1 => patterns.pop().unwrap().kind,
+            _ => ArrayPatternKind::Tuple(patterns),
+        })
+    }
+
+    let ps = [ parse_range_pattern      // Needs to come before parse_number_pattern
+             , parse_number_pattern
              , parse_bool_pattern
+             , parse_string_pattern
+             , parse_record_pattern     // Needs to come before parse_cons_pattern
              , parse_cons_pattern
              , parse_at_pattern
              , parse_wildcard_zero_or_more_pattern // Needs order: _*, _{Expr<N>}, _
              , parse_wildcard_n_pattern
              , parse_wildcard_pattern
              , parse_array_standard_array_pattern
+             , parse_named_pattern_ref
+             , parse_group_pattern
              , parse_var_pattern// This should probably be last to avoid eating up keywords, etc
              ];
 
-    let mut pattern = None;
-    
-    for p in ps {
-        match p(parse_expr, input) {
-            Ok(e) => { pattern = Some(e); break; },
-            e @ Err(ParseError::Fatal(_)) => return e,
-            _ => { },
+    let primary = |input : &mut Input| {
+        let start = start_position(input)?;
+
+        let mut pattern = None;
+
+        for p in ps {
+            match p(parse_expr, input) {
+                Ok(kind) => { pattern = Some(ArrayPattern { kind, span: input.span_from(start) }); break; },
+                Err(e @ ParseError::Fatal(_)) => return Err(e),
+                _ => { },
+            }
         }
-    }
 
-    let pattern = match pattern {
-        Some(pattern) => pattern, 
-        None => return Err(ParseError::Error),
+        let pattern = match pattern {
+            Some(pattern) => pattern,
+            None => return Err(ParseError::Error),
+        };
+
+        match parse_quantifier(parse_expr, input) {
+            Ok(quantifier) => Ok(ArrayPattern { kind: ArrayPatternKind::Quantified { pattern: Box::new(pattern), quantifier }, span: input.span_from(start) }),
+            Err(ParseError::Error) => Ok(pattern),
+            Err(e @ ParseError::Fatal(_)) => Err(e),
+        }
     };
 
+    let start = start_position(input)?;
+
+    let pattern = parse_alternatives( primary
+                                    , |alternatives, span| ArrayPattern { kind: ArrayPatternKind::Or(alternatives), span }
+                                    , allow_or
+                                    , input )?;
+
     match keyword(input, "if") {
         Ok(_) => { },
         Err(ParseError::Error) => return Ok(pattern),
         Err(e @ ParseError::Fatal(_)) => return Err(e),
     }
 
-    let predicate = Box::new(fatal(parse_expr(input), "pattern must have expression after if")?);
+    let predicate = Box::new(fatal(parse_expr(input), input, "pattern must have expression after if")?);
 
-    Ok(ArrayPattern::If { pattern: Box::new(pattern), predicate })
+    Ok(ArrayPattern { kind: ArrayPatternKind::If { pattern: Box::new(pattern), predicate }, span: input.span_from(start) })
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ast::ExprKind;
+
+    fn true_expr(input : &mut Input) -> Result<Expr, ParseError> {
+        Ok(Expr { kind: ExprKind::Bool(true), span: input.span_from(input.position()) })
+    }
+
+    fn digit_expr(input : &mut Input) -> Result<Expr, ParseError> {
+        let start = start_position(input)?;
+        parse_number(input)?;
+        Ok(Expr { kind: ExprKind::Number(0), span: input.span_from(start) })
+    }
 
     #[test]
     fn array_pattern_var_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("a");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::Variable(_) ) );
+        assert!( matches!( result.kind, ArrayPatternKind::Variable(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -366,7 +728,7 @@ mod test {
     fn array_pattern_number_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("100");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::Number(_) ) );
+        assert!( matches!( result.kind, ArrayPatternKind::Number(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -375,16 +737,30 @@ mod test {
     fn array_pattern_bool_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("true");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::Bool(_) ) );
+        assert!( matches!( result.kind, ArrayPatternKind::Bool(_) ) );
         // TODO add more details 
         Ok(())
     }
 
+    #[test]
+    fn array_pattern_record_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Person { name : \"x\", age }");
+        let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
+        let fields = match result.kind {
+            ArrayPatternKind::Record { name, fields } if name == "Person" => fields,
+            _ => panic!("expected record pattern"),
+        };
+        assert_eq!( fields.len(), 2 );
+        assert!( matches!( &fields[0], (f, p) if f == "name" && matches!( &p.kind, ArrayPatternKind::String(s) if s == "x" ) ) );
+        assert!( matches!( &fields[1], (f, p) if f == "age" && matches!( &p.kind, ArrayPatternKind::Variable(v) if v == "age" ) ) );
+        Ok(())
+    }
+
     #[test]
     fn array_pattern_cons_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("Cons(A, A)");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::Cons { .. } ) );
+        assert!( matches!( result.kind, ArrayPatternKind::Cons { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -393,7 +769,7 @@ mod test {
     fn array_pattern_at_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("x @ Cons(A, A)");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::At { .. } ) );
+        assert!( matches!( result.kind, ArrayPatternKind::At { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -402,7 +778,7 @@ mod test {
     fn array_pattern_at_should_parse_recursive() -> Result<(), ParseError> {
         let mut input = Input::new("x @ y @ Cons(A, A)");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::At { .. } ) );
+        assert!( matches!( result.kind, ArrayPatternKind::At { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -411,7 +787,7 @@ mod test {
     fn array_pattern_wildcard_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("_");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::Wildcard ) );
+        assert!( matches!( result.kind, ArrayPatternKind::Wildcard ) );
         // TODO add more details 
         Ok(())
     }
@@ -420,7 +796,7 @@ mod test {
     fn array_pattern_wildcard_zero_or_more_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("_*");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::WildcardZeroOrMore ) );
+        assert!( matches!( result.kind, ArrayPatternKind::WildcardZeroOrMore ) );
         // TODO add more details 
         Ok(())
     }
@@ -428,8 +804,8 @@ mod test {
     #[test]
     fn array_pattern_wildcard_n_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("_{N}");
-        let result = parse_array_pattern(|i| { i.next()?; Ok(Expr::Number(8)) }, &mut input)?;
-        assert!( matches!( result, ArrayPattern::WildcardN(_)) );
+        let result = parse_array_pattern(|i| { let start = i.position(); i.next().ok_or(ParseError::Error)?; Ok(Expr { kind: ExprKind::Number(8), span: i.span_from(start) }) }, &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::WildcardN(_)) );
         // TODO add more details 
         Ok(())
     }
@@ -437,8 +813,8 @@ mod test {
     #[test]
     fn array_pattern_if_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("x if true");
-        let result = parse_array_pattern(|_| Ok(Expr::Bool(true)), &mut input)?;
-        assert!( matches!( result, ArrayPattern::If {..} ) );
+        let result = parse_array_pattern(true_expr, &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::If {..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -447,7 +823,7 @@ mod test {
     fn array_pattern_standard_array_should_parse_empty() -> Result<(), ParseError> {
         let mut input = Input::new("[]");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, ArrayPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -456,7 +832,7 @@ mod test {
     fn array_pattern_standard_array_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("[x, y, z]");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, ArrayPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -465,7 +841,7 @@ mod test {
     fn array_pattern_standard_array_should_parse_with_rest() -> Result<(), ParseError> {
         let mut input = Input::new("[x, y, z | r]");
         let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, ArrayPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, ArrayPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -474,7 +850,7 @@ mod test {
     fn standard_pattern_var_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("a");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::Variable(_) ) );
+        assert!( matches!( result.kind, StandardPatternKind::Variable(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -483,7 +859,7 @@ mod test {
     fn standard_pattern_number_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("100");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::Number(_) ) );
+        assert!( matches!( result.kind, StandardPatternKind::Number(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -492,16 +868,30 @@ mod test {
     fn standard_pattern_bool_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("true");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::Bool(_) ) );
+        assert!( matches!( result.kind, StandardPatternKind::Bool(_) ) );
         // TODO add more details 
         Ok(())
     }
 
+    #[test]
+    fn standard_pattern_record_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Person { name : \"x\", age }");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+        let fields = match result.kind {
+            StandardPatternKind::Record { name, fields } if name == "Person" => fields,
+            _ => panic!("expected record pattern"),
+        };
+        assert_eq!( fields.len(), 2 );
+        assert!( matches!( &fields[0], (f, p) if f == "name" && matches!( &p.kind, StandardPatternKind::String(s) if s == "x" ) ) );
+        assert!( matches!( &fields[1], (f, p) if f == "age" && matches!( &p.kind, StandardPatternKind::Variable(v) if v == "age" ) ) );
+        Ok(())
+    }
+
     #[test]
     fn standard_pattern_cons_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("Cons(A, A)");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::Cons { .. } ) );
+        assert!( matches!( result.kind, StandardPatternKind::Cons { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -510,7 +900,7 @@ mod test {
     fn standard_pattern_at_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("x @ Cons(A, A)");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::At { .. } ) );
+        assert!( matches!( result.kind, StandardPatternKind::At { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -519,7 +909,7 @@ mod test {
     fn standard_pattern_at_should_parse_recursive() -> Result<(), ParseError> {
         let mut input = Input::new("x @ y @ Cons(A, A)");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::At { .. } ) );
+        assert!( matches!( result.kind, StandardPatternKind::At { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -528,7 +918,7 @@ mod test {
     fn standard_pattern_wildcard_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("_");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::Wildcard ) );
+        assert!( matches!( result.kind, StandardPatternKind::Wildcard ) );
         // TODO add more details 
         Ok(())
     }
@@ -536,8 +926,8 @@ mod test {
     #[test]
     fn standard_pattern_if_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("x if true");
-        let result = parse_standard_pattern(|_| Ok(Expr::Bool(true)), &mut input)?;
-        assert!( matches!( result, StandardPattern::If {..} ) );
+        let result = parse_standard_pattern(true_expr, &mut input)?;
+        assert!( matches!( result.kind, StandardPatternKind::If {..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -546,7 +936,7 @@ mod test {
     fn standard_pattern_standard_array_should_parse_empty() -> Result<(), ParseError> {
         let mut input = Input::new("[]");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, StandardPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -555,7 +945,7 @@ mod test {
     fn standard_pattern_standard_array_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("[x, y, z]");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, StandardPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -564,7 +954,7 @@ mod test {
     fn standard_pattern_standard_array_should_parse_with_rest() -> Result<(), ParseError> {
         let mut input = Input::new("[x, y, z | r]");
         let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, StandardPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, StandardPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -573,7 +963,7 @@ mod test {
     fn path_pattern_var_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("a");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::Variable(_) ) );
+        assert!( matches!( result.kind, PathPatternKind::Variable(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -582,7 +972,7 @@ mod test {
     fn path_pattern_number_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("100");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::Number(_) ) );
+        assert!( matches!( result.kind, PathPatternKind::Number(_) ) );
         // TODO add more details 
         Ok(())
     }
@@ -591,16 +981,30 @@ mod test {
     fn path_pattern_bool_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("true");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::Bool(_) ) );
+        assert!( matches!( result.kind, PathPatternKind::Bool(_) ) );
         // TODO add more details 
         Ok(())
     }
 
+    #[test]
+    fn path_pattern_record_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Person { name : \"x\", age }");
+        let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
+        let fields = match result.kind {
+            PathPatternKind::Record { name, fields } if name == "Person" => fields,
+            _ => panic!("expected record pattern"),
+        };
+        assert_eq!( fields.len(), 2 );
+        assert!( matches!( &fields[0], (f, p) if f == "name" && matches!( &p.kind, PathPatternKind::String(s) if s == "x" ) ) );
+        assert!( matches!( &fields[1], (f, p) if f == "age" && matches!( &p.kind, PathPatternKind::Variable(v) if v == "age" ) ) );
+        Ok(())
+    }
+
     #[test]
     fn path_pattern_cons_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("Cons(A, A)");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::Cons { .. } ) );
+        assert!( matches!( result.kind, PathPatternKind::Cons { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -609,7 +1013,7 @@ mod test {
     fn path_pattern_at_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("x @ Cons(A, A)");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::At { .. } ) );
+        assert!( matches!( result.kind, PathPatternKind::At { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -618,7 +1022,7 @@ mod test {
     fn path_pattern_at_should_parse_recursive() -> Result<(), ParseError> {
         let mut input = Input::new("x @ y @ Cons(A, A)");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::At { .. } ) );
+        assert!( matches!( result.kind, PathPatternKind::At { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -627,7 +1031,7 @@ mod test {
     fn path_pattern_wildcard_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("_");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::Wildcard ) );
+        assert!( matches!( result.kind, PathPatternKind::Wildcard ) );
         // TODO add more details 
         Ok(())
     }
@@ -636,7 +1040,7 @@ mod test {
     fn path_pattern_next_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("!");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::Next(None)) );
+        assert!( matches!( result.kind, PathPatternKind::Next(None)) );
         Ok(())
     }
 
@@ -644,7 +1048,7 @@ mod test {
     fn path_pattern_next_with_order_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("!2");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::Next(Some(2)) ) );
+        assert!( matches!( result.kind, PathPatternKind::Next(Some(2)) ) );
         Ok(())
     }
 
@@ -652,7 +1056,7 @@ mod test {
     fn path_pattern_and_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("&path:output");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::And { .. } ) );
+        assert!( matches!( result.kind, PathPatternKind::And { .. } ) );
         // TODO add more details 
         Ok(())
     }
@@ -661,7 +1065,7 @@ mod test {
     fn path_pattern_next_and_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("!&path:output");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::NextAnd {..} ) );
+        assert!( matches!( result.kind, PathPatternKind::NextAnd {..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -670,7 +1074,7 @@ mod test {
     fn path_pattern_next_and_with_order_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("!2&path:output");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::NextAnd {..} ) );
+        assert!( matches!( result.kind, PathPatternKind::NextAnd {..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -678,8 +1082,8 @@ mod test {
     #[test]
     fn path_pattern_if_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("x if true");
-        let result = parse_path_pattern(|_| Ok(Expr::Bool(true)), &mut input)?;
-        assert!( matches!( result, PathPattern::If {..} ) );
+        let result = parse_path_pattern(true_expr, &mut input)?;
+        assert!( matches!( result.kind, PathPatternKind::If {..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -688,7 +1092,7 @@ mod test {
     fn path_pattern_standard_array_should_parse_empty() -> Result<(), ParseError> {
         let mut input = Input::new("[]");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, PathPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -697,7 +1101,7 @@ mod test {
     fn path_pattern_standard_array_should_parse() -> Result<(), ParseError> {
         let mut input = Input::new("[x, y, z, !]");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, PathPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
@@ -706,8 +1110,280 @@ mod test {
     fn path_pattern_standard_array_should_parse_with_rest() -> Result<(), ParseError> {
         let mut input = Input::new("[x, y, z, ! | r]");
         let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
-        assert!( matches!( result, PathPattern::StandardArray{..} ) );
+        assert!( matches!( result.kind, PathPatternKind::StandardArray{..} ) );
         // TODO add more details 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn array_pattern_string_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new(r#""label""#);
+        let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::String(s) if s == "label" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn standard_pattern_string_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new(r#"Name("label", x)"#);
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+        let params = match result.kind {
+            StandardPatternKind::Cons { params, .. } => params,
+            _ => panic!("expected cons pattern"),
+        };
+        assert!( matches!( &params[0].kind, StandardPatternKind::String(s) if s == "label" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn path_pattern_string_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new(r#""label""#);
+        let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, PathPatternKind::String(s) if s == "label" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn standard_pattern_or_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Red | Orange | Yellow(x)");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+        let alternatives = match result.kind {
+            StandardPatternKind::Or(alternatives) => alternatives,
+            _ => panic!("expected or pattern"),
+        };
+        assert_eq!( alternatives.len(), 3 );
+        assert!( matches!( &alternatives[2].kind, StandardPatternKind::Cons { name, .. } if name == "Yellow" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn standard_pattern_or_should_apply_guard_to_every_alternative() -> Result<(), ParseError> {
+        let mut input = Input::new("A(x) | B(x) if true");
+        let result = parse_standard_pattern(true_expr, &mut input)?;
+        assert!( matches!( result.kind, StandardPatternKind::If { pattern, .. } if matches!( pattern.kind, StandardPatternKind::Or(_) ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn standard_pattern_standard_array_rest_should_not_parse_as_or() -> Result<(), ParseError> {
+        let mut input = Input::new("[x | r]");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, StandardPatternKind::StandardArray(StandardArrayPattern::Array { items, rest: Some(_) }) if items.len() == 1 ) );
+        Ok(())
+    }
+
+    #[test]
+    fn standard_pattern_standard_array_should_parse_grouped_or() -> Result<(), ParseError> {
+        let mut input = Input::new("[(A | B), x | r]");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+        let items = match result.kind {
+            StandardPatternKind::StandardArray(StandardArrayPattern::Array { items, rest: Some(_) }) => items,
+            _ => panic!("expected standard array pattern with rest"),
+        };
+        assert!( matches!( &items[0].kind, StandardPatternKind::Or(alternatives) if alternatives.len() == 2 ) );
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_or_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Cons(x, _) | Nil");
+        let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::Or(alternatives) if alternatives.len() == 2 ) );
+        Ok(())
+    }
+
+    #[test]
+    fn path_pattern_or_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Node(!, !) | Leaf(!)");
+        let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, PathPatternKind::Or(alternatives) if alternatives.len() == 2 ) );
+        Ok(())
+    }
+
+    #[test]
+    fn path_pattern_or_should_not_eat_series_end() -> Result<(), ParseError> {
+        let mut input = Input::new("a |}");
+        let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, PathPatternKind::Variable(_) ) );
+        punct(&mut input, "|}")?;
+        Ok(())
+    }
+
+    #[test]
+    fn or_pattern_should_require_alternative() {
+        let mut input = Input::new("A | =>");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(_)) ) );
+    }
+
+    #[test]
+    fn array_pattern_quantifiers_should_parse() -> Result<(), ParseError> {
+        for (text, expected) in [("x*", "*"), ("Cons(a, _)+", "+"), ("[a, b]?", "?")] {
+            let mut input = Input::new(text);
+            let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
+            let quantifier = match result.kind {
+                ArrayPatternKind::Quantified { quantifier, .. } => quantifier,
+                _ => panic!("expected quantified pattern for {text}"),
+            };
+            match (quantifier, expected) {
+                (Quantifier::ZeroOrMore, "*") | (Quantifier::OneOrMore, "+") | (Quantifier::ZeroOrOne, "?") => { },
+                (q, _) => panic!("unexpected quantifier {:?} for {text}", q),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_range_quantifier_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Cons(x, _){1,3}");
+        let result = parse_array_pattern(digit_expr, &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::Quantified { pattern, quantifier: Quantifier::Range { min: Some(_), max: Some(_) } }
+            if matches!( pattern.kind, ArrayPatternKind::Cons { .. } ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_open_range_quantifier_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("_{2,}");
+        let result = parse_array_pattern(digit_expr, &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::Quantified { pattern, quantifier: Quantifier::Range { min: Some(_), max: None } }
+            if matches!( pattern.kind, ArrayPatternKind::Wildcard ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_exact_quantifier_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("x{N}");
+        let result = parse_array_pattern(|i| { let start = i.position(); i.next().ok_or(ParseError::Error)?; Ok(Expr { kind: ExprKind::Number(8), span: i.span_from(start) }) }, &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::Quantified { quantifier: Quantifier::Exactly(_), .. } ) );
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_cons_followed_by_field_name_should_parse_as_record() -> Result<(), ParseError> {
+        for text in ["Foo{n}", "Foo { n }"] {
+            let mut input = Input::new(text);
+            let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
+            assert!( matches!( &result.kind, ArrayPatternKind::Record { name, fields }
+                if name == "Foo" && fields.len() == 1 && fields[0].0 == "n" && matches!( &fields[0].1.kind, ArrayPatternKind::Variable(n) if n == "n" ) ), "{text}" );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_cons_followed_by_count_should_parse_as_quantifier() -> Result<(), ParseError> {
+        for text in ["Foo{2}", "(Foo){n}"] {
+            let mut input = Input::new(text);
+            let result = parse_array_pattern(|i| { let start = i.position(); i.next().ok_or(ParseError::Error)?; Ok(Expr { kind: ExprKind::Number(2), span: i.span_from(start) }) }, &mut input)?;
+            assert!( matches!( result.kind, ArrayPatternKind::Quantified { quantifier: Quantifier::Exactly(_), .. } ), "{text}" );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_quantifier_should_bind_tighter_than_or() -> Result<(), ParseError> {
+        let mut input = Input::new("a | b*");
+        let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::Or(alternatives)
+            if matches!( alternatives[1].kind, ArrayPatternKind::Quantified { .. } ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_empty_quantifier_should_fail() {
+        let mut input = Input::new("x{,}");
+        let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(_)) ) );
+    }
+
+    #[test]
+    fn range_patterns_should_parse_to_inclusive_bounds() -> Result<(), ParseError> {
+        let cases = [ ("1..10", Some(1), Some(9))
+                    , ("1..=10", Some(1), Some(10))
+                    , ("-5..", Some(-5), None)
+                    , ("..0", None, Some(-1))
+                    , ("..=0", None, Some(0))
+                    , ("< 0", None, Some(-1))
+                    , ("<= 0", None, Some(0))
+                    , ("> 0", Some(1), None)
+                    , (">= 0", Some(0), None)
+                    ];
+        for (text, expected_min, expected_max) in cases {
+            let mut input = Input::new(text);
+            let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+            assert!( matches!( result.kind, StandardPatternKind::Range { min, max } if min == expected_min && max == expected_max ), "{text}" );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn number_pattern_should_still_parse_without_range() -> Result<(), ParseError> {
+        let mut input = Input::new("Pair(1, 2)");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, StandardPatternKind::Cons { params, .. } if matches!( params[0].kind, StandardPatternKind::Number(1) ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn open_range_pattern_should_not_eat_match_arrow() -> Result<(), ParseError> {
+        let mut input = Input::new("1.. => x");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, StandardPatternKind::Range { min: Some(1), max: None } ) );
+        punct(&mut input, "=>")?;
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_range_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Cons(0..10, _)");
+        let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::Cons { params, .. } if matches!( params[0].kind, ArrayPatternKind::Range { min: Some(0), max: Some(9) } ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn path_pattern_comparison_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("Leaf(> 0)");
+        let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, PathPatternKind::Cons { params, .. } if matches!( params[0].kind, PathPatternKind::Range { min: Some(1), max: None } ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn empty_range_pattern_should_fail() {
+        let mut input = Input::new("5..5");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(_)) ) );
+    }
+
+    #[test]
+    fn standard_pattern_tuple_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("(a, Cons(b, _), (1))");
+        let result = parse_standard_pattern(|_| Err(ParseError::Error), &mut input)?;
+        let items = match result.kind {
+            StandardPatternKind::Tuple(items) => items,
+            _ => panic!("expected tuple pattern"),
+        };
+        assert_eq!( items.len(), 3 );
+        assert!( matches!( items[2].kind, StandardPatternKind::Number(1) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn array_pattern_tuple_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("(x, _)*");
+        let result = parse_array_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, ArrayPatternKind::Quantified { pattern, .. } 
+            if matches!( &pattern.kind, ArrayPatternKind::Tuple(items) if items.len() == 2 ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn path_pattern_tuple_should_parse() -> Result<(), ParseError> {
+        let mut input = Input::new("(!, a) | (b, !)");
+        let result = parse_path_pattern(|_| Err(ParseError::Error), &mut input)?;
+        assert!( matches!( result.kind, PathPatternKind::Or(alternatives) 
+            if alternatives.iter().all(|p| matches!( &p.kind, PathPatternKind::Tuple(items) if items.len() == 2 )) ) );
+        Ok(())
+    }
+}
@@ -0,0 +1,967 @@
+use crate::ast::{ Ast
+                , Comment
+                , Position
+                , NamedPattern
+                , ConsDef
+                , FunParam
+                , Expr
+                , ExprKind
+                , Type
+                , TypeKind
+                , StandardArrayPattern
+                , StandardPattern
+                , StandardPatternKind
+                , ArrayPattern
+                , ArrayPatternKind
+                , PathPattern
+                , PathPatternKind
+                , Quantifier
+                };
+
+// (builtin function, operator, precedence) for the operators that the parser desugars
+// into calls.  '!=', '&&' and '||' desugar into other forms and are handled separately.
+const OPERATORS : [(&str, &str, usize); 11] =
+    [ ("xor", "^", 3)
+    , ("eq", "==", 4)
+    , ("lte", "<=", 5)
+    , ("gte", ">=", 5)
+    , ("lt", "<", 5)
+    , ("gt", ">", 5)
+    , ("add", "+", 6)
+    , ("sub", "-", 6)
+    , ("mul", "*", 7)
+    , ("div", "/", 7)
+    , ("rem", "%", 7)
+    ];
+
+const UNARY : usize = 8;
+const ATOM : usize = 9;
+const OPEN : usize = 0; // if, let and lambda extend as far to the right as they can
+
+// Where a pattern shows up decides whether it needs to be grouped with '( )' to parse back the same way.
+#[derive(Clone, Copy, PartialEq)]
+enum Place {
+    Top,
+    Alternative,
+    Item,
+    Guarded,
+    Quantified,
+}
+
+// Prints the asts back out as canonical source.  Comments are attached to the top level
+// definition, match case or let that follows them and are printed on their own lines
+// right before it.
+pub fn print(asts : &[Ast], comments : &[Comment]) -> String {
+    let mut printer = Printer { out: String::new(), indent: 0, comments, next_comment: 0 };
+
+    for (i, ast) in asts.iter().enumerate() {
+        if i != 0 {
+            printer.out.push('\n');
+        }
+        printer.comments_before(ast_start(ast));
+        printer.ast(ast);
+        printer.out.push('\n');
+    }
+
+    if printer.next_comment < comments.len() && !asts.is_empty() {
+        printer.out.push('\n');
+    }
+    printer.comments_before(Position { line: usize::MAX, column: usize::MAX });
+
+    printer.out
+}
+
+// A markdown reference of the data types, functions and top level lets along with their doc comments.
+pub fn print_reference(asts : &[Ast]) -> String {
+    let mut printer = Printer { out: String::new(), indent: 0, comments: &[], next_comment: 0 };
+
+    for ast in asts {
+        match ast {
+            Ast::DataDef { name, type_params, cons_defs, doc, .. } => {
+                printer.push(&format!("## data {name}"));
+                if !type_params.is_empty() {
+                    printer.push(&format!("<{}>", type_params.join(", ")));
+                }
+                printer.push("\n\n");
+                if let Some(doc) = doc {
+                    printer.push(&format!("{doc}\n\n"));
+                }
+                for cons_def in cons_defs {
+                    printer.push("* `");
+                    printer.cons_def(cons_def);
+                    printer.push("`");
+                    if let Some(doc) = &cons_def.doc {
+                        printer.push(&format!(" - {}", doc.replace('\n', " ")));
+                    }
+                    printer.push("\n");
+                }
+                if !cons_defs.is_empty() {
+                    printer.push("\n");
+                }
+            },
+            Ast::FunDef { name, params, return_type, doc, .. } => {
+                printer.push(&format!("## fun {name}"));
+                printer.series(params, "(", ")", |p, param| p.param(param, Place::Top));
+                printer.push(" -> ");
+                printer.ty(return_type);
+                printer.push("\n\n");
+                if let Some(doc) = doc {
+                    printer.push(&format!("{doc}\n\n"));
+                }
+            },
+            Ast::LetDef { name, t, doc, .. } => {
+                printer.push(&format!("## let {name}"));
+                if let Some(t) = t {
+                    printer.push(" : ");
+                    printer.ty(t);
+                }
+                printer.push("\n\n");
+                if let Some(doc) = doc {
+                    printer.push(&format!("{doc}\n\n"));
+                }
+            },
+            Ast::Import { .. } | Ast::PatternDef { .. } => { },
+        }
+    }
+
+    printer.out
+}
+
+fn ast_start(ast : &Ast) -> Position {
+    match ast {
+        Ast::FunDef { span, .. }
+        | Ast::LetDef { span, .. }
+        | Ast::DataDef { span, .. }
+        | Ast::Import { span, .. }
+        | Ast::PatternDef { span, .. } => span.start,
+    }
+}
+
+fn before(a : Position, b : Position) -> bool {
+    (a.line, a.column) < (b.line, b.column)
+}
+
+fn escape(s : &str) -> String {
+    let mut ret = String::new();
+    for c in s.chars() {
+        match c {
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            '\0' => ret.push_str("\\0"),
+            '\\' => ret.push_str("\\\\"),
+            '"' => ret.push_str("\\\""),
+            c => ret.push(c),
+        }
+    }
+    format!("\"{ret}\"")
+}
+
+fn builtin<'a>(expr : &'a Expr, name : &str) -> Option<&'a [Expr]> {
+    match &expr.kind {
+        ExprKind::FunCall { fun_expr, params } if matches!( &fun_expr.kind, ExprKind::Variable(v) if v == name ) => Some(params),
+        _ => None,
+    }
+}
+
+fn unary(expr : &Expr) -> Option<(&'static str, &Expr)> {
+    if let Some([e]) = builtin(expr, "not") {
+        if builtin(e, "eq").map(|ps| ps.len()) != Some(2) {
+            return Some(("!", e));
+        }
+    }
+    // A literal would be read back as a negative number rather than as a subtraction
+    match builtin(expr, "sub") {
+        Some([Expr { kind: ExprKind::Number(0), .. }, e]) if !matches!( e.kind, ExprKind::Number(_) | ExprKind::Float(_) ) => Some(("-", e)),
+        _ => None,
+    }
+}
+
+fn binary(expr : &Expr) -> Option<(&'static str, usize, &Expr, &Expr)> {
+    if let Some([e]) = builtin(expr, "not") {
+        if let Some([l, r]) = builtin(e, "eq") {
+            return Some(("!=", 4, l, r));
+        }
+    }
+
+    match &expr.kind {
+        ExprKind::If { condition, then_expr, else_expr } if matches!( else_expr.kind, ExprKind::Bool(false) ) =>
+            return Some(("&&", 2, condition, then_expr)),
+        ExprKind::If { condition, then_expr, else_expr } if matches!( then_expr.kind, ExprKind::Bool(true) ) =>
+            return Some(("||", 1, condition, else_expr)),
+        _ => { },
+    }
+
+    for (name, op, precedence) in OPERATORS {
+        if let Some([l, r]) = builtin(expr, name) {
+            return Some((op, precedence, l, r));
+        }
+    }
+
+    None
+}
+
+fn precedence(expr : &Expr) -> usize {
+    if unary(expr).is_some() {
+        return UNARY;
+    }
+    if let Some((_, precedence, _, _)) = binary(expr) {
+        return precedence;
+    }
+    match &expr.kind {
+        ExprKind::If { .. } | ExprKind::Let { .. } | ExprKind::LetRec { .. } | ExprKind::Lambda { .. } => OPEN,
+        ExprKind::Number(n) if *n < 0 => UNARY,
+        ExprKind::Float(f) if f.is_sign_negative() => UNARY,
+        _ => ATOM,
+    }
+}
+
+struct Printer<'a> {
+    out : String,
+    indent : usize,
+    comments : &'a [Comment],
+    next_comment : usize,
+}
+
+impl<'a> Printer<'a> {
+    fn push(&mut self, s : &str) {
+        self.out.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(self.indent));
+    }
+
+    // Only called at the start of a line.
+    fn comments_before(&mut self, position : Position) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if !before(comment.span.start, position) {
+                break;
+            }
+            self.push(&format!("#{}", comment.text));
+            self.newline();
+            self.next_comment += 1;
+        }
+    }
+
+    fn series<T>(&mut self, items : &[T], start : &str, end : &str, mut f : impl FnMut(&mut Self, &T)) {
+        self.push(start);
+        for (i, item) in items.iter().enumerate() {
+            if i != 0 {
+                self.push(", ");
+            }
+            f(self, item);
+        }
+        self.push(end);
+    }
+
+    fn ast(&mut self, ast : &Ast) {
+        match ast {
+            Ast::FunDef { name, params, return_type, expr, .. } => {
+                self.push(&format!("fun {name}"));
+                self.series(params, "(", ")", |p, param| p.param(param, Place::Top));
+                self.push(" -> ");
+                self.ty(return_type);
+                self.push(" =");
+                if matches!( expr.kind, ExprKind::Let { .. } | ExprKind::LetRec { .. } ) {
+                    self.indent += 4;
+                    self.newline();
+                    self.line_expr(expr);
+                    self.indent -= 4;
+                }
+                else {
+                    self.push(" ");
+                    self.expr(expr);
+                }
+                self.push(";");
+            },
+            Ast::LetDef { name, t, expr, .. } => {
+                self.push(&format!("let {name}"));
+                if let Some(t) = t {
+                    self.push(" : ");
+                    self.ty(t);
+                }
+                self.push(" =");
+                if matches!( expr.kind, ExprKind::Let { .. } | ExprKind::LetRec { .. } ) {
+                    self.indent += 4;
+                    self.newline();
+                    self.line_expr(expr);
+                    self.indent -= 4;
+                }
+                else {
+                    self.push(" ");
+                    self.expr(expr);
+                }
+                self.push(";");
+            },
+            Ast::DataDef { name, type_params, cons_defs, span, .. } => {
+                self.push(&format!("data {name}"));
+                if !type_params.is_empty() {
+                    self.push(&format!("<{}>", type_params.join(", ")));
+                }
+                self.push(" =");
+                // Comments on constructors need each constructor on its own line.  The '|' goes at the
+                // end of the line so that a doc comment sits right before the constructor it documents.
+                let commented = self.comments.get(self.next_comment).is_some_and(|c| before(c.span.start, span.end));
+                if commented && !cons_defs.is_empty() {
+                    self.indent += 4;
+                    for (i, cons_def) in cons_defs.iter().enumerate() {
+                        if i != 0 {
+                            self.push(" |");
+                        }
+                        self.newline();
+                        self.comments_before(cons_def.span.start);
+                        self.cons_def(cons_def);
+                    }
+                    self.indent -= 4;
+                }
+                else {
+                    for (i, cons_def) in cons_defs.iter().enumerate() {
+                        self.push(if i == 0 { " " } else { " | " });
+                        self.cons_def(cons_def);
+                    }
+                }
+                self.push(";");
+            },
+            Ast::Import { path, alias, .. } => self.push(&format!("import {} as {alias};", escape(path))),
+            Ast::PatternDef { name, pattern, .. } => {
+                self.push(&format!("pattern {name} = "));
+                match pattern {
+                    NamedPattern::Path(ps) => self.series(ps, "{| ", " |}", |p, x| p.path(x, Place::Top)),
+                    NamedPattern::Array(ps) => self.series(ps, "[| ", " |]", |p, x| p.array(x, Place::Top)),
+                }
+                self.push(";");
+            },
+        }
+    }
+
+    fn cons_def(&mut self, cons_def : &ConsDef) {
+        self.push(&cons_def.name);
+        match &cons_def.fields {
+            Some(fields) => {
+                let fields = fields.iter().zip(&cons_def.params).collect::<Vec<_>>();
+                self.push(" ");
+                self.series(&fields, "{ ", " }", |p, (f, t)| { p.push(&format!("{f} : ")); p.ty(t); });
+            },
+            None if cons_def.params.is_empty() => { },
+            None => self.series(&cons_def.params, "(", ")", |p, t| p.ty(t)),
+        }
+    }
+
+    fn param(&mut self, param : &FunParam, place : Place) {
+        self.standard(&param.pattern, place);
+        if let Some(t) = &param.t {
+            self.push(" : ");
+            self.ty(t);
+        }
+    }
+
+    fn ty(&mut self, t : &Type) {
+        match &t.kind {
+            TypeKind::Generic(name) | TypeKind::Concrete(name) => self.push(name),
+            TypeKind::Array(t) => {
+                self.push("[");
+                self.ty(t);
+                self.push("]");
+            },
+            TypeKind::Fun { input, output } => {
+                self.push("fun");
+                self.series(input, "(", ")", |p, t| p.ty(t));
+                self.push(" -> ");
+                self.ty(output);
+            },
+            TypeKind::Index { name, params } => {
+                self.push(name);
+                self.series(params, "<", ">", |p, t| p.ty(t));
+            },
+            TypeKind::Anon(rows) => self.series(rows, "{ ", " }", |p, (name, t)| { p.push(&format!("{name} : ")); p.ty(t); }),
+            TypeKind::Tuple(ts) => self.series(ts, "(", ")", |p, t| p.ty(t)),
+        }
+    }
+
+    fn line_expr(&mut self, expr : &Expr) {
+        self.comments_before(expr.span.start);
+        self.expr(expr);
+    }
+
+    fn operand(&mut self, expr : &Expr, parens : bool) {
+        if parens {
+            self.push("(");
+            self.expr(expr);
+            self.push(")");
+        }
+        else {
+            self.expr(expr);
+        }
+    }
+
+    // The target of a call or a field access
+    fn postfix(&mut self, expr : &Expr) {
+        let parens = precedence(expr) != ATOM
+                  || matches!( &expr.kind, ExprKind::Number(_) | ExprKind::Float(_) )
+                  || matches!( &expr.kind, ExprKind::Cons { params, .. } if params.is_empty() );
+        self.operand(expr, parens);
+    }
+
+    fn expr(&mut self, expr : &Expr) {
+        if let Some((op, e)) = unary(expr) {
+            self.push(op);
+            self.operand(e, precedence(e) < UNARY);
+            return;
+        }
+
+        if let Some((op, precedence_of_op, l, r)) = binary(expr) {
+            self.operand(l, precedence(l) < precedence_of_op);
+            self.push(&format!(" {op} "));
+            self.operand(r, precedence(r) <= precedence_of_op);
+            return;
+        }
+
+        match &expr.kind {
+            ExprKind::Number(n) => self.push(&n.to_string()),
+            // Display never uses an exponent, which the lexer couldn't read back
+            ExprKind::Float(f) if f.fract() == 0.0 => self.push(&format!("{f}.0")),
+            ExprKind::Float(f) => self.push(&f.to_string()),
+            ExprKind::Bool(b) => self.push(&b.to_string()),
+            ExprKind::String(s) => self.push(&escape(s)),
+            ExprKind::Variable(name) => self.push(name),
+            ExprKind::Cons { name, params } => {
+                self.push(name);
+                if !params.is_empty() {
+                    self.series(params, "(", ")", |p, e| p.expr(e));
+                }
+            },
+            ExprKind::Record { name, fields } => {
+                self.push(&format!("{name} "));
+                self.series(fields, "{ ", " }", |p, (f, e)| match &e.kind {
+                    ExprKind::Variable(v) if v == f => p.push(f),
+                    _ => { p.push(&format!("{f} : ")); p.expr(e); },
+                });
+            },
+            ExprKind::Tuple(items) => self.series(items, "(", ")", |p, e| p.expr(e)),
+            ExprKind::FieldAccess { expr, field } => {
+                self.postfix(expr);
+                self.push(&format!(".{field}"));
+            },
+            ExprKind::Let { pattern, t, value, expr } => {
+                self.push("let ");
+                // The guard would otherwise take in the ': T' and '='
+                let parens = matches!( pattern.kind, StandardPatternKind::If { .. } );
+                self.group(parens, |p| p.standard(pattern, Place::Top));
+                if let Some(t) = t {
+                    self.push(" : ");
+                    self.ty(t);
+                }
+                self.push(" = ");
+                self.expr(value);
+                self.push(" in");
+                self.newline();
+                self.line_expr(expr);
+            },
+            ExprKind::LetRec { bindings, expr } => {
+                self.series(bindings, "let rec ", " in", |p, binding| {
+                    p.push(&binding.name);
+                    if let Some(t) = &binding.t {
+                        p.push(" : ");
+                        p.ty(t);
+                    }
+                    p.push(" = ");
+                    p.expr(&binding.value);
+                });
+                self.newline();
+                self.line_expr(expr);
+            },
+            ExprKind::Lambda { params, return_type, expr } => {
+                // The closing '|' would be read as another alternative
+                self.series(params, "|", "|", |p, param| p.param(param, Place::Item));
+                if let Some(t) = return_type {
+                    self.push(" -> ");
+                    self.ty(t);
+                }
+                self.push(" ");
+                self.expr(expr);
+            },
+            ExprKind::Match { expr, cases } => {
+                self.push("match ");
+                // 'match Person { name } { }' would read the record as the cases
+                let parens = matches!( &expr.kind, ExprKind::Record { .. } );
+                self.operand(expr, parens);
+                self.push(" {");
+                self.indent += 4;
+                for (i, case) in cases.iter().enumerate() {
+                    self.newline();
+                    self.comments_before(case.pattern.span.start);
+                    self.standard(&case.pattern, Place::Top);
+                    self.push(" => ");
+                    self.expr(&case.expr);
+                    if i != cases.len() - 1 {
+                        self.push(",");
+                    }
+                }
+                self.indent -= 4;
+                if !cases.is_empty() {
+                    self.newline();
+                }
+                self.push("}");
+            },
+            ExprKind::If { condition, then_expr, else_expr } => {
+                self.push("if ");
+                self.expr(condition);
+                self.push(" then ");
+                self.expr(then_expr);
+                self.push(" else ");
+                self.expr(else_expr);
+            },
+            ExprKind::FunCall { fun_expr, params } => {
+                self.postfix(fun_expr);
+                self.series(params, "(", ")", |p, e| p.expr(e));
+            },
+            ExprKind::Array(items) => self.series(items, "[", "]", |p, e| p.expr(e)),
+            ExprKind::PathPattern(ps) => self.series(ps, "{| ", " |}", |p, x| p.path(x, Place::Top)),
+            ExprKind::ArrayPattern(ps) => self.series(ps, "[| ", " |]", |p, x| p.array(x, Place::Top)),
+            ExprKind::NamedPattern(name) => self.push(&format!("${name}")),
+        }
+    }
+
+    fn range(&mut self, min : &Option<i64>, max : &Option<i64>) {
+        match (min, max) {
+            (Some(min), Some(max)) => self.push(&format!("{min}..={max}")),
+            (Some(min), None) => self.push(&format!("{min}..")),
+            (None, Some(max)) => self.push(&format!("..={max}")),
+            (None, None) => unreachable!("range pattern should have at least one bound"),
+        }
+    }
+
+    fn record<P>(&mut self, name : &str, fields : &[(String, P)], pun : fn(&P) -> Option<&str>, mut f : impl FnMut(&mut Self, &P)) {
+        self.push(&format!("{name} "));
+        self.series(fields, "{ ", " }", |p, (field, pattern)| match pun(pattern) {
+            Some(v) if v == field => p.push(field),
+            _ => { p.push(&format!("{field} : ")); f(p, pattern); },
+        });
+    }
+
+    fn standard_array<P>(&mut self, array : &StandardArrayPattern<P>, mut f : impl FnMut(&mut Self, &P)) {
+        match array {
+            StandardArrayPattern::Empty => self.push("[]"),
+            StandardArrayPattern::Array { items, rest } => {
+                self.push("[");
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        self.push(", ");
+                    }
+                    f(self, item);
+                }
+                if let Some(rest) = rest {
+                    self.push(" | ");
+                    f(self, rest);
+                }
+                self.push("]");
+            },
+        }
+    }
+
+    fn group(&mut self, parens : bool, f : impl FnOnce(&mut Self)) {
+        if parens {
+            self.push("(");
+        }
+        f(self);
+        if parens {
+            self.push(")");
+        }
+    }
+
+    fn standard(&mut self, pattern : &StandardPattern, place : Place) {
+        let parens = place != Place::Top && match &pattern.kind {
+            StandardPatternKind::At { .. } | StandardPatternKind::If { .. } => true,
+            StandardPatternKind::Or(_) => place != Place::Guarded,
+            _ => false,
+        };
+
+        self.group(parens, |p| match &pattern.kind {
+            StandardPatternKind::Number(n) => p.push(&n.to_string()),
+            StandardPatternKind::Range { min, max } => p.range(min, max),
+            StandardPatternKind::Bool(b) => p.push(&b.to_string()),
+            StandardPatternKind::String(s) => p.push(&escape(s)),
+            StandardPatternKind::Variable(name) => p.push(name),
+            StandardPatternKind::Cons { name, params } => {
+                p.push(name);
+                if !params.is_empty() {
+                    p.series(params, "(", ")", |p, x| p.standard(x, Place::Top));
+                }
+            },
+            StandardPatternKind::Record { name, fields } =>
+                p.record( name
+                        , fields
+                        , |x| match &x.kind { StandardPatternKind::Variable(v) => Some(v), _ => None }
+                        , |p, x| p.standard(x, Place::Top) ),
+            StandardPatternKind::Tuple(items) => p.series(items, "(", ")", |p, x| p.standard(x, Place::Top)),
+            StandardPatternKind::At { name, pattern } => {
+                p.push(&format!("{name} @ "));
+                p.standard(pattern, Place::Top);
+            },
+            StandardPatternKind::Wildcard => p.push("_"),
+            StandardPatternKind::If { pattern, predicate } => {
+                p.standard(pattern, Place::Guarded);
+                p.push(" if ");
+                p.expr(predicate);
+            },
+            StandardPatternKind::StandardArray(array) => p.standard_array(array, |p, x| p.standard(x, Place::Item)),
+            StandardPatternKind::Or(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i != 0 {
+                        p.push(" | ");
+                    }
+                    p.standard(alternative, Place::Alternative);
+                }
+            },
+        });
+    }
+
+    fn array(&mut self, pattern : &ArrayPattern, place : Place) {
+        let parens = place != Place::Top && match &pattern.kind {
+            ArrayPatternKind::At { .. } | ArrayPatternKind::If { .. } => true,
+            ArrayPatternKind::Or(_) => place != Place::Guarded,
+            ArrayPatternKind::Quantified { .. } => place == Place::Quantified,
+            _ => false,
+        };
+
+        self.group(parens, |p| match &pattern.kind {
+            ArrayPatternKind::Number(n) => p.push(&n.to_string()),
+            ArrayPatternKind::Range { min, max } => p.range(min, max),
+            ArrayPatternKind::Bool(b) => p.push(&b.to_string()),
+            ArrayPatternKind::String(s) => p.push(&escape(s)),
+            ArrayPatternKind::Variable(name) => p.push(name),
+            ArrayPatternKind::Cons { name, params } => {
+                p.push(name);
+                if !params.is_empty() {
+                    p.series(params, "(", ")", |p, x| p.array(x, Place::Top));
+                }
+            },
+            ArrayPatternKind::Record { name, fields } =>
+                p.record( name
+                        , fields
+                        , |x| match &x.kind { ArrayPatternKind::Variable(v) => Some(v), _ => None }
+                        , |p, x| p.array(x, Place::Top) ),
+            ArrayPatternKind::Tuple(items) => p.series(items, "(", ")", |p, x| p.array(x, Place::Top)),
+            ArrayPatternKind::At { name, pattern } => {
+                p.push(&format!("{name} @ "));
+                p.array(pattern, Place::Top);
+            },
+            ArrayPatternKind::Wildcard => p.push("_"),
+            ArrayPatternKind::WildcardZeroOrMore => p.push("_*"),
+            ArrayPatternKind::WildcardN(e) => {
+                p.push("_{");
+                p.expr(e);
+                p.push("}");
+            },
+            ArrayPatternKind::Quantified { pattern, quantifier } => {
+                // '_*' and '_{n}' would be read back as their own wildcard patterns and 'Nil{n}' as a record
+                let parens = match (&pattern.kind, quantifier) {
+                    (ArrayPatternKind::Wildcard, Quantifier::ZeroOrMore | Quantifier::Exactly(_)) => true,
+                    (ArrayPatternKind::Cons { params, .. }, Quantifier::Exactly(_) | Quantifier::Range { .. }) => params.is_empty(),
+                    _ => false,
+                };
+                p.group(parens, |p| p.array(pattern, Place::Quantified));
+                p.quantifier(quantifier);
+            },
+            ArrayPatternKind::If { pattern, predicate } => {
+                p.array(pattern, Place::Guarded);
+                p.push(" if ");
+                p.expr(predicate);
+            },
+            ArrayPatternKind::StandardArray(array) => p.standard_array(array, |p, x| p.array(x, Place::Item)),
+            ArrayPatternKind::Or(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i != 0 {
+                        p.push(" | ");
+                    }
+                    p.array(alternative, Place::Alternative);
+                }
+            },
+            ArrayPatternKind::Named(name) => p.push(&format!("${name}")),
+        });
+    }
+
+    fn quantifier(&mut self, quantifier : &Quantifier) {
+        match quantifier {
+            Quantifier::ZeroOrMore => self.push("*"),
+            Quantifier::OneOrMore => self.push("+"),
+            Quantifier::ZeroOrOne => self.push("?"),
+            Quantifier::Exactly(e) => {
+                self.push("{");
+                self.expr(e);
+                self.push("}");
+            },
+            Quantifier::Range { min, max } => {
+                self.push("{");
+                if let Some(min) = min {
+                    self.expr(min);
+                }
+                self.push(",");
+                if let Some(max) = max {
+                    self.expr(max);
+                }
+                self.push("}");
+            },
+        }
+    }
+
+    fn path(&mut self, pattern : &PathPattern, place : Place) {
+        let parens = place != Place::Top && match &pattern.kind {
+            PathPatternKind::At { .. } | PathPatternKind::If { .. } => true,
+            PathPatternKind::Or(_) => place != Place::Guarded,
+            _ => false,
+        };
+
+        self.group(parens, |p| match &pattern.kind {
+            PathPatternKind::Number(n) => p.push(&n.to_string()),
+            PathPatternKind::Range { min, max } => p.range(min, max),
+            PathPatternKind::Bool(b) => p.push(&b.to_string()),
+            PathPatternKind::String(s) => p.push(&escape(s)),
+            PathPatternKind::Variable(name) => p.push(name),
+            PathPatternKind::Cons { name, params } => {
+                p.push(name);
+                if !params.is_empty() {
+                    p.series(params, "(", ")", |p, x| p.path(x, Place::Top));
+                }
+            },
+            PathPatternKind::Record { name, fields } =>
+                p.record( name
+                        , fields
+                        , |x| match &x.kind { PathPatternKind::Variable(v) => Some(v), _ => None }
+                        , |p, x| p.path(x, Place::Top) ),
+            PathPatternKind::Tuple(items) => p.series(items, "(", ")", |p, x| p.path(x, Place::Top)),
+            PathPatternKind::At { name, pattern } => {
+                p.push(&format!("{name} @ "));
+                p.path(pattern, Place::Top);
+            },
+            PathPatternKind::Wildcard => p.push("_"),
+            PathPatternKind::Next(None) => p.push("!"),
+            PathPatternKind::Next(Some(order)) => p.push(&format!("!{order}")),
+            PathPatternKind::And { name, output } => p.push(&format!("&{name}:{output}")),
+            PathPatternKind::NextAnd { order: None, name, output } => p.push(&format!("!&{name}:{output}")),
+            PathPatternKind::NextAnd { order: Some(order), name, output } => p.push(&format!("!{order}&{name}:{output}")),
+            PathPatternKind::If { pattern, predicate } => {
+                p.path(pattern, Place::Guarded);
+                p.push(" if ");
+                p.expr(predicate);
+            },
+            PathPatternKind::StandardArray(array) => p.standard_array(array, |p, x| p.path(x, Place::Item)),
+            PathPatternKind::Or(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i != 0 {
+                        p.push(" | ");
+                    }
+                    p.path(alternative, Place::Alternative);
+                }
+            },
+            PathPatternKind::Named(name) => p.push(&format!("${name}")),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsing::parser::parse_with_comments;
+
+    // Spans change when the source is reprinted so they are left out of the comparison
+    fn without_spans(asts : &[Ast]) -> String {
+        let debug = format!("{:?}", asts);
+        let mut ret = String::new();
+        let mut rest = debug.as_str();
+        while let Some(index) = rest.find("span: Span {") {
+            ret.push_str(&rest[..index]);
+            let end = rest[index..].find("} }").expect("span should end with '} }'") + index + 3;
+            rest = &rest[end..];
+        }
+        ret.push_str(rest);
+        ret
+    }
+
+    fn format(input : &str) -> String {
+        let (asts, comments) = parse_with_comments(input).expect("test input should parse");
+        print(&asts, &comments)
+    }
+
+    fn assert_round_trip(input : &str) {
+        let (asts, comments) = parse_with_comments(input).expect("test input should parse");
+        let printed = print(&asts, &comments);
+        let (reparsed, recomments) = parse_with_comments(&printed).unwrap_or_else(|e| panic!("printed source should parse {:?}\n{printed}", e));
+        assert_eq!( without_spans(&reparsed), without_spans(&asts), "\n{printed}" );
+        assert_eq!( print(&reparsed, &recomments), printed );
+    }
+
+    #[test]
+    fn should_print_canonical_layout() {
+        let output = format("
+            data List<a> = Cons(a, List<a>)|Nil ;
+            fun   len(l:List<a>)->Number=match l { Cons(_, rest) => 1 + len(rest), Nil => 0 };
+        ");
+
+        assert_eq!( output, "data List<a> = Cons(a, List<a>) | Nil;\n\
+                             \n\
+                             fun len(l : List<a>) -> Number = match l {\n    \
+                                 Cons(_, rest) => 1 + len(rest),\n    \
+                                 Nil => 0\n\
+                             };\n" );
+    }
+
+    #[test]
+    fn should_print_lets_on_their_own_lines() {
+        let output = format("fun f() -> Number = let a = 1 in let b : Number = 2 in a * b;");
+
+        assert_eq!( output, "fun f() -> Number =\n    let a = 1 in\n    let b : Number = 2 in\n    a * b;\n" );
+    }
+
+    #[test]
+    fn should_keep_comments_with_the_following_node() {
+        let output = format("
+            # lists
+            data List<a> = Nil;
+
+            fun f(x : Number) -> Number =
+                # first
+                let a = 1 in
+                match x {
+                    # zero
+                    0 => a,
+                    _ => x
+                };
+            # end
+        ");
+
+        assert_eq!( output, "# lists\n\
+                             data List<a> = Nil;\n\
+                             \n\
+                             fun f(x : Number) -> Number =\n    \
+                                 # first\n    \
+                                 let a = 1 in\n    \
+                                 match x {\n        \
+                                     # zero\n        \
+                                     0 => a,\n        \
+                                     _ => x\n    \
+                                 };\n\
+                             \n\
+                             # end\n" );
+    }
+
+    #[test]
+    fn should_keep_doc_comments_before_what_they_document() {
+        let input = "\
+## Shapes
+data Shape =
+    ## A circle
+    Circle(Float) |
+    ## A square
+    Square { side : Float };
+
+## Area
+fun area(s : Shape) -> Float = 1.0;
+";
+
+        assert_eq!( format(input), input );
+        assert_round_trip(input);
+    }
+
+    #[test]
+    fn should_print_reference_with_docs() {
+        let (asts, _) = parse_with_comments("
+            ## Shapes
+            ## to draw
+            data Shape =
+                ## A circle
+                Circle(Float) | Square { side : Float };
+            import \"a.pat\" as a;
+            ## Area
+            fun area(s : Shape) -> Float = 1.0;
+            fun id(x : a) -> a = x;
+            ## Unit
+            let unit : Float = 1.0;
+        ").expect("test input should parse");
+
+        assert_eq!( print_reference(&asts), "## data Shape\n\n\
+                                             Shapes\nto draw\n\n\
+                                             * `Circle(Float)` - A circle\n\
+                                             * `Square { side : Float }`\n\n\
+                                             ## fun area(s : Shape) -> Float\n\n\
+                                             Area\n\n\
+                                             ## fun id(x : a) -> a\n\n\
+                                             ## let unit : Float\n\n\
+                                             Unit\n\n" );
+    }
+
+    #[test]
+    fn should_print_operators_with_needed_parentheses() {
+        let output = format("fun f(a : Number, b : Number) -> Bool = (a - (b - 1)) * -a == 0 && !(a > b || !c) && a != b;");
+
+        assert_eq!( output, "fun f(a : Number, b : Number) -> Bool = (a - (b - 1)) * -a == 0 && !(a > b || !c) && a != b;\n" );
+    }
+
+    #[test]
+    fn should_round_trip_expressions() {
+        assert_round_trip(r#"
+            import "lib/list.pat" as list;
+            fun f(a : Number, p : Person) -> fun(Number) -> [Number] =
+                let g = |x : Number, y| -> Number list::map(x, |z| z.add(1)).len() in
+                let s = "a\n\"b\"\\" in
+                let Pair(x, [y | r]) : Pair = p in
+                let (z if z) = true in
+                let rec even = |n : Number| -> Bool n == 0 || odd(n - 1), odd = |n| !even(n) in
+                let a = 1, b = |x| x, rec = 2 in
+                if a.gt(-5) then g(a - -5, sub(0, 5)) else (|x| x)(p.name).x;
+            fun g(a : Number) -> Number = -f(a) + (if true then 1 else 2) + Person { name : "x", age }.age + (match Nil { _ => 1 });
+            fun h() -> [Number] = [Cons(1, Nil), Nil, Nil.x, (5).x, (1.5).x, [1, 2]];
+            fun j(n : Number) -> Number = go(n) where go = |i| if i == 0 then 0 else go(i - 1);
+            fun i() -> Float = -1.5 - -0.25 + 100000000000000000000000.0 + sub(0, 2.0) + 0x10 + 1_000.0;
+            let k : Number = 1 + 2;
+            let l = let x = 1 in x;
+            fun m(p : (Number, [(a, b)])) -> (Number, Bool) = let (x, (y, _)) = (p, (1, 2)) in (x.add(1), (y, 3).z);
+            fun n([x | _] : [a], Cons(h, _) | Nil : List) -> a = fold(x, |(a, b), (Pair(c, _) | Other(c)), d : Number| c);
+            fun o(name : String) -> Number = match (Person { name }) { Person { name : n } => match None { } };
+        "#);
+    }
+
+    #[test]
+    fn should_round_trip_types() {
+        assert_round_trip("
+            data Data<a, b> = A(fun(a, [b]) -> Data<a, b>) | B { x : { y : Number, z : [a] } } | C;
+            data Empty = ;
+            fun f(g : fun() -> fun(a) -> b) -> Empty<Number, { }> = 5;
+        ");
+    }
+
+    #[test]
+    fn should_round_trip_standard_patterns() {
+        assert_round_trip(r#"
+            fun f(x : Number) -> Number = match x {
+                Cons(a, Nil) | Other(a) if a.gt(0) => a,
+                x @ Cons(_, y @ (1 | 2)) => 1,
+                (x @ Cons(_, _)) | Nil => 1,
+                (x if x.gt(0)) if x.lt(10) => 2,
+                [(A | B), x @ Y, (z if z) | r] => 3,
+                Person { name : "a\tb", age } => age,
+                Person { } => 0,
+                [] => 4,
+                (a, (b, _) | (_, b), x @ (1, 2)) => b,
+                -5 | 0..=10 | 11.. | ..=-3 | true => 5
+            };
+        "#);
+    }
+
+    #[test]
+    fn should_round_trip_array_patterns() {
+        assert_round_trip("
+            pattern p = [| _*, _{n}, (_)*, (_){n}, _{1,2}, _+, x?, (Nil){2}, Nil*, Cons(a, _){,3}, [a, b]{1,}, (a | b)*, (x @ y)+, (x*)*, (a, _)*, (a, b | c) |];
+            fun f() -> Number = [| $p, x @ A | B, Pair(x, y) if x.eq(y), [a, b | r] |];
+        ");
+    }
+
+    #[test]
+    fn should_round_trip_path_patterns() {
+        assert_round_trip("
+            pattern p = {| Node(!, !2), !&a:b, !3&c:d, &e:f, Leaf(x) | Branch(x, _), $other, [!, ! | r], (!, a) | (b, !) |};
+        ");
+    }
+}
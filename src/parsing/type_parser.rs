@@ -1,24 +1,28 @@
 
 use super::input::{Input, ParseError};
 use super::util::{ parse_symbol
+                 , parse_qualified_symbol
+                 , last_segment
                  , parse_params
+                 , parse_series
                  , keyword
                  , punct
                  , fatal
                  , fail
+                 , start_position
                  };
-use crate::ast::Type;
+use crate::ast::{Type, TypeKind, Span};
 
-fn parse_generic_type(input : &mut Input) -> Result<Type, ParseError> {
+fn parse_generic_type(input : &mut Input) -> Result<TypeKind, ParseError> {
     let rp = input.clone();
 
-    let sym = parse_symbol(input)?;
+    let sym = parse_qualified_symbol(input)?;
 
     let first = sym.chars().nth(0)
         .expect("parse_generic_type parse_symbol somehow returned zero length string");
 
-    if first.is_lowercase() {
-        Ok(Type::Generic(sym))
+    if first.is_lowercase() && !sym.contains("::") {
+        Ok(TypeKind::Generic(sym))
     }
     else {
         input.restore(rp);
@@ -26,16 +30,16 @@ fn parse_generic_type(input : &mut Input) -> Result<Type, ParseError> {
     }
 }
 
-fn parse_concrete_type(input : &mut Input) -> Result<Type, ParseError> {
+fn parse_concrete_type(input : &mut Input) -> Result<TypeKind, ParseError> {
     let rp = input.clone();
 
-    let sym = parse_symbol(input)?;
+    let sym = parse_qualified_symbol(input)?;
 
-    let first = sym.chars().nth(0)
+    let first = last_segment(&sym).chars().nth(0)
         .expect("parse_concrete_type parse_symbol somehow returned zero length string");
 
     if first.is_uppercase() {
-        Ok(Type::Concrete(sym))
+        Ok(TypeKind::Concrete(sym))
     }
     else {
         input.restore(rp);
@@ -43,23 +47,54 @@ fn parse_concrete_type(input : &mut Input) -> Result<Type, ParseError> {
     }
 }
 
-fn parse_fun_type(input : &mut Input) -> Result<Type, ParseError> {
+fn parse_fun_type(input : &mut Input) -> Result<TypeKind, ParseError> {
     keyword(input, "fun")?;
 
-    let i = fatal(parse_params(parse_type, input), "fun type must have param list")?;
+    let i = fatal(parse_params(parse_type, input), input, "fun type must have param list")?;
 
-    fatal(punct(input, "->"), "fun type must have '->'")?;
+    fatal(punct(input, "->"), input, "fun type must have '->'")?;
 
-    let output = Box::new(fatal(parse_type(input), "fun type must have output type")?);
+    let output = Box::new(fatal(parse_type(input), input, "fun type must have output type")?);
 
-    Ok(Type::Fun{ input: i, output})
+    Ok(TypeKind::Fun{ input: i, output})
 }
 
-fn parse_array_type(input : &mut Input) -> Result<Type, ParseError> {
+fn parse_array_type(input : &mut Input) -> Result<TypeKind, ParseError> {
     punct(input, "[")?;
-    let t = Box::new(fatal(parse_type(input), "array type must have type")?);
-    fatal(punct(input, "]"), "array type must have closing ']'")?;
-    Ok(Type::Array(t))
+    let t = Box::new(fatal(parse_type(input), input, "array type must have type")?);
+    fatal(punct(input, "]"), input, "array type must have closing ']'")?;
+    Ok(TypeKind::Array(t))
+}
+
+fn parse_anon_type(input : &mut Input) -> Result<TypeKind, ParseError> {
+    fn parse_row(input : &mut Input) -> Result<(String, Span, Type), ParseError> {
+        let start = start_position(input)?;
+        let name = parse_symbol(input)?;
+        let span = input.span_from(start);
+        fatal(punct(input, ":"), input, "anon type field must have ':'")?;
+        let t = fatal(parse_type(input), input, "anon type field must have type")?;
+        Ok((name, span, t))
+    }
+
+    let rows = parse_series(parse_row, "{", "}", input)?;
+
+    for (i, (name, span, _)) in rows.iter().enumerate() {
+        if rows[..i].iter().any(|(n, _, _)| n == name) {
+            return Err(ParseError::Fatal(vec![(format!("anon type has duplicate field {name}"), *span)]));
+        }
+    }
+
+    Ok(TypeKind::Anon(rows.into_iter().map(|(name, _, t)| (name, t)).collect()))
+}
+
+// Parentheses around a single type are only a group
+fn parse_tuple_type(input : &mut Input) -> Result<TypeKind, ParseError> {
+    let mut ts = parse_params(parse_type, input)?;
+    match ts.len() {
+        0 => fail(input, "tuple type must have at least two types"),
+        1 => Ok(ts.pop().unwrap().kind),
+        _ => Ok(TypeKind::Tuple(ts)),
+    }
 }
 
 pub fn parse_type(input : &mut Input) -> Result<Type, ParseError> {
@@ -68,27 +103,31 @@ pub fn parse_type(input : &mut Input) -> Result<Type, ParseError> {
              , parse_generic_type
              , parse_concrete_type 
              , parse_array_type
+             , parse_anon_type
+             , parse_tuple_type
              ];
 
+    let start = start_position(input)?;
+
     let mut t = None;
     
     for p in ps {
         match p(input) {
             Ok(e) => { t = Some(e); break; },
-            e @ Err(ParseError::Fatal(_)) => return e,
+            Err(e @ ParseError::Fatal(_)) => return Err(e),
             _ => { },
         }
     }
 
     let name = match t {
-        Some(Type::Concrete(name)) => name, 
-        Some(t) => return Ok(t),
+        Some(TypeKind::Concrete(name)) => name, 
+        Some(kind) => return Ok(Type { kind, span: input.span_from(start) }),
         None => return Err(ParseError::Error),
     };
 
     match punct(input, "<") {
         Ok(_) => { },
-        Err(ParseError::Error) => return Ok(Type::Concrete(name)),
+        Err(ParseError::Error) => return Ok(Type { kind: TypeKind::Concrete(name), span: input.span_from(start) }),
         Err(e @ ParseError::Fatal(_)) => return Err(e),
     }
 
@@ -102,12 +141,12 @@ pub fn parse_type(input : &mut Input) -> Result<Type, ParseError> {
         }
         match punct(input, ">") {
             Ok(_) => break,
-            Err(ParseError::Error) => return fail("index type parameters must have ending '>'"),
+            Err(ParseError::Error) => return fail(input, "index type parameters must have ending '>'"),
             Err(e @ ParseError::Fatal(_)) => return Err(e),
         }
     }
 
-    Ok(Type::Index{ name, params })
+    Ok(Type { kind: TypeKind::Index{ name, params }, span: input.span_from(start) })
 }
 
 #[cfg(test)]
@@ -118,7 +157,7 @@ mod test {
     fn should_parse_fun_type() -> Result<(), ParseError> {
         let mut input = Input::new("fun (a, B, c) -> D");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Fun { .. } ) );
+        assert!( matches!( result.kind, TypeKind::Fun { .. } ) );
         // TODO add more details
         Ok(())
     }
@@ -127,7 +166,7 @@ mod test {
     fn should_parse_fun_type_with_no_params() -> Result<(), ParseError> {
         let mut input = Input::new("fun () -> D");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Fun { .. } ) );
+        assert!( matches!( result.kind, TypeKind::Fun { .. } ) );
         // TODO add more details
         Ok(())
     }
@@ -136,7 +175,7 @@ mod test {
     fn should_parse_index_type() -> Result<(), ParseError> {
         let mut input = Input::new("A<a, b, C>");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Index { .. } ) );
+        assert!( matches!( result.kind, TypeKind::Index { .. } ) );
         // TODO add more details
         Ok(())
     }
@@ -145,7 +184,7 @@ mod test {
     fn should_parse_concrete_type() -> Result<(), ParseError> {
         let mut input = Input::new("A");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Concrete(_) ) );
+        assert!( matches!( result.kind, TypeKind::Concrete(_) ) );
         // TODO add more details
         Ok(())
     }
@@ -154,7 +193,7 @@ mod test {
     fn should_parse_generic_type() -> Result<(), ParseError> {
         let mut input = Input::new("a");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Generic(_) ) );
+        assert!( matches!( result.kind, TypeKind::Generic(_) ) );
         // TODO add more details
         Ok(())
     }
@@ -163,7 +202,7 @@ mod test {
     fn should_parse_index_type_with_fun_inside() -> Result<(), ParseError> {
         let mut input = Input::new("Index<fun (a) -> a>");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Index { .. } ) );
+        assert!( matches!( result.kind, TypeKind::Index { .. } ) );
         // TODO add more details
         Ok(())
     }
@@ -172,7 +211,7 @@ mod test {
     fn should_parse_fun_type_with_index_inside() -> Result<(), ParseError> {
         let mut input = Input::new("fun (A<a>) -> X");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Fun { .. } ) );
+        assert!( matches!( result.kind, TypeKind::Fun { .. } ) );
         // TODO add more details
         Ok(())
     }
@@ -181,7 +220,7 @@ mod test {
     fn should_parse_fun_type_with_index_inside_with_fun_inside() -> Result<(), ParseError> {
         let mut input = Input::new("fun (A<fun (a) -> B>) -> X");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Fun { .. } ) );
+        assert!( matches!( result.kind, TypeKind::Fun { .. } ) );
         // TODO add more details
         Ok(())
     }
@@ -190,7 +229,7 @@ mod test {
     fn should_parse_everything() -> Result<(), ParseError> {
         let mut input = Input::new("fun (A<fun (a) -> B>, [C<d>], [fun () -> X]) -> fun () -> X<a, b>");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Fun {..} ) );
+        assert!( matches!( result.kind, TypeKind::Fun {..} ) );
         // TODO add more details
         Ok(())
     }
@@ -199,8 +238,69 @@ mod test {
     fn should_parse_everything_with_no_whitespace() -> Result<(), ParseError> {
         let mut input = Input::new("fun(A<fun(a)->B>,[C<d>],[fun()->X])->fun()->X<a,b>");
         let result = parse_type(&mut input)?;
-        assert!( matches!( result, Type::Fun {..} ) );
+        assert!( matches!( result.kind, TypeKind::Fun {..} ) );
         // TODO add more details
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_parse_anon_type() -> Result<(), ParseError> {
+        let mut input = Input::new("{ a : Number, b : List<x> }");
+        let result = parse_type(&mut input)?;
+        let rows = match result.kind {
+            TypeKind::Anon(rows) => rows,
+            _ => panic!("expected anon type"),
+        };
+        assert_eq!( rows.len(), 2 );
+        assert!( matches!( &rows[0], (a, t) if a == "a" && matches!( &t.kind, TypeKind::Concrete(n) if n == "Number" ) ) );
+        assert!( matches!( &rows[1], (b, t) if b == "b" && matches!( &t.kind, TypeKind::Index { name, .. } if name == "List" ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_nested_anon_type() -> Result<(), ParseError> {
+        let mut input = Input::new("fun([{ a : { b : c } }]) -> {}");
+        let result = parse_type(&mut input)?;
+        assert!( matches!( result.kind, TypeKind::Fun { output, .. } if matches!( &output.kind, TypeKind::Anon(rows) if rows.is_empty() ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_anon_type_with_duplicate_field() {
+        let mut input = Input::new("{ a : Number, a : String }");
+        let result = parse_type(&mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(fs)) if fs[0].0 == "anon type has duplicate field a" ) );
+    }
+
+    #[test]
+    fn should_parse_qualified_type() -> Result<(), ParseError> {
+        let mut input = Input::new("list::List<list::Item>");
+        let result = parse_type(&mut input)?;
+        assert!( matches!( result.kind, TypeKind::Index { name, params } if name == "list::List" 
+            && matches!( &params[0].kind, TypeKind::Concrete(n) if n == "list::Item" ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_tuple_type() -> Result<(), ParseError> {
+        let mut input = Input::new("(Number, [a], (B, c))");
+        let result = parse_type(&mut input)?;
+        assert!( matches!( result.kind, TypeKind::Tuple(ts) if ts.len() == 3 && matches!( &ts[2].kind, TypeKind::Tuple(inner) if inner.len() == 2 ) ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_single_type_in_parentheses_as_group() -> Result<(), ParseError> {
+        let mut input = Input::new("(A)");
+        let result = parse_type(&mut input)?;
+        assert!( matches!( result.kind, TypeKind::Concrete(a) if a == "A" ) );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_empty_tuple_type() {
+        let mut input = Input::new("()");
+        let result = parse_type(&mut input);
+        assert!( matches!( result, Err(ParseError::Fatal(fs)) if fs[0].0 == "tuple type must have at least two types" ) );
+    }
+}
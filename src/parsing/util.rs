@@ -1,5 +1,9 @@
 
+use std::num::IntErrorKind;
+
 use super::input::{Input, ParseError};
+use super::lexer::{Token, TokenKind};
+use crate::ast::{Position, Span};
 
 pub fn into<T, A, FParser : Fn(&mut Input) -> Result<T, ParseError>, FMap : Fn(T) -> A>(
     input : &mut Input, 
@@ -12,121 +16,176 @@ pub fn into<T, A, FParser : Fn(&mut Input) -> Result<T, ParseError>, FMap : Fn(T
     }
 }
 
-fn parse_junk(input : &mut Input) -> Result<(), ParseError> {
-
-    let mut comment = false;
+pub fn at_end(input : &mut Input) -> Result<bool, ParseError> {
+    Ok(input.peek().is_none())
+}
 
-    loop {
+pub fn start_position(input : &mut Input) -> Result<Position, ParseError> {
+    Ok(input.position())
+}
 
-        match (comment, input.peek()) {
-            (true, Ok('\n')) => { comment = false; input.next().unwrap(); }
-            (true, Ok('\r')) => { comment = false; input.next().unwrap(); }
-            (true, Ok(_)) => { input.next().unwrap(); }
-            (false, Ok(c)) if c.is_whitespace() => { input.next().unwrap(); },
-            (false, Ok('#')) => { comment = true; input.next().unwrap(); },
-            (false, Ok(_)) => return Ok(()), 
-            (_, Err(ParseError::Error)) => return Ok(()),
-            (_, Err(e @ ParseError::Fatal(_))) => return Err(e),
-        }
+fn error_span(input : &Input) -> Span {
+    match input.peek() {
+        Some(token) => token.span,
+        None => input.span_from(input.position()),
     }
 }
 
 pub fn parse_symbol(input : &mut Input) -> Result<String, ParseError> {
-    parse_junk(input)?;
-
-    let mut cs = vec![];
-
     match input.peek() {
-        Ok(c) if c.is_alphabetic() || c == '_' => { cs.push(c); input.next().unwrap(); },
-        Err(e @ ParseError::Fatal(_)) => return Err(e),
-        _ => return Err(ParseError::Error),
+        Some(Token { kind: TokenKind::Symbol(sym), .. }) => {
+            let sym = sym.to_string();
+            input.next();
+            Ok(sym)
+        },
+        _ => Err(ParseError::Error),
     }
+}
+
+// Names from imported modules are qualified with the alias of the import ('list::Cons').
+pub fn parse_qualified_symbol(input : &mut Input) -> Result<String, ParseError> {
+    let mut sym = parse_symbol(input)?;
 
     loop {
-        match input.peek() {
-            Ok(c) if c.is_alphanumeric() || c == '_' => { cs.push(c); input.next().unwrap(); },
+        let rp = input.clone();
+        match punct(input, "::") {
+            Ok(_) => { },
+            Err(ParseError::Error) => return Ok(sym),
+            Err(e @ ParseError::Fatal(_)) => return Err(e),
+        }
+        match parse_symbol(input) {
+            Ok(next) => { sym = format!("{sym}::{next}"); },
+            Err(ParseError::Error) => { input.restore(rp); return Ok(sym); },
             Err(e @ ParseError::Fatal(_)) => return Err(e),
-            _ => return Ok(cs.into_iter().collect::<String>()),
         }
     }
 }
 
+// The unqualified part of a possibly qualified name.
+pub fn last_segment(sym : &str) -> &str {
+    sym.rsplit("::").next().unwrap_or(sym)
+}
+
 pub fn parse_number(input : &mut Input) -> Result<i64, ParseError> {
-    parse_junk(input)?;
+    let rp = input.clone();
 
-    let mut cs = vec![];
-    let mut negative = false;
+    let negative = punct(input, "-").is_ok();
 
     match input.peek() {
-        Ok(c) if c.is_ascii_digit() => { cs.push(c); input.next().unwrap(); },
-        Ok(c) if c == '-' => { negative = true; input.next().unwrap(); },
-        Err(e @ ParseError::Fatal(_)) => return Err(e),
-        _ => return Err(ParseError::Error),
-    }
-
-    loop {
-        match input.peek() {
-            Ok(c) if c.is_ascii_digit() => { cs.push(c); input.next().unwrap(); },
-            Err(e @ ParseError::Fatal(_)) => return Err(e),
-            _ if cs.len() < 1 => return fail("encountered single '-'"),
-            _ if negative => return Ok(cs.into_iter().collect::<String>().parse::<i64>().expect("Internal Rust Parse Error") * -1),
-            _ => return Ok(cs.into_iter().collect::<String>().parse::<i64>().expect("Internal Rust Parse Error")),
-        }
+        Some(Token { kind: TokenKind::Number(literal), .. }) if !negative || input.joined() => {
+            let (radix, digits) = match literal.get(..2) {
+                Some("0x") => (16, &literal[2..]),
+                Some("0b") => (2, &literal[2..]),
+                _ => (10, &literal[..]),
+            };
+            let digits = digits.replace('_', "");
+            let sign = if negative { "-" } else { "" };
+
+            match i64::from_str_radix(&format!("{sign}{digits}"), radix) {
+                Ok(n) => {
+                    input.next();
+                    Ok(n)
+                },
+                Err(e) if matches!( e.kind(), IntErrorKind::PosOverflow | IntErrorKind::NegOverflow ) =>
+                    fail(input, &format!("number literal {sign}{literal} does not fit in a Number")),
+                Err(_) => fail(input, &format!("malformed number literal {literal}")),
+            }
+        },
+        Some(Token { kind: TokenKind::Float(_), .. }) if !negative || input.joined() => {
+            input.restore(rp);
+            Err(ParseError::Error)
+        },
+        _ if negative => fail(&rp, "encountered single '-'"),
+        _ => Err(ParseError::Error),
     }
 }
 
-pub fn parse_bool(input : &mut Input) -> Result<bool, ParseError> {
-    parse_junk(input)?;
-
+pub fn parse_float(input : &mut Input) -> Result<f64, ParseError> {
     let rp = input.clone();
 
-    match parse_symbol(input) {
-        Ok(sym) if sym == "true" => Ok(true),
-        Ok(sym) if sym == "false" => Ok(false),
-        Err(e @ ParseError::Fatal(_)) => Err(e),
-        _ => { input.restore(rp); Err(ParseError::Error)},
+    let negative = punct(input, "-").is_ok();
+
+    match input.peek() {
+        Some(Token { kind: TokenKind::Float(literal), .. }) if !negative || input.joined() => {
+            let sign = if negative { "-" } else { "" };
+
+            match format!("{sign}{}", literal.replace('_', "")).parse::<f64>() {
+                Ok(f) if f.is_finite() => {
+                    input.next();
+                    Ok(f)
+                },
+                Ok(_) => fail(input, &format!("float literal {sign}{literal} is out of range")),
+                Err(_) => fail(input, &format!("malformed float literal {literal}")),
+            }
+        },
+        _ => {
+            input.restore(rp);
+            Err(ParseError::Error)
+        },
     }
 }
 
-pub fn keyword(input : &mut Input, value : &str) -> Result<(), ParseError> {
-    parse_junk(input)?;
-
-    let rp = input.clone();
+pub fn parse_string(input : &mut Input) -> Result<String, ParseError> {
+    match input.peek() {
+        Some(Token { kind: TokenKind::String(s), .. }) => {
+            let s = s.to_string();
+            input.next();
+            Ok(s)
+        },
+        Some(Token { kind: TokenKind::Error(message), .. }) => fail(input, message),
+        _ => Err(ParseError::Error),
+    }
+}
 
-    for c in value.chars() {
-        match input.next() {
-            Ok(v) if c == v => { },
-            Ok(_) => { input.restore(rp); return Err(ParseError::Error); },
-            Err(ParseError::Error) => { input.restore(rp); return Err(ParseError::Error); }, 
-            Err(e @ ParseError::Fatal(_)) => return Err(e),
-        }
+pub fn parse_bool(input : &mut Input) -> Result<bool, ParseError> {
+    if keyword(input, "true").is_ok() {
+        Ok(true)
+    }
+    else if keyword(input, "false").is_ok() {
+        Ok(false)
     }
+    else {
+        Err(ParseError::Error)
+    }
+}
 
+pub fn keyword(input : &mut Input, value : &str) -> Result<(), ParseError> {
     match input.peek() {
-        Ok(v) if !(v.is_alphanumeric() || v == '_') => Ok(()),
-        Ok(_) => { input.restore(rp); Err(ParseError::Error) },
-        Err(ParseError::Error) => Ok(()),
-        Err(e @ ParseError::Fatal(_)) => Err(e),
+        Some(Token { kind: TokenKind::Keyword(k), .. }) if *k == value => { input.next(); Ok(()) },
+        _ => Err(ParseError::Error),
     }
 }
 
+// Multi character punctuation ('->', '|}', '_*', etc) has to be written without any space between its tokens.
 pub fn punct(input : &mut Input, value : &str) -> Result<(), ParseError> {
-    parse_junk(input)?;
-
     let rp = input.clone();
 
-    for c in value.chars() {
-        match input.next() {
-            Ok(v) if c == v => { },
-            Ok(_) => { input.restore(rp); return Err(ParseError::Error); },
-            Err(ParseError::Error) => { input.restore(rp); return Err(ParseError::Error); }, 
-            Err(e @ ParseError::Fatal(_)) => return Err(e),
+    let mut text = String::new();
+
+    while text.len() < value.len() {
+        if !text.is_empty() && !input.joined() {
+            input.restore(rp);
+            return Err(ParseError::Error);
+        }
+        match input.next().map(|t| &t.kind) {
+            Some(TokenKind::Punct(c)) => text.push(*c),
+            Some(TokenKind::Symbol(s)) if s == "_" => text.push('_'),
+            _ => { input.restore(rp); return Err(ParseError::Error); },
+        }
+        if !value.starts_with(&text) {
+            input.restore(rp);
+            return Err(ParseError::Error);
         }
     }
 
     Ok(())
 }
 
+// Whether the next tokens are value and are written right after what was just parsed.
+pub fn joined_punct(input : &Input, value : &str) -> bool {
+    input.joined() && punct(&mut input.clone(), value).is_ok()
+}
+
 pub fn maybe<T>( x : Result<T, ParseError> ) -> Result<Option<T>, ParseError> {
     match x { 
         Ok(v) => Ok(Some(v)),
@@ -135,22 +194,30 @@ pub fn maybe<T>( x : Result<T, ParseError> ) -> Result<Option<T>, ParseError> {
     }
 }
 
-pub fn fatal<T>(x : Result<T, ParseError>, message : &str) -> Result<T, ParseError> {
+pub fn fatal<T>(x : Result<T, ParseError>, input : &Input, message : &str) -> Result<T, ParseError> {
     match x {
         o @ Ok(_) => o,
-        Err(ParseError::Error) => Err(ParseError::Fatal(vec![message.to_string()])), 
+        Err(ParseError::Error) => Err(ParseError::Fatal(vec![(message.to_string(), error_span(input))])), 
         Err(ParseError::Fatal(mut fs)) => {
-            fs.push(message.to_string());
+            fs.push((message.to_string(), error_span(input)));
             Err(ParseError::Fatal(fs))
         },
     }
 }
 
-pub fn fail<T>(message : &str) -> Result<T, ParseError> {
-    Err(ParseError::Fatal(vec![message.to_string()]))
+pub fn fail<T>(input : &Input, message : &str) -> Result<T, ParseError> {
+    Err(ParseError::Fatal(vec![(message.to_string(), error_span(input))]))
 }
 
 pub fn parse_series<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, start : &str, end : &str, input : &mut Input) -> Result<Vec<T>, ParseError> {
+    // Nothing between the delimiters can run into the cases of an enclosing match
+    let mut inner = input.scrutinee(false);
+    let ret = parse_delimited(p, start, end, &mut inner);
+    input.restore(inner);
+    ret
+}
+
+fn parse_delimited<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, start : &str, end : &str, input : &mut Input) -> Result<Vec<T>, ParseError> {
     punct(input, start)?;
     match punct(input, end) {
         Ok(_) => return Ok(vec![]),
@@ -159,7 +226,7 @@ pub fn parse_series<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, start
     }
     let mut ps = vec![];
     loop {
-        ps.push(fatal(p(input), "series items must parse successfully")?);
+        ps.push(fatal(p(input), input, "series items must parse successfully")?);
         match punct(input, ",") {
             Ok(_) => continue,
             Err(ParseError::Error) => { },
@@ -167,7 +234,7 @@ pub fn parse_series<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, start
         }
         match punct(input, end) {
             Ok(_) => break,
-            Err(ParseError::Error) => return fail(&format!("series items must have ending '{end}'")),
+            Err(ParseError::Error) => return fail(input, &format!("series items must have ending '{end}'")),
             Err(e @ ParseError::Fatal(_)) => return Err(e),
         }
     }
@@ -175,6 +242,44 @@ pub fn parse_series<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, start
     Ok(ps)
 }
 
+// A '{' following a constructor name starts a record (rather than a match body, etc) when
+// it is followed by '}' or by a field name and then ':', ',' or '}'.  In a match scrutinee
+// only a field name and then ':' does, so 'match None {}' is an empty match.  Records there
+// that start some other way need parens.  In array patterns this also decides between a record
+// and a quantifier, so 'Foo{n}' is a record while 'Foo{2}' is quantified.
+pub fn is_record_start(input : &Input) -> bool {
+    let mut rp = input.clone();
+    if punct(&mut rp, "{").is_err() {
+        return false;
+    }
+    if punct(&mut rp, "}").is_ok() {
+        return !input.in_scrutinee();
+    }
+    if parse_symbol(&mut rp).is_err() {
+        return false;
+    }
+    if punct(&mut rp, ":").is_ok() {
+        return true;
+    }
+    !input.in_scrutinee() && (punct(&mut rp, ",").is_ok() || punct(&mut rp, "}").is_ok())
+}
+
+// Record fields are 'name : item'.  The item may be left off ('name') in which case 
+// the caller decides what the field name is shorthand for.
+pub fn parse_record_fields<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, input : &mut Input) -> Result<Vec<(String, Span, Option<T>)>, ParseError> {
+    let field = |input : &mut Input| {
+        let start = start_position(input)?;
+        let name = parse_symbol(input)?;
+        let span = input.span_from(start);
+        match punct(input, ":") {
+            Ok(_) => Ok((name, span, Some(fatal(p(input), input, "record field must have a value after ':'")?))),
+            Err(ParseError::Error) => Ok((name, span, None)),
+            Err(e @ ParseError::Fatal(_)) => Err(e),
+        }
+    };
+    parse_series(field, "{", "}", input)
+}
+
 pub fn parse_array<T, F : Fn(&mut Input) -> Result<T, ParseError>>(p : F, input : &mut Input) -> Result<Vec<T>, ParseError> {
     parse_series(p, "[", "]", input)
 }
@@ -237,6 +342,62 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn should_parse_hex_binary_and_separated_ints() -> Result<(), ParseError> {
+        let mut input = Input::new("0xFF -0b101 1_000_000");
+
+        assert_eq!( parse_number(&mut input)?, 255 );
+        assert_eq!( parse_number(&mut input)?, -5 );
+        assert_eq!( parse_number(&mut input)?, 1_000_000 );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_min_int() -> Result<(), ParseError> {
+        let mut input = Input::new("-9223372036854775808");
+        let result = parse_number(&mut input)?;
+
+        assert_eq!( result, i64::MIN );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_on_out_of_range_int() {
+        let mut input = Input::new("9223372036854775808");
+        let result = parse_number(&mut input);
+
+        assert!( matches!( result, Err(ParseError::Fatal(fs)) if fs[0].0 == "number literal 9223372036854775808 does not fit in a Number" ) );
+    }
+
+    #[test]
+    fn should_fail_on_malformed_int() {
+        let mut input = Input::new("0x1G");
+        let result = parse_number(&mut input);
+
+        assert!( matches!( result, Err(ParseError::Fatal(fs)) if fs[0].0 == "malformed number literal 0x1G" ) );
+    }
+
+    #[test]
+    fn should_parse_float() -> Result<(), ParseError> {
+        let mut input = Input::new("1_000.25 -0.5");
+
+        assert_eq!( parse_float(&mut input)?, 1000.25 );
+        assert_eq!( parse_float(&mut input)?, -0.5 );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_parse_int_as_float() {
+        let mut input = Input::new("-5");
+        let result = parse_float(&mut input);
+
+        assert!( matches!( result, Err(ParseError::Error) ) );
+        assert_eq!( parse_number(&mut input).ok(), Some(-5) );
+    }
+
     #[test]
     fn should_parse_true() -> Result<(), ParseError> {
         let mut input = Input::new("true");
@@ -273,38 +434,95 @@ mod test {
 
     #[test]
     fn keyword_should_parse_with_whitespace() -> Result<(), ParseError> {
-        let mut input = Input::new("input extra");
-        keyword(&mut input, "input")?;
+        let mut input = Input::new("match extra");
+        keyword(&mut input, "match")?;
         Ok(())
     }
 
     #[test]
     fn keyword_should_parse_with_non_symbol() -> Result<(), ParseError> {
-        let mut input = Input::new("input(");
-        keyword(&mut input, "input")?;
+        let mut input = Input::new("match(");
+        keyword(&mut input, "match")?;
         punct(&mut input, "(")?;
         Ok(())
     }
 
     #[test]
     fn keyword_should_parse_with_end_of_file() -> Result<(), ParseError> {
-        let mut input = Input::new("input");
-        keyword(&mut input, "input")?;
+        let mut input = Input::new("match");
+        keyword(&mut input, "match")?;
         Ok(())
     }
 
     #[test]
     fn keyword_should_leave_input_alone_on_failure() -> Result<(), ParseError> {
-        let mut input = Input::new("inputx");
-        let result = keyword(&mut input, "input");
+        let mut input = Input::new("matchx");
+        let result = keyword(&mut input, "match");
         
         assert!( matches!( result, Err(_) ) );
 
         let result = parse_symbol(&mut input)?;
 
-        assert_eq!( result, "inputx" );
+        assert_eq!( result, "matchx" );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_string() -> Result<(), ParseError> {
+        let mut input = Input::new(r#" "some string" "#);
+        let result = parse_string(&mut input)?;
+
+        assert_eq!( result, "some string" );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_string_with_escapes() -> Result<(), ParseError> {
+        let mut input = Input::new(r#""a\n\t\"b\"\\""#);
+        let result = parse_string(&mut input)?;
+
+        assert_eq!( result, "a\n\t\"b\"\\" );
 
         Ok(())
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn should_fail_unterminated_string() {
+        let mut input = Input::new(r#""blah"#);
+        let result = parse_string(&mut input);
+
+        assert!( matches!( result, Err(ParseError::Fatal(_)) ) );
+    }
+
+    #[test]
+    fn should_fail_unknown_escape() {
+        let mut input = Input::new(r#""\q""#);
+        let result = parse_string(&mut input);
+
+        assert!( matches!( result, Err(ParseError::Fatal(_)) ) );
+    }
+
+    #[test]
+    fn keyword_should_not_parse_as_symbol() {
+        let mut input = Input::new("match");
+        let result = parse_symbol(&mut input);
+
+        assert!( matches!( result, Err(ParseError::Error) ) );
+    }
+
+    #[test]
+    fn punct_should_require_joined_tokens() -> Result<(), ParseError> {
+        let mut input = Input::new("- > ->");
+        let result = punct(&mut input, "->");
+
+        assert!( matches!( result, Err(ParseError::Error) ) );
+
+        punct(&mut input, "-")?;
+        punct(&mut input, ">")?;
+        punct(&mut input, "->")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,501 @@
+use crate::ast::{ Expr
+                , ExprKind
+                , Type
+                , TypeKind
+                , StandardArrayPattern
+                , StandardPattern
+                , StandardPatternKind
+                , ArrayPattern
+                , ArrayPatternKind
+                , PathPattern
+                , PathPatternKind
+                , Quantifier
+                };
+
+// Every method visits the children of its node by default.  An implementation overrides the
+// nodes that it cares about and calls the matching walk function to keep going past them.
+pub trait Visit {
+    fn expr(&mut self, expr : &Expr) { walk_expr(self, expr) }
+    fn ty(&mut self, t : &Type) { walk_ty(self, t) }
+    fn standard(&mut self, pattern : &StandardPattern) { walk_standard(self, pattern) }
+    fn array(&mut self, pattern : &ArrayPattern) { walk_array(self, pattern) }
+    fn path(&mut self, pattern : &PathPattern) { walk_path(self, pattern) }
+}
+
+// Like Visit but the nodes can be changed in place, and the first error stops the traversal.
+pub trait VisitMut {
+    type Error;
+    fn expr(&mut self, expr : &mut Expr) -> Result<(), Self::Error> { walk_expr_mut(self, expr) }
+    fn ty(&mut self, t : &mut Type) -> Result<(), Self::Error> { walk_ty_mut(self, t) }
+    fn standard(&mut self, pattern : &mut StandardPattern) -> Result<(), Self::Error> { walk_standard_mut(self, pattern) }
+    fn array(&mut self, pattern : &mut ArrayPattern) -> Result<(), Self::Error> { walk_array_mut(self, pattern) }
+    fn path(&mut self, pattern : &mut PathPattern) -> Result<(), Self::Error> { walk_path_mut(self, pattern) }
+}
+
+// Calls f on the expression and on every expression inside of it, including the guards and
+// quantifier counts inside of patterns.  Parents are visited before their children.
+pub fn exprs(expr : &Expr, f : &mut dyn FnMut(&Expr)) {
+    struct Exprs<'a> {
+        f : &'a mut dyn FnMut(&Expr),
+    }
+
+    impl Visit for Exprs<'_> {
+        fn expr(&mut self, expr : &Expr) {
+            (self.f)(expr);
+            walk_expr(self, expr);
+        }
+    }
+
+    Exprs { f }.expr(expr);
+}
+
+// The variables that a pattern binds in the order that they show up.  Every alternative of an
+// or pattern binds the same variables, so only the first one is looked at.  Named patterns
+// need to be inlined before these are called.
+pub fn standard_variables(pattern : &StandardPattern, out : &mut Vec<String>) {
+    Variables(out).standard(pattern);
+}
+
+pub fn array_variables(pattern : &ArrayPattern, out : &mut Vec<String>) {
+    Variables(out).array(pattern);
+}
+
+pub fn path_variables(pattern : &PathPattern, out : &mut Vec<String>) {
+    Variables(out).path(pattern);
+}
+
+struct Variables<'a>(&'a mut Vec<String>);
+
+impl Visit for Variables<'_> {
+    // Nothing that an expression in a pattern binds is in scope afterwards
+    fn expr(&mut self, _ : &Expr) { }
+
+    fn standard(&mut self, pattern : &StandardPattern) {
+        match &pattern.kind {
+            StandardPatternKind::Variable(name) => self.0.push(name.to_string()),
+            StandardPatternKind::At { name, .. } => {
+                self.0.push(name.to_string());
+                walk_standard(self, pattern);
+            },
+            StandardPatternKind::Or(alternatives) => self.standard(&alternatives[0]),
+            _ => walk_standard(self, pattern),
+        }
+    }
+
+    fn array(&mut self, pattern : &ArrayPattern) {
+        match &pattern.kind {
+            ArrayPatternKind::Variable(name) => self.0.push(name.to_string()),
+            ArrayPatternKind::At { name, .. } => {
+                self.0.push(name.to_string());
+                walk_array(self, pattern);
+            },
+            ArrayPatternKind::Or(alternatives) => self.array(&alternatives[0]),
+            ArrayPatternKind::Named(_) => panic!("array_variables should not have any named patterns; they are inlined"),
+            _ => walk_array(self, pattern),
+        }
+    }
+
+    fn path(&mut self, pattern : &PathPattern) {
+        match &pattern.kind {
+            PathPatternKind::Variable(name) => self.0.push(name.to_string()),
+            PathPatternKind::At { name, .. } => {
+                self.0.push(name.to_string());
+                walk_path(self, pattern);
+            },
+            PathPatternKind::And { output, .. } | PathPatternKind::NextAnd { output, .. } => self.0.push(output.to_string()),
+            PathPatternKind::Or(alternatives) => self.path(&alternatives[0]),
+            PathPatternKind::Named(_) => panic!("path_variables should not have any named patterns; they are inlined"),
+            _ => walk_path(self, pattern),
+        }
+    }
+}
+
+pub fn walk_expr<V : Visit + ?Sized>(v : &mut V, expr : &Expr) {
+    match &expr.kind {
+        ExprKind::Let { pattern, t, value, expr } => {
+            v.expr(value);
+            v.standard(pattern);
+            t.iter().for_each(|t| v.ty(t));
+            v.expr(expr);
+        },
+        ExprKind::Cons { params, .. } => params.iter().for_each(|e| v.expr(e)),
+        ExprKind::Record { fields, .. } => fields.iter().for_each(|(_, e)| v.expr(e)),
+        ExprKind::Tuple(items) => items.iter().for_each(|e| v.expr(e)),
+        ExprKind::FieldAccess { expr, .. } => v.expr(expr),
+        ExprKind::LetRec { bindings, expr } => {
+            for binding in bindings {
+                binding.t.iter().for_each(|t| v.ty(t));
+                v.expr(&binding.value);
+            }
+            v.expr(expr);
+        },
+        ExprKind::Lambda { params, return_type, expr } => {
+            for param in params {
+                v.standard(&param.pattern);
+                param.t.iter().for_each(|t| v.ty(t));
+            }
+            return_type.iter().for_each(|t| v.ty(t));
+            v.expr(expr);
+        },
+        ExprKind::Match { expr, cases } => {
+            v.expr(expr);
+            for case in cases {
+                v.standard(&case.pattern);
+                v.expr(&case.expr);
+            }
+        },
+        ExprKind::If { condition, then_expr, else_expr } => {
+            v.expr(condition);
+            v.expr(then_expr);
+            v.expr(else_expr);
+        },
+        ExprKind::FunCall { fun_expr, params } => {
+            v.expr(fun_expr);
+            params.iter().for_each(|e| v.expr(e));
+        },
+        ExprKind::Array(items) => items.iter().for_each(|e| v.expr(e)),
+        ExprKind::PathPattern(ps) => ps.iter().for_each(|p| v.path(p)),
+        ExprKind::ArrayPattern(ps) => ps.iter().for_each(|p| v.array(p)),
+        ExprKind::Number(_)
+        | ExprKind::Float(_)
+        | ExprKind::Bool(_)
+        | ExprKind::String(_)
+        | ExprKind::Variable(_)
+        | ExprKind::NamedPattern(_) => { },
+    }
+}
+
+pub fn walk_ty<V : Visit + ?Sized>(v : &mut V, t : &Type) {
+    match &t.kind {
+        TypeKind::Array(t) => v.ty(t),
+        TypeKind::Fun { input, output } => {
+            input.iter().for_each(|t| v.ty(t));
+            v.ty(output);
+        },
+        TypeKind::Index { params, .. } | TypeKind::Tuple(params) => params.iter().for_each(|t| v.ty(t)),
+        TypeKind::Anon(rows) => rows.iter().for_each(|(_, t)| v.ty(t)),
+        TypeKind::Generic(_) | TypeKind::Concrete(_) => { },
+    }
+}
+
+pub fn walk_standard<V : Visit + ?Sized>(v : &mut V, pattern : &StandardPattern) {
+    match &pattern.kind {
+        StandardPatternKind::Cons { params, .. } => params.iter().for_each(|p| v.standard(p)),
+        StandardPatternKind::Record { fields, .. } => fields.iter().for_each(|(_, p)| v.standard(p)),
+        StandardPatternKind::Tuple(items) => items.iter().for_each(|p| v.standard(p)),
+        StandardPatternKind::At { pattern, .. } => v.standard(pattern),
+        StandardPatternKind::If { pattern, predicate } => {
+            v.standard(pattern);
+            v.expr(predicate);
+        },
+        StandardPatternKind::StandardArray(array) => standard_array_items(array).for_each(|p| v.standard(p)),
+        StandardPatternKind::Or(alternatives) => alternatives.iter().for_each(|p| v.standard(p)),
+        StandardPatternKind::Number(_)
+        | StandardPatternKind::Range { .. }
+        | StandardPatternKind::Bool(_)
+        | StandardPatternKind::String(_)
+        | StandardPatternKind::Variable(_)
+        | StandardPatternKind::Wildcard => { },
+    }
+}
+
+pub fn walk_array<V : Visit + ?Sized>(v : &mut V, pattern : &ArrayPattern) {
+    match &pattern.kind {
+        ArrayPatternKind::Cons { params, .. } => params.iter().for_each(|p| v.array(p)),
+        ArrayPatternKind::Record { fields, .. } => fields.iter().for_each(|(_, p)| v.array(p)),
+        ArrayPatternKind::Tuple(items) => items.iter().for_each(|p| v.array(p)),
+        ArrayPatternKind::At { pattern, .. } => v.array(pattern),
+        ArrayPatternKind::If { pattern, predicate } => {
+            v.array(pattern);
+            v.expr(predicate);
+        },
+        ArrayPatternKind::WildcardN(e) => v.expr(e),
+        ArrayPatternKind::Quantified { pattern, quantifier } => {
+            v.array(pattern);
+            quantifier_exprs(quantifier).for_each(|e| v.expr(e));
+        },
+        ArrayPatternKind::StandardArray(array) => standard_array_items(array).for_each(|p| v.array(p)),
+        ArrayPatternKind::Or(alternatives) => alternatives.iter().for_each(|p| v.array(p)),
+        ArrayPatternKind::Number(_)
+        | ArrayPatternKind::Range { .. }
+        | ArrayPatternKind::Bool(_)
+        | ArrayPatternKind::String(_)
+        | ArrayPatternKind::Variable(_)
+        | ArrayPatternKind::Wildcard
+        | ArrayPatternKind::WildcardZeroOrMore
+        | ArrayPatternKind::Named(_) => { },
+    }
+}
+
+pub fn walk_path<V : Visit + ?Sized>(v : &mut V, pattern : &PathPattern) {
+    match &pattern.kind {
+        PathPatternKind::Cons { params, .. } => params.iter().for_each(|p| v.path(p)),
+        PathPatternKind::Record { fields, .. } => fields.iter().for_each(|(_, p)| v.path(p)),
+        PathPatternKind::Tuple(items) => items.iter().for_each(|p| v.path(p)),
+        PathPatternKind::At { pattern, .. } => v.path(pattern),
+        PathPatternKind::If { pattern, predicate } => {
+            v.path(pattern);
+            v.expr(predicate);
+        },
+        PathPatternKind::StandardArray(array) => standard_array_items(array).for_each(|p| v.path(p)),
+        PathPatternKind::Or(alternatives) => alternatives.iter().for_each(|p| v.path(p)),
+        PathPatternKind::Number(_)
+        | PathPatternKind::Range { .. }
+        | PathPatternKind::Bool(_)
+        | PathPatternKind::String(_)
+        | PathPatternKind::Variable(_)
+        | PathPatternKind::Wildcard
+        | PathPatternKind::Next(_)
+        | PathPatternKind::And { .. }
+        | PathPatternKind::NextAnd { .. }
+        | PathPatternKind::Named(_) => { },
+    }
+}
+
+pub fn walk_expr_mut<V : VisitMut + ?Sized>(v : &mut V, expr : &mut Expr) -> Result<(), V::Error> {
+    match &mut expr.kind {
+        ExprKind::Let { pattern, t, value, expr } => {
+            v.expr(value)?;
+            v.standard(pattern)?;
+            if let Some(t) = t {
+                v.ty(t)?;
+            }
+            v.expr(expr)?;
+        },
+        ExprKind::Cons { params: items, .. } | ExprKind::Tuple(items) | ExprKind::Array(items) => {
+            for e in items {
+                v.expr(e)?;
+            }
+        },
+        ExprKind::Record { fields, .. } => {
+            for (_, e) in fields {
+                v.expr(e)?;
+            }
+        },
+        ExprKind::FieldAccess { expr, .. } => v.expr(expr)?,
+        ExprKind::LetRec { bindings, expr } => {
+            for binding in bindings {
+                if let Some(t) = &mut binding.t {
+                    v.ty(t)?;
+                }
+                v.expr(&mut binding.value)?;
+            }
+            v.expr(expr)?;
+        },
+        ExprKind::Lambda { params, return_type, expr } => {
+            for param in params {
+                v.standard(&mut param.pattern)?;
+                if let Some(t) = &mut param.t {
+                    v.ty(t)?;
+                }
+            }
+            if let Some(t) = return_type {
+                v.ty(t)?;
+            }
+            v.expr(expr)?;
+        },
+        ExprKind::Match { expr, cases } => {
+            v.expr(expr)?;
+            for case in cases {
+                v.standard(&mut case.pattern)?;
+                v.expr(&mut case.expr)?;
+            }
+        },
+        ExprKind::If { condition, then_expr, else_expr } => {
+            v.expr(condition)?;
+            v.expr(then_expr)?;
+            v.expr(else_expr)?;
+        },
+        ExprKind::FunCall { fun_expr, params } => {
+            v.expr(fun_expr)?;
+            for e in params {
+                v.expr(e)?;
+            }
+        },
+        ExprKind::PathPattern(ps) => {
+            for p in ps {
+                v.path(p)?;
+            }
+        },
+        ExprKind::ArrayPattern(ps) => {
+            for p in ps {
+                v.array(p)?;
+            }
+        },
+        ExprKind::Number(_)
+        | ExprKind::Float(_)
+        | ExprKind::Bool(_)
+        | ExprKind::String(_)
+        | ExprKind::Variable(_)
+        | ExprKind::NamedPattern(_) => { },
+    }
+    Ok(())
+}
+
+pub fn walk_ty_mut<V : VisitMut + ?Sized>(v : &mut V, t : &mut Type) -> Result<(), V::Error> {
+    match &mut t.kind {
+        TypeKind::Array(t) => v.ty(t)?,
+        TypeKind::Fun { input, output } => {
+            for t in input {
+                v.ty(t)?;
+            }
+            v.ty(output)?;
+        },
+        TypeKind::Index { params, .. } | TypeKind::Tuple(params) => {
+            for t in params {
+                v.ty(t)?;
+            }
+        },
+        TypeKind::Anon(rows) => {
+            for (_, t) in rows {
+                v.ty(t)?;
+            }
+        },
+        TypeKind::Generic(_) | TypeKind::Concrete(_) => { },
+    }
+    Ok(())
+}
+
+pub fn walk_standard_mut<V : VisitMut + ?Sized>(v : &mut V, pattern : &mut StandardPattern) -> Result<(), V::Error> {
+    match &mut pattern.kind {
+        StandardPatternKind::Cons { params: items, .. } | StandardPatternKind::Tuple(items) | StandardPatternKind::Or(items) => {
+            for p in items {
+                v.standard(p)?;
+            }
+        },
+        StandardPatternKind::Record { fields, .. } => {
+            for (_, p) in fields {
+                v.standard(p)?;
+            }
+        },
+        StandardPatternKind::At { pattern, .. } => v.standard(pattern)?,
+        StandardPatternKind::If { pattern, predicate } => {
+            v.standard(pattern)?;
+            v.expr(predicate)?;
+        },
+        StandardPatternKind::StandardArray(array) => {
+            for p in standard_array_items_mut(array) {
+                v.standard(p)?;
+            }
+        },
+        StandardPatternKind::Number(_)
+        | StandardPatternKind::Range { .. }
+        | StandardPatternKind::Bool(_)
+        | StandardPatternKind::String(_)
+        | StandardPatternKind::Variable(_)
+        | StandardPatternKind::Wildcard => { },
+    }
+    Ok(())
+}
+
+pub fn walk_array_mut<V : VisitMut + ?Sized>(v : &mut V, pattern : &mut ArrayPattern) -> Result<(), V::Error> {
+    match &mut pattern.kind {
+        ArrayPatternKind::Cons { params: items, .. } | ArrayPatternKind::Tuple(items) | ArrayPatternKind::Or(items) => {
+            for p in items {
+                v.array(p)?;
+            }
+        },
+        ArrayPatternKind::Record { fields, .. } => {
+            for (_, p) in fields {
+                v.array(p)?;
+            }
+        },
+        ArrayPatternKind::At { pattern, .. } => v.array(pattern)?,
+        ArrayPatternKind::If { pattern, predicate } => {
+            v.array(pattern)?;
+            v.expr(predicate)?;
+        },
+        ArrayPatternKind::WildcardN(e) => v.expr(e)?,
+        ArrayPatternKind::Quantified { pattern, quantifier } => {
+            v.array(pattern)?;
+            for e in quantifier_exprs_mut(quantifier) {
+                v.expr(e)?;
+            }
+        },
+        ArrayPatternKind::StandardArray(array) => {
+            for p in standard_array_items_mut(array) {
+                v.array(p)?;
+            }
+        },
+        ArrayPatternKind::Number(_)
+        | ArrayPatternKind::Range { .. }
+        | ArrayPatternKind::Bool(_)
+        | ArrayPatternKind::String(_)
+        | ArrayPatternKind::Variable(_)
+        | ArrayPatternKind::Wildcard
+        | ArrayPatternKind::WildcardZeroOrMore
+        | ArrayPatternKind::Named(_) => { },
+    }
+    Ok(())
+}
+
+pub fn walk_path_mut<V : VisitMut + ?Sized>(v : &mut V, pattern : &mut PathPattern) -> Result<(), V::Error> {
+    match &mut pattern.kind {
+        PathPatternKind::Cons { params: items, .. } | PathPatternKind::Tuple(items) | PathPatternKind::Or(items) => {
+            for p in items {
+                v.path(p)?;
+            }
+        },
+        PathPatternKind::Record { fields, .. } => {
+            for (_, p) in fields {
+                v.path(p)?;
+            }
+        },
+        PathPatternKind::At { pattern, .. } => v.path(pattern)?,
+        PathPatternKind::If { pattern, predicate } => {
+            v.path(pattern)?;
+            v.expr(predicate)?;
+        },
+        PathPatternKind::StandardArray(array) => {
+            for p in standard_array_items_mut(array) {
+                v.path(p)?;
+            }
+        },
+        PathPatternKind::Number(_)
+        | PathPatternKind::Range { .. }
+        | PathPatternKind::Bool(_)
+        | PathPatternKind::String(_)
+        | PathPatternKind::Variable(_)
+        | PathPatternKind::Wildcard
+        | PathPatternKind::Next(_)
+        | PathPatternKind::And { .. }
+        | PathPatternKind::NextAnd { .. }
+        | PathPatternKind::Named(_) => { },
+    }
+    Ok(())
+}
+
+pub fn standard_array_items<P>(array : &StandardArrayPattern<P>) -> impl Iterator<Item = &P> {
+    let (items, rest) = match array {
+        StandardArrayPattern::Empty => (&[][..], None),
+        StandardArrayPattern::Array { items, rest } => (&items[..], rest.as_deref()),
+    };
+    items.iter().chain(rest)
+}
+
+pub fn standard_array_items_mut<P>(array : &mut StandardArrayPattern<P>) -> impl Iterator<Item = &mut P> {
+    let (items, rest) = match array {
+        StandardArrayPattern::Empty => (&mut [][..], None),
+        StandardArrayPattern::Array { items, rest } => (&mut items[..], rest.as_deref_mut()),
+    };
+    items.iter_mut().chain(rest)
+}
+
+// The expressions that count how many times the quantified pattern repeats
+pub fn quantifier_exprs(quantifier : &Quantifier) -> impl Iterator<Item = &Expr> {
+    let (min, max) = match quantifier {
+        Quantifier::ZeroOrMore | Quantifier::OneOrMore | Quantifier::ZeroOrOne => (None, None),
+        Quantifier::Exactly(e) => (Some(e), None),
+        Quantifier::Range { min, max } => (min.as_ref(), max.as_ref()),
+    };
+    min.into_iter().chain(max).map(|e| &**e)
+}
+
+pub fn quantifier_exprs_mut(quantifier : &mut Quantifier) -> impl Iterator<Item = &mut Expr> {
+    let (min, max) = match quantifier {
+        Quantifier::ZeroOrMore | Quantifier::OneOrMore | Quantifier::ZeroOrOne => (None, None),
+        Quantifier::Exactly(e) => (Some(e), None),
+        Quantifier::Range { min, max } => (min.as_mut(), max.as_mut()),
+    };
+    min.into_iter().chain(max).map(|e| &mut **e)
+}